@@ -0,0 +1,55 @@
+// Débit de l'entrelacement stéréo et du gain, en scalaire puis avec chaque jeu d'instructions
+// disponible: cargo bench --bench sample_simd
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+#[path = "../src/sample_simd.rs"]
+mod sample_simd;
+
+use sample_simd::SimdLevel;
+
+// Une minute de stéréo à 48 kHz
+const FRAMES: usize = 48_000 * 60;
+const MEASURE_TIME: Duration = Duration::from_secs(1);
+
+// Millions d'échantillons traités par seconde, sur au moins MEASURE_TIME
+fn throughput(samples_per_run: usize, mut run: impl FnMut()) -> f64 {
+    run();
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < MEASURE_TIME {
+        run();
+        runs += 1;
+    }
+    (samples_per_run * runs) as f64 / start.elapsed().as_secs_f64() / 1e6
+}
+
+fn main() {
+    let left: Vec<f32> = (0..FRAMES).map(|i| (i as f32 * 0.01).sin()).collect();
+    let right: Vec<f32> = (0..FRAMES).map(|i| (i as f32 * 0.013).cos()).collect();
+    let mut output = Vec::with_capacity(FRAMES * 2);
+    let mut samples: Vec<f32> = left.iter().chain(&right).copied().collect();
+
+    println!("Détecté: {:?}", SimdLevel::detect());
+    let mut scalar = None;
+    for level in SimdLevel::available() {
+        let interleave = throughput(FRAMES * 2, || {
+            output.clear();
+            sample_simd::interleave(level, &[&left, &right], &mut output);
+            black_box(&output);
+        });
+        let gain = throughput(samples.len(), || {
+            sample_simd::apply_gain(level, &mut samples, black_box(1.0));
+            black_box(&samples);
+        });
+        let (base_interleave, base_gain) = *scalar.get_or_insert((interleave, gain));
+        println!(
+            "{:<6} entrelacement {:>8.1} M éch./s (x{:.2})   gain {:>8.1} M éch./s (x{:.2})",
+            format!("{:?}", level),
+            interleave,
+            interleave / base_interleave,
+            gain,
+            gain / base_gain
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use ffmpeg_next as ffmpeg;
+use ffmpeg::filter::Graph;
+use ffmpeg::format::sample::Type as SampleType;
+use ffmpeg::format::Sample;
+use ffmpeg::ChannelLayout;
+
+// Chaîne de filtres audio ffmpeg (--af), entre le décodeur et le rééchantillonneur.
+// La sortie est directement au format de la carte son: f32 entrelacé, débit et canaux de sortie
+pub struct AudioFilter {
+    // Chaîne au format de ffmpeg -af, ex: "volume=2,highpass=f=200"
+    spec: String,
+    output_rate: u32,
+    output_channels: u16,
+    graph: Option<Graph>,
+    // Format, disposition et débit des frames pour lesquels le graphe a été construit
+    input: Option<(Sample, ChannelLayout, u32)>,
+    finished: bool,
+}
+
+impl AudioFilter {
+    // La chaîne est vérifiée tout de suite avec les paramètres du décodeur, pour qu'une faute de frappe
+    // soit signalée au démarrage plutôt qu'à la première frame
+    pub fn new(
+        spec: &str,
+        input: (Sample, ChannelLayout, u32),
+        output_rate: u32,
+        output_channels: u16,
+    ) -> Result<Self> {
+        let mut filter = Self {
+            spec: spec.to_string(),
+            output_rate,
+            output_channels,
+            graph: None,
+            input: None,
+            finished: false,
+        };
+        let graph = filter
+            .build(input)
+            .with_context(|| format!("Chaîne de filtres audio invalide: {}", spec))?;
+        println!("Filtres audio: {}", spec);
+        filter.graph = Some(graph);
+        filter.input = Some(input);
+        Ok(filter)
+    }
+
+    // Le graphe est reconstruit si le format du flux change en cours de lecture
+    pub fn push(&mut self, frame: &ffmpeg::frame::Audio) -> Result<()> {
+        let mut layout = frame.channel_layout();
+        if layout.is_empty() {
+            layout = ChannelLayout::default(i32::from(frame.channels()));
+        }
+        let input = (frame.format(), layout, frame.rate());
+        if self.input != Some(input) {
+            self.graph = Some(self.build(input)?);
+            self.input = Some(input);
+            self.finished = false;
+        }
+        if let Some(ref mut graph) = self.graph {
+            graph.get("in").context("Entrée du filtre introuvable")?.source().add(frame)?;
+        }
+        Ok(())
+    }
+
+    // Fin du flux: le filtre rend ses derniers échantillons
+    pub fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        if let Some(ref mut graph) = self.graph {
+            graph.get("in").context("Entrée du filtre introuvable")?.source().flush()?;
+        }
+        Ok(())
+    }
+
+    // Frame filtrée suivante, false s'il faut d'abord en pousser une autre
+    pub fn pull(&mut self, frame: &mut ffmpeg::frame::Audio) -> Result<bool> {
+        let Some(ref mut graph) = self.graph else {
+            return Ok(false);
+        };
+        match graph.get("out").context("Sortie du filtre introuvable")?.sink().frame(frame) {
+            Ok(()) => Ok(true),
+            Err(ffmpeg::Error::Other { errno: ffmpeg::error::EAGAIN }) | Err(ffmpeg::Error::Eof) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn build(&self, (format, layout, rate): (Sample, ChannelLayout, u32)) -> Result<Graph> {
+        let args = format!(
+            "time_base=1/{}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+            rate,
+            rate,
+            format.name(),
+            layout.bits()
+        );
+
+        let mut graph = Graph::new();
+        graph.add(&ffmpeg::filter::find("abuffer").context("Filtre abuffer indisponible")?, "in", &args)?;
+        let mut output = graph.add(
+            &ffmpeg::filter::find("abuffersink").context("Filtre abuffersink indisponible")?,
+            "out",
+            "",
+        )?;
+        // ffmpeg insère lui-même la conversion vers le format de sortie en fin de chaîne
+        output.set_sample_format(Sample::F32(SampleType::Packed));
+        output.set_sample_rate(self.output_rate);
+        output.set_channel_layout(ChannelLayout::default(i32::from(self.output_channels)));
+        graph.output("in", 0)?.input("out", 0)?.parse(&self.spec)?;
+        graph.validate()?;
+        Ok(graph)
+    }
+}
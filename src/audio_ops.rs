@@ -0,0 +1,408 @@
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+use ffmpeg::format::sample::Type as SampleType;
+use ffmpeg::format::Sample;
+use ffmpeg::software::resampling::context::Context as ResamplingContext;
+use ffmpeg::ChannelLayout;
+
+use crate::audio_filter::AudioFilter;
+use crate::sample_simd::{self, SimdLevel};
+
+// Correction de dérive: écart maximal appliqué au ratio, et gain par rapport à l'écart de remplissage
+const MAX_DRIFT_PPM: f64 = 50.0;
+const DRIFT_GAIN_PPM: f64 = 200.0;
+// Nombre de mesures moyennées avant chaque ajustement
+const DRIFT_WINDOW: usize = 10;
+
+pub struct SampleConverter {
+    level: SimdLevel,
+}
+
+impl Default for SampleConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SampleConverter {
+    pub fn new() -> Self {
+        Self { level: SimdLevel::detect() }
+    }
+
+    pub fn level(&self) -> SimdLevel {
+        self.level
+    }
+
+    // Convertit une frame décodée en échantillons f32 entrelacés, gain appliqué
+    pub fn convert(&self, frame: &ffmpeg::frame::Audio, gain: f32, output: &mut Vec<f32>) -> Result<()> {
+        let channels = frame.channels() as usize;
+        let samples = frame.samples();
+        output.clear();
+
+        match frame.format() {
+            Sample::F32(SampleType::Packed) => {
+                // plane() ne couvre qu'un canal pour un format entrelacé, on relit les octets bruts
+                let data = &frame.data(0)[..samples * channels * 4];
+                output.extend(
+                    data.chunks_exact(4)
+                        .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]])),
+                );
+            }
+            Sample::F32(SampleType::Planar) => {
+                let planes: Vec<&[f32]> = (0..channels).map(|c| frame.plane::<f32>(c)).collect();
+                self.interleave(&planes, output);
+            }
+            format => anyhow::bail!("Format audio non supporté: {:?}", format),
+        }
+
+        if gain != 1.0 {
+            self.apply_gain(output, gain);
+        }
+        Ok(())
+    }
+
+    pub fn interleave(&self, planes: &[&[f32]], output: &mut Vec<f32>) {
+        sample_simd::interleave(self.level, planes, output);
+    }
+
+    pub fn apply_gain(&self, samples: &mut [f32], gain: f32) {
+        sample_simd::apply_gain(self.level, samples, gain);
+    }
+}
+
+// Paramètres de l'audio décodé, qui peuvent changer en cours de flux
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct InputFormat {
+    format: Sample,
+    layout: ChannelLayout,
+    rate: u32,
+}
+
+impl InputFormat {
+    fn new(format: Sample, mut layout: ChannelLayout, channels: u16, rate: u32) -> Self {
+        // Certains décodeurs ne renseignent pas la disposition des canaux
+        if layout.is_empty() {
+            layout = ChannelLayout::default(i32::from(channels));
+        }
+        Self { format, layout, rate }
+    }
+
+    fn of_frame(frame: &ffmpeg::frame::Audio) -> Self {
+        Self::new(frame.format(), frame.channel_layout(), frame.channels(), frame.rate())
+    }
+}
+
+// Adapte l'audio décodé aux paramètres réellement obtenus du périphérique SDL
+pub struct AudioResampler {
+    // Absent quand le décodeur produit déjà du f32 au bon débit et au bon nombre de canaux
+    context: Option<ResamplingContext>,
+    input: InputFormat,
+    output_rate: u32,
+    output_channels: u16,
+    // Garde un contexte même sans conversion, pour pouvoir corriger la dérive
+    drift_correction: bool,
+    // Filtres --af appliqués avant le rééchantillonnage, None pour laisser l'audio intact
+    filter: Option<AudioFilter>,
+}
+
+impl AudioResampler {
+    pub fn new(decoder: &ffmpeg::codec::decoder::Audio, output_rate: u32, output_channels: u16) -> Result<Self> {
+        let input = InputFormat::new(decoder.format(), decoder.channel_layout(), decoder.channels(), decoder.rate());
+        Self::with_input(input, output_rate, output_channels)
+    }
+
+    fn with_input(input: InputFormat, output_rate: u32, output_channels: u16) -> Result<Self> {
+        Ok(Self {
+            context: build_context(input, output_rate, output_channels, false)?,
+            input,
+            output_rate,
+            output_channels,
+            drift_correction: false,
+            filter: None,
+        })
+    }
+
+    // Intercale une chaîne de filtres audio entre le décodeur et le rééchantillonnage
+    pub fn set_filter(&mut self, spec: &str) -> Result<()> {
+        let input = (self.input.format, self.input.layout, self.input.rate);
+        let filter = AudioFilter::new(spec, input, self.output_rate, self.output_channels)?;
+        // La chaîne rend déjà le format de sortie: le contexte ne sert plus qu'à corriger la dérive
+        self.input = InputFormat::new(
+            Sample::F32(SampleType::Packed),
+            ChannelLayout::default(i32::from(self.output_channels)),
+            self.output_channels,
+            self.output_rate,
+        );
+        self.context = build_context(self.input, self.output_rate, self.output_channels, self.drift_correction)?;
+        self.filter = Some(filter);
+        Ok(())
+    }
+
+    pub fn enable_drift_correction(&mut self) -> Result<()> {
+        self.drift_correction = true;
+        if self.context.is_none() {
+            self.context = build_context(self.input, self.output_rate, self.output_channels, true)?;
+        }
+        Ok(())
+    }
+
+    // Étire (ppm > 0) ou resserre (ppm < 0) légèrement la sortie, sur une fenêtre d'un million d'échantillons
+    pub fn set_compensation(&mut self, ppm: i32) -> Result<()> {
+        if let Some(ref mut context) = self.context {
+            let ret = unsafe { ffmpeg::ffi::swr_set_compensation(context.as_mut_ptr(), ppm, 1_000_000) };
+            if ret < 0 {
+                return Err(ffmpeg::Error::from(ret).into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    pub fn output_channels(&self) -> u16 {
+        self.output_channels
+    }
+
+    // Convertit une frame décodée en f32 entrelacé au format de sortie, gain appliqué
+    pub fn process(
+        &mut self,
+        converter: &SampleConverter,
+        frame: &ffmpeg::frame::Audio,
+        gain: f32,
+        output: &mut Vec<f32>,
+    ) -> Result<()> {
+        match self.filter {
+            Some(ref mut filter) => filter.push(frame)?,
+            None => return self.resample(converter, frame, gain, output),
+        }
+        output.clear();
+        self.resample_filtered(converter, gain, output)
+    }
+
+    // Une frame poussée dans les filtres peut en rendre zéro, une ou plusieurs
+    fn resample_filtered(&mut self, converter: &SampleConverter, gain: f32, output: &mut Vec<f32>) -> Result<()> {
+        let mut filtered = ffmpeg::frame::Audio::empty();
+        let mut samples = Vec::new();
+        while let Some(ref mut filter) = self.filter {
+            if !filter.pull(&mut filtered)? {
+                break;
+            }
+            self.resample(converter, &filtered, gain, &mut samples)?;
+            output.extend_from_slice(&samples);
+        }
+        Ok(())
+    }
+
+    fn resample(
+        &mut self,
+        converter: &SampleConverter,
+        frame: &ffmpeg::frame::Audio,
+        gain: f32,
+        output: &mut Vec<f32>,
+    ) -> Result<()> {
+        self.update_input(InputFormat::of_frame(frame))?;
+        let context = match self.context {
+            Some(ref mut context) => context,
+            None => return converter.convert(frame, gain, output),
+        };
+
+        // run() n'alloue que frame.samples() en sortie, insuffisant lors d'un suréchantillonnage
+        let input_rate = context.input().rate.max(1) as usize;
+        let output_rate = context.output().rate as usize;
+        let pending = context.delay().map(|delay| delay.output.max(0) as usize).unwrap_or(0);
+        let capacity = frame.samples() * output_rate / input_rate + pending + 32;
+
+        let mut resampled = ffmpeg::frame::Audio::new(
+            Sample::F32(SampleType::Packed),
+            capacity,
+            context.output().channel_layout,
+        );
+        context.run(frame, &mut resampled)?;
+        converter.convert(&resampled, gain, output)
+    }
+
+    // Changement de débit, de format ou de canaux en cours de lecture: le contexte est reconstruit
+    // pour que la sortie reste identique. Renvoie true s'il l'a été
+    fn update_input(&mut self, input: InputFormat) -> Result<bool> {
+        if input == self.input {
+            return Ok(false);
+        }
+        println!(
+            "Changement de format audio: {:?} {} Hz -> {:?} {} Hz",
+            self.input.format, self.input.rate, input.format, input.rate
+        );
+        self.context = build_context(input, self.output_rate, self.output_channels, self.drift_correction)?;
+        self.input = input;
+        Ok(true)
+    }
+
+    // Récupère les échantillons encore retenus par les filtres et le rééchantillonneur en fin de flux
+    pub fn drain(&mut self, converter: &SampleConverter, gain: f32, output: &mut Vec<f32>) -> Result<()> {
+        output.clear();
+        if let Some(ref mut filter) = self.filter {
+            filter.finish()?;
+            self.resample_filtered(converter, gain, output)?;
+        }
+        let mut samples = Vec::new();
+        self.flush_context(converter, gain, &mut samples)?;
+        output.extend_from_slice(&samples);
+        Ok(())
+    }
+
+    fn flush_context(&mut self, converter: &SampleConverter, gain: f32, output: &mut Vec<f32>) -> Result<()> {
+        output.clear();
+        let context = match self.context {
+            Some(ref mut context) => context,
+            None => return Ok(()),
+        };
+
+        let pending = context.delay().map(|delay| delay.output.max(0) as usize).unwrap_or(0);
+        if pending == 0 {
+            return Ok(());
+        }
+        let mut resampled = ffmpeg::frame::Audio::new(
+            Sample::F32(SampleType::Packed),
+            pending + 32,
+            context.output().channel_layout,
+        );
+        context.flush(&mut resampled)?;
+        converter.convert(&resampled, gain, output)
+    }
+}
+
+fn build_context(
+    input: InputFormat,
+    output_rate: u32,
+    output_channels: u16,
+    force: bool,
+) -> Result<Option<ResamplingContext>> {
+    let native = !force
+        && matches!(input.format, Sample::F32(_))
+        && input.rate == output_rate
+        && input.layout.channels() == i32::from(output_channels);
+    if native {
+        return Ok(None);
+    }
+
+    let context = ResamplingContext::get(
+        input.format,
+        input.layout,
+        input.rate,
+        Sample::F32(SampleType::Packed),
+        ChannelLayout::default(i32::from(output_channels)),
+        output_rate,
+    )?;
+    println!(
+        "Rééchantillonnage audio: {:?} {} Hz {} canaux -> f32 {} Hz {} canaux",
+        input.format,
+        input.rate,
+        input.layout.channels(),
+        output_rate,
+        output_channels
+    );
+    Ok(Some(context))
+}
+
+// Asservit le remplissage du buffer SDL autour de son niveau de croisière, pour compenser
+// l'écart entre l'horloge de la carte son et celle du décodage sur une longue lecture
+pub struct DriftCorrector {
+    // Remplissage de référence, mesuré sur la première fenêtre après (re)démarrage
+    target: Option<f64>,
+    readings: Vec<usize>,
+    ppm: i32,
+}
+
+impl Default for DriftCorrector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DriftCorrector {
+    pub fn new() -> Self {
+        Self { target: None, readings: Vec::with_capacity(DRIFT_WINDOW), ppm: 0 }
+    }
+
+    // Enregistre un remplissage, retourne la compensation à appliquer quand elle change
+    pub fn observe(&mut self, fill: usize) -> Option<i32> {
+        // Buffer vidé (seek, sous-alimentation): la référence n'a plus de sens
+        if fill == 0 {
+            self.target = None;
+            self.readings.clear();
+            return self.update(0);
+        }
+
+        self.readings.push(fill);
+        if self.readings.len() < DRIFT_WINDOW {
+            return None;
+        }
+        let mean = self.readings.iter().sum::<usize>() as f64 / self.readings.len() as f64;
+        self.readings.clear();
+
+        let target = *self.target.get_or_insert(mean);
+        // Buffer qui gonfle: la carte son consomme trop lentement, on produit moins d'échantillons
+        let error = (mean - target) / target;
+        let ppm = (-error * DRIFT_GAIN_PPM).clamp(-MAX_DRIFT_PPM, MAX_DRIFT_PPM).round() as i32;
+        self.update(ppm)
+    }
+
+    fn update(&mut self, ppm: i32) -> Option<i32> {
+        if ppm == self.ppm {
+            return None;
+        }
+        self.ppm = ppm;
+        Some(ppm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(sample: Sample, layout: ChannelLayout, rate: u32) -> InputFormat {
+        InputFormat::new(sample, layout, layout.channels() as u16, rate)
+    }
+
+    #[test]
+    fn input_format_defaults_missing_layout() {
+        let input = InputFormat::new(Sample::F32(SampleType::Planar), ChannelLayout(0), 2, 48000);
+        assert_eq!(input.layout, ChannelLayout::STEREO);
+    }
+
+    #[test]
+    fn mid_stream_format_change_rebuilds_once() {
+        let planar = Sample::F32(SampleType::Planar);
+        let native = format(planar, ChannelLayout::STEREO, 48000);
+        let mut resampler = AudioResampler::with_input(native, 48000, 2).unwrap();
+        assert!(resampler.context.is_none());
+        assert!(!resampler.update_input(native).unwrap());
+
+        // Débit, puis disposition des canaux qui changent: une reconstruction à chaque fois, pas à
+        // chaque frame
+        let resampled = format(planar, ChannelLayout::STEREO, 44100);
+        assert!(resampler.update_input(resampled).unwrap());
+        assert!(resampler.context.is_some());
+        assert!(!resampler.update_input(resampled).unwrap());
+
+        let surround = format(planar, ChannelLayout::_5POINT1, 44100);
+        assert!(resampler.update_input(surround).unwrap());
+        assert!(!resampler.update_input(surround).unwrap());
+        assert_eq!(resampler.input, surround);
+
+        // Retour au format natif: le contexte n'est plus nécessaire
+        assert!(resampler.update_input(native).unwrap());
+        assert!(resampler.context.is_none());
+    }
+
+    #[test]
+    fn drift_correction_keeps_context_across_changes() {
+        let native = format(Sample::F32(SampleType::Packed), ChannelLayout::STEREO, 48000);
+        let mut resampler = AudioResampler::with_input(native, 48000, 2).unwrap();
+        resampler.enable_drift_correction().unwrap();
+        assert!(resampler.update_input(format(Sample::I16(SampleType::Packed), ChannelLayout::STEREO, 48000)).unwrap());
+        assert!(resampler.update_input(native).unwrap());
+        assert!(resampler.context.is_some());
+    }
+}
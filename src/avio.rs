@@ -0,0 +1,189 @@
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+use ffmpeg::ffi;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::raw::{c_int, c_void};
+
+// Taille du buffer de lecture confié à ffmpeg
+const BUFFER_SIZE: usize = 64 * 1024;
+// Valeurs de whence, comme lseek
+const SEEK_SET: c_int = 0;
+const SEEK_CUR: c_int = 1;
+const SEEK_END: c_int = 2;
+
+trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+// Contexte AVIO qui lit dans un lecteur Rust (octets en mémoire, déchiffrés...) au lieu d'un fichier.
+// Il doit survivre au contexte de format qui l'utilise: ffmpeg ne libère jamais un pb fourni par l'appelant
+pub struct ReaderIo {
+    context: *mut ffi::AVIOContext,
+    reader: *mut Box<dyn ReadSeek>,
+}
+
+// Le lecteur est Send et seul le contexte de format qui possède ce pb y accède
+unsafe impl Send for ReaderIo {}
+
+impl ReaderIo {
+    pub fn new<R: Read + Seek + Send + 'static>(mut reader: R) -> Result<Self> {
+        // Un lecteur dont la position est inaccessible (pipe, flux) est lu en séquence: sans callback
+        // de recherche, ffmpeg marque le contexte comme non positionnable
+        let seekable = can_seek(&mut reader);
+        let boxed: Box<dyn ReadSeek> = Box::new(reader);
+        let reader = Box::into_raw(Box::new(boxed));
+        unsafe {
+            let buffer = ffi::av_malloc(BUFFER_SIZE) as *mut u8;
+            if buffer.is_null() {
+                drop(Box::from_raw(reader));
+                anyhow::bail!("Impossible d'allouer le buffer de lecture");
+            }
+            let context = ffi::avio_alloc_context(
+                buffer,
+                BUFFER_SIZE as c_int,
+                0,
+                reader as *mut c_void,
+                Some(read_packet),
+                None,
+                if seekable { Some(seek) } else { None },
+            );
+            if context.is_null() {
+                ffi::av_free(buffer as *mut c_void);
+                drop(Box::from_raw(reader));
+                anyhow::bail!("Impossible de créer le contexte de lecture");
+            }
+            Ok(Self { context, reader })
+        }
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut ffi::AVIOContext {
+        self.context
+    }
+}
+
+impl Drop for ReaderIo {
+    fn drop(&mut self) {
+        unsafe {
+            // ffmpeg a pu remplacer le buffer: c'est celui du contexte qui est libéré
+            ffi::av_freep(&mut (*self.context).buffer as *mut *mut u8 as *mut c_void);
+            ffi::avio_context_free(&mut self.context);
+            drop(Box::from_raw(self.reader));
+        }
+    }
+}
+
+unsafe extern "C" fn read_packet(opaque: *mut c_void, buf: *mut u8, buf_size: c_int) -> c_int {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+    let buf = std::slice::from_raw_parts_mut(buf, buf_size.max(0) as usize);
+    loop {
+        match reader.read(buf) {
+            Ok(0) => return ffi::AVERROR_EOF,
+            Ok(read) => return read as c_int,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return ffi::AVERROR(ffmpeg::error::EIO),
+        }
+    }
+}
+
+unsafe extern "C" fn seek(opaque: *mut c_void, offset: i64, whence: c_int) -> i64 {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+    // AVSEEK_SIZE demande la taille totale, sans déplacer la position
+    if whence & ffi::AVSEEK_SIZE as c_int != 0 {
+        let size = stream_size(&mut **reader);
+        return size.map_or(i64::from(ffi::AVERROR(ffmpeg::error::EIO)), |size| size as i64);
+    }
+    let position = match whence & !(ffi::AVSEEK_FORCE as c_int) {
+        SEEK_SET => SeekFrom::Start(offset.max(0) as u64),
+        SEEK_CUR => SeekFrom::Current(offset),
+        SEEK_END => SeekFrom::End(offset),
+        _ => return i64::from(ffi::AVERROR(ffmpeg::error::EINVAL)),
+    };
+    reader
+        .seek(position)
+        .map_or(i64::from(ffi::AVERROR(ffmpeg::error::EIO)), |position| position as i64)
+}
+
+// Lecteur sans position (entrée standard, socket): lu au fil de l'eau, sans rien garder en mémoire.
+// ReaderIo n'installe alors pas de callback de recherche
+pub struct Sequential<R>(pub R);
+
+impl<R: Read> Read for Sequential<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R> Seek for Sequential<R> {
+    fn seek(&mut self, _position: SeekFrom) -> io::Result<u64> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+fn can_seek<R: Seek>(reader: &mut R) -> bool {
+    reader.stream_position().is_ok()
+}
+
+fn stream_size(reader: &mut dyn ReadSeek) -> io::Result<u64> {
+    let current = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(current))?;
+    Ok(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Cursor;
+    use std::os::fd::OwnedFd;
+
+    #[test]
+    fn file_and_memory_readers_are_seekable() {
+        let mut file = File::open(file!()).unwrap();
+        assert!(can_seek(&mut file));
+        assert!(can_seek(&mut Cursor::new(vec![0u8; 16])));
+    }
+
+    // Appelle les callbacks comme le ferait ffmpeg, sur un Cursor<Vec<u8>>
+    #[test]
+    fn callbacks_read_and_seek_a_cursor() {
+        let mut reader: Box<dyn ReadSeek> = Box::new(Cursor::new((0..100u8).collect::<Vec<u8>>()));
+        let opaque = &mut reader as *mut Box<dyn ReadSeek> as *mut c_void;
+        let mut buf = [0u8; 64];
+        unsafe {
+            assert_eq!(read_packet(opaque, buf.as_mut_ptr(), 64), 64);
+            assert!(buf.iter().copied().eq(0..64));
+
+            // La demande de taille ne déplace pas la position
+            assert_eq!(seek(opaque, 0, ffi::AVSEEK_SIZE as c_int), 100);
+            assert_eq!(seek(opaque, 0, SEEK_CUR), 64);
+
+            assert_eq!(seek(opaque, -10, SEEK_END), 90);
+            assert_eq!(read_packet(opaque, buf.as_mut_ptr(), 64), 10);
+            assert!(buf[..10].iter().copied().eq(90..100));
+            assert_eq!(read_packet(opaque, buf.as_mut_ptr(), 64), ffi::AVERROR_EOF);
+
+            assert_eq!(seek(opaque, 5, SEEK_SET | ffi::AVSEEK_FORCE as c_int), 5);
+            assert_eq!(seek(opaque, 5, SEEK_CUR), 10);
+            assert_eq!(read_packet(opaque, buf.as_mut_ptr(), 4), 4);
+            assert_eq!(&buf[..4], &[10, 11, 12, 13]);
+            assert_eq!(seek(opaque, 0, 7), i64::from(ffi::AVERROR(ffmpeg::error::EINVAL)));
+        }
+    }
+
+    #[test]
+    fn sequential_reader_is_read_but_not_seekable() {
+        let mut reader = Sequential(&b"abc"[..]);
+        assert!(!can_seek(&mut reader));
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, b"abc");
+    }
+
+    #[test]
+    fn pipe_reader_is_not_seekable() {
+        // Un File sur un pipe implémente Seek, mais lseek échoue (ESPIPE)
+        let (reader, _writer) = io::pipe().unwrap();
+        let mut pipe = File::from(OwnedFd::from(reader));
+        assert!(!can_seek(&mut pipe));
+    }
+}
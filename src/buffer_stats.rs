@@ -0,0 +1,196 @@
+use anyhow::Result;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use crate::bitmap_font;
+
+const MARGIN: i32 = 8;
+const PADDING: i32 = 4;
+const FONT_SCALE: u32 = 2;
+// Espace entre deux lignes, en pixels de police
+const LINE_SPACING: u32 = 2;
+// En dessous de ce remplissage, le buffer audio est affiché en alerte
+const LOW_FILL_PERCENT: f64 = 25.0;
+
+const BACKGROUND: Color = Color::RGBA(0, 0, 0, 160);
+const TEXT: Color = Color::RGB(255, 255, 255);
+const WARNING: Color = Color::RGB(240, 200, 40);
+
+// État des tampons, écrit en continu par le callback audio (remplissage, sous-alimentations) et par
+// la boucle principale (décodeur vidéo), lu par l'affichage et par les ajustements automatiques.
+// Uniquement des atomiques: le callback audio ne doit jamais attendre un verrou
+#[derive(Default)]
+pub struct BufferStats {
+    // Échantillons en file dans le buffer audio (32 bits de poids fort) et capacité de ce buffer
+    // (poids faible), tous canaux confondus. Un seul atomique: le remplissage lu est toujours
+    // rapporté à la capacité du même passage, même pendant un agrandissement du buffer
+    audio_fill: AtomicU64,
+    // Échantillons par seconde de lecture, pour exprimer le remplissage en durée
+    audio_rate: AtomicU64,
+    // Callbacks qui ont trouvé le buffer vide en pleine lecture
+    underruns: AtomicU64,
+    // Paquets envoyés au décodeur vidéo dont la frame n'est pas encore sortie
+    video_queue: AtomicU64,
+    // Avance (positive) ou retard (négatif) de la dernière frame décodée sur son heure d'affichage,
+    // en microsecondes. i64::MIN tant qu'aucune frame n'est passée
+    decode_lead_us: AtomicI64,
+}
+
+// Copie des compteurs: le remplissage audio et sa capacité sont lus ensemble, les autres compteurs
+// un à un, chacun à jour au moment de sa lecture
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BufferSnapshot {
+    pub audio_fill_percent: Option<f64>,
+    pub audio_buffered: Option<f64>,
+    pub underruns: u64,
+    pub video_queue: u64,
+    pub decode_lead: Option<f64>,
+}
+
+impl BufferStats {
+    pub fn new() -> Self {
+        let stats = Self::default();
+        stats.decode_lead_us.store(i64::MIN, Ordering::Relaxed);
+        stats
+    }
+
+    // Appelé par le callback audio à chaque passage, et quand la capacité change
+    pub fn record_audio_fill(&self, queued: usize, capacity: usize, samples_per_second: f64) {
+        let queued = queued.min(u32::MAX as usize) as u64;
+        let capacity = capacity.min(u32::MAX as usize) as u64;
+        self.audio_fill.store(queued << 32 | capacity, Ordering::Relaxed);
+        self.audio_rate.store(samples_per_second.round() as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    pub fn record_video_queue(&self, depth: u64) {
+        self.video_queue.store(depth, Ordering::Relaxed);
+    }
+
+    pub fn record_decode_lead(&self, lead: f64) {
+        let lead_us = (lead * 1_000_000.0).round() as i64;
+        self.decode_lead_us.store(lead_us.max(i64::MIN + 1), Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> BufferSnapshot {
+        let audio_fill = self.audio_fill.load(Ordering::Relaxed);
+        let queued = (audio_fill >> 32) as f64;
+        let capacity = audio_fill & u64::from(u32::MAX);
+        let rate = self.audio_rate.load(Ordering::Relaxed);
+        let decode_lead_us = self.decode_lead_us.load(Ordering::Relaxed);
+        BufferSnapshot {
+            audio_fill_percent: (capacity > 0).then(|| queued / capacity as f64 * 100.0),
+            audio_buffered: (rate > 0).then(|| queued / rate as f64),
+            underruns: self.underruns(),
+            video_queue: self.video_queue.load(Ordering::Relaxed),
+            decode_lead: (decode_lead_us != i64::MIN).then(|| decode_lead_us as f64 / 1_000_000.0),
+        }
+    }
+}
+
+// Bloc de texte dans le coin inférieur gauche: remplissage du buffer audio, sous-alimentations,
+// paquets en attente dans le décodeur vidéo et avance du décodage. Jaune si un tampon s'épuise
+pub fn draw(canvas: &mut Canvas<Window>, stats: &BufferSnapshot) -> Result<()> {
+    let audio = match (stats.audio_fill_percent, stats.audio_buffered) {
+        (Some(fill), Some(buffered)) => format!("AUDIO {:.0}% ({:.0} MS)", fill, buffered * 1000.0),
+        _ => "AUDIO -".to_string(),
+    };
+    let lead = match stats.decode_lead {
+        Some(lead) if lead >= 0.0 => format!("AVANCE {:.1} MS", lead * 1000.0),
+        Some(lead) => format!("RETARD {:.1} MS", -lead * 1000.0),
+        None => "AVANCE -".to_string(),
+    };
+    let lines = [
+        audio,
+        format!("SOUS-ALIMENTATIONS {}", stats.underruns),
+        format!("FILE VIDEO {}", stats.video_queue),
+        lead,
+    ];
+    let warning = stats.audio_fill_percent.is_some_and(|fill| fill < LOW_FILL_PERCENT)
+        || stats.decode_lead.is_some_and(|lead| lead < 0.0);
+
+    let line_height = bitmap_font::text_height(FONT_SCALE) + LINE_SPACING * FONT_SCALE;
+    let text_width = lines
+        .iter()
+        .map(|line| bitmap_font::text_width(line, FONT_SCALE))
+        .max()
+        .unwrap_or(0);
+    let width = text_width + 2 * PADDING as u32;
+    let height = lines.len() as u32 * line_height - LINE_SPACING * FONT_SCALE + 2 * PADDING as u32;
+    let (_, canvas_height) = canvas.output_size().map_err(|e| anyhow::anyhow!(e))?;
+    let y = canvas_height as i32 - height as i32 - MARGIN;
+
+    // Même précaution que fps_overlay: la couleur courante sert à canvas.clear()
+    let previous_color = canvas.draw_color();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKGROUND);
+    canvas
+        .fill_rect(Rect::new(MARGIN, y, width, height))
+        .map_err(|e| anyhow::anyhow!(e))?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(if warning { WARNING } else { TEXT });
+    for (row, line) in lines.iter().enumerate() {
+        let line_y = y + PADDING + (row as u32 * line_height) as i32;
+        bitmap_font::draw_text(canvas, MARGIN + PADDING, line_y, FONT_SCALE, line)?;
+    }
+    canvas.set_draw_color(previous_color);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_is_empty_before_any_activity() {
+        let snapshot = BufferStats::new().snapshot();
+        assert_eq!(snapshot.audio_fill_percent, None);
+        assert_eq!(snapshot.audio_buffered, None);
+        assert_eq!(snapshot.decode_lead, None);
+        assert_eq!(snapshot.underruns, 0);
+    }
+
+    #[test]
+    fn snapshot_reflects_the_latest_values() {
+        let stats = BufferStats::new();
+        stats.record_audio_fill(24000, 32768, 96000.0);
+        stats.record_video_queue(3);
+        stats.record_decode_lead(0.0125);
+        stats.record_underrun();
+        stats.record_underrun();
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.audio_fill_percent, Some(24000.0 / 32768.0 * 100.0));
+        assert_eq!(snapshot.audio_buffered, Some(0.25));
+        assert_eq!(snapshot.video_queue, 3);
+        assert_eq!(snapshot.decode_lead, Some(0.0125));
+        assert_eq!(snapshot.underruns, 2);
+
+        // Retard: avance négative, distincte de l'absence de mesure
+        stats.record_decode_lead(-0.040);
+        assert_eq!(stats.snapshot().decode_lead, Some(-0.040));
+        stats.record_decode_lead(f64::NEG_INFINITY);
+        assert!(stats.snapshot().decode_lead.is_some());
+    }
+
+    #[test]
+    fn audio_fill_is_stored_with_its_capacity() {
+        let stats = BufferStats::new();
+        // Buffer plein au maximum d'agrandissement en 7.1, puis réduit
+        stats.record_audio_fill(65536 * 8, 65536 * 8, 384000.0);
+        assert_eq!(stats.snapshot().audio_fill_percent, Some(100.0));
+        stats.record_audio_fill(8192, 32768, 96000.0);
+        assert_eq!(stats.snapshot().audio_fill_percent, Some(25.0));
+        stats.record_audio_fill(0, 32768, 96000.0);
+        assert_eq!(stats.snapshot().audio_fill_percent, Some(0.0));
+    }
+}
@@ -0,0 +1,764 @@
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+use ffmpeg::format::Pixel;
+use ffmpeg::software::scaling::{context::Context as ScalingContext, flag::Flags};
+use ffmpeg::util::frame::video::Video;
+use crate::fps_overlay;
+use crate::frame_info::FrameInfo;
+use crate::hwaccel::{self, HwAccel};
+use crate::interpolate::InterpolationMode;
+use crate::player;
+use crate::video_filter::VideoFilter;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// En deçà, l'attente se fait en boucle active: sleep() peut dépasser de plusieurs millisecondes
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+// Plus petite largeur/hauteur vidéo acceptée
+pub const MIN_VIDEO_DIMENSION: u32 = 2;
+// Cadence supposée quand le flux n'en annonce aucune exploitable (0/0 en raw ou dans certains TS)
+const DEFAULT_FRAME_RATE: (i32, i32) = (25, 1);
+// Écart de PTS (en secondes) par rapport à la progression attendue au-delà duquel on considère
+// une discontinuité: remise à zéro de l'horloge d'un MPEG-TS, fichiers concaténés
+const PTS_DISCONTINUITY_THRESHOLD: f64 = 1.0;
+
+// Avec --direct-nv12 sur un renderer compatible, une frame NV12 déjà à la taille d'affichage part
+// telle quelle vers la texture NV12. Tout autre format ou une taille à changer passe par le scaler
+fn bypasses_scaler(direct_nv12: bool, format: Pixel, size: (u32, u32), output_size: (u32, u32)) -> bool {
+    direct_nv12 && format == Pixel::NV12 && size == output_size
+}
+
+pub struct Decoder {
+    pub decoder: ffmpeg::codec::decoder::Video,
+    pub stream_index: usize,
+    // API matérielle effectivement active, None en décodage logiciel
+    pub hw_accel: HwAccel,
+    pub scaler: ScalingContext,
+    // Format d'entrée du scaler: celui des frames logicielles reçues, qui peut différer de
+    // decoder.format() en décodage matériel (format GPU, puis NV12 après transfert)
+    pub scaler_format: Pixel,
+    pub time_base: f64,
+    // Conservé en rationnel exact (ex: 24000/1001) pour ne pas dériver sur un long film
+    pub frame_rate: ffmpeg::Rational,
+    // Vitesse de lecture, 1.0 = temps réel
+    pub speed: f64,
+    // Statistiques détaillées toutes les 30 frames
+    pub log_stats: bool,
+    // Cadence d'affichage visée quand la source la dépasse (240 fps sur un écran 60 Hz), None sinon
+    pub display_rate: Option<f64>,
+    // Fraction de frame affichable accumulée: une frame source est gardée à chaque unité atteinte
+    pub display_phase: f64,
+    // Frames sautées volontairement pour suivre display_rate, distinctes des frames en retard
+    pub decimated_frames: u64,
+    pub start_time: Option<Instant>,
+    // Numéro de la prochaine frame attendue depuis start_time
+    pub target_index: u64,
+    pub frame_count: u64,
+    // Derniers intervalles entre frames affichées, pour --fps-overlay
+    pub frame_intervals: VecDeque<Duration>,
+    // PTS (en secondes) de la dernière frame décodée, pour détecter les discontinuités
+    pub last_pts_time: Option<f64>,
+    // Métadonnées de la dernière frame reçue, pour l'affichage de la touche K
+    pub last_frame_info: Option<FrameInfo>,
+    // Filtres ffmpeg appliqués avant le scaler (--vf, puis --interpolate), None sans filtre
+    pub filter: Option<VideoFilter>,
+    // Cadence produite par l'interpolation du ralenti, pour les statistiques
+    pub interpolation_rate: Option<ffmpeg::Rational>,
+    // Paquets envoyés dont la frame n'est pas encore sortie: réordonnancement des B-frames et
+    // décodage multithread. En images clés seules, les paquets ignorés par le décodeur ne comptent pas
+    pub queued_packets: u64,
+    pub keyframes_only: bool,
+    // Seek précis: les frames antérieures à cet instant sont décodées sans être affichées
+    pub seek_target: Option<f64>,
+    // --direct-nv12 sur un renderer compatible: les frames NV12 à la taille d'affichage sortent
+    // telles quelles, sans passer par le scaler
+    pub direct_nv12: bool,
+    // Taille d'affichage de la dernière frame reçue quand ses pixels ne sont pas carrés (SAR),
+    // relevée avant le scaler qui ne recopie pas le SAR. None pour des pixels carrés
+    pub anamorphic_size: Option<(u32, u32)>,
+    pub last_frame_time: Option<Instant>,
+    pub next_frame_target: Option<Instant>,
+    pub total_drift: Duration,
+}
+
+impl Decoder {
+    pub fn new(
+        decoder: ffmpeg::codec::decoder::Video,
+        stream: &ffmpeg::Stream,
+        frame_rate: Option<ffmpeg::Rational>,
+    ) -> Result<Self> {
+        let time_base = f64::from(stream.time_base());
+        let frame_rate = match frame_rate {
+            Some(frame_rate) => frame_rate,
+            None => stream_frame_rate(stream),
+        };
+
+        println!("Initialisation décodeur vidéo:");
+        println!("  Time base: {}", time_base);
+        println!("  Frame rate: {} ({:.3} fps)", frame_rate, f64::from(frame_rate));
+        println!("  Frame duration: {:?}", frame_offset(frame_rate, 1));
+
+        check_video_dimensions(decoder.width(), decoder.height())?;
+
+        let scaler = ScalingContext::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            Pixel::YUV420P,
+            decoder.width(),
+            decoder.height(),
+            Flags::BILINEAR,
+        )?;
+
+        let scaler_format = decoder.format();
+        Ok(Self {
+            decoder,
+            stream_index: stream.index(),
+            hw_accel: HwAccel::None,
+            scaler,
+            scaler_format,
+            time_base,
+            frame_rate,
+            speed: 1.0,
+            log_stats: true,
+            display_rate: None,
+            display_phase: 0.0,
+            decimated_frames: 0,
+            start_time: None,
+            target_index: 0,
+            frame_count: 0,
+            frame_intervals: VecDeque::with_capacity(fps_overlay::HISTORY_LEN),
+            last_pts_time: None,
+            last_frame_info: None,
+            filter: None,
+            interpolation_rate: None,
+            queued_packets: 0,
+            keyframes_only: false,
+            seek_target: None,
+            direct_nv12: false,
+            anamorphic_size: None,
+            last_frame_time: None,
+            next_frame_target: None,
+            total_drift: Duration::ZERO,
+        })
+    }
+
+    // Taille des images à afficher: celle du flux, ou celle produite par la chaîne de filtres
+    pub fn frame_size(&self) -> (u32, u32) {
+        match self.filter {
+            Some(ref filter) => filter.output_size(),
+            None => (self.decoder.width(), self.decoder.height()),
+        }
+    }
+
+    // Reconstruit le scaler pour produire des frames à la taille demandée
+    pub fn set_output_size(&mut self, width: u32, height: u32) -> Result<()> {
+        let output = self.scaler.output();
+        if output.width == width && output.height == height {
+            return Ok(());
+        }
+        // Fenêtre réduite: on garde la taille précédente
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        // L'entrée du scaler suit les frames reçues, qui sortent éventuellement des filtres
+        let input = self.scaler.input();
+        self.scaler = ScalingContext::get(
+            self.scaler_format,
+            input.width,
+            input.height,
+            Pixel::YUV420P,
+            width,
+            height,
+            Flags::BILINEAR,
+        )?;
+        Ok(())
+    }
+
+    pub fn describe(&self) -> String {
+        match self.hw_accel {
+            HwAccel::None => "Décodage logiciel".to_string(),
+            accel => format!("Décodage matériel ({})", accel.name()),
+        }
+    }
+
+    // Repart de zéro pour le cadencement, par exemple après un seek
+    pub fn reset_clock(&mut self) {
+        self.start_time = None;
+        self.target_index = 0;
+        self.last_frame_time = None;
+        self.next_frame_target = None;
+        self.last_pts_time = None;
+        self.display_phase = 0.0;
+    }
+
+    // Détecte un saut de PTS en avant ou en arrière par rapport à la frame précédente.
+    // Le cadencement est alors réancré sur la nouvelle frame, comme après un seek.
+    pub fn check_discontinuity(&mut self, pts_time: f64) {
+        let Some(previous) = self.last_pts_time.replace(pts_time) else {
+            return;
+        };
+        let expected = previous + frame_offset(self.frame_rate, 1).as_secs_f64();
+        if is_discontinuity(expected, pts_time) {
+            println!("Discontinuité vidéo: {:.3}s attendu, {:.3}s reçu", expected, pts_time);
+            self.reset_clock();
+            self.last_pts_time = Some(pts_time);
+        }
+    }
+
+    // Ajoute des filtres en fin de chaîne. La chaîne complète est vérifiée, et le scaler produit
+    // désormais des images à la taille de sa sortie
+    pub fn add_filter(&mut self, spec: &str, time_base: ffmpeg::Rational) -> Result<()> {
+        let spec = match self.filter {
+            Some(ref filter) => format!("{},{}", filter.spec(), spec),
+            None => spec.to_string(),
+        };
+        let filter = VideoFilter::new(&spec, time_base, self.decoder.width(), self.decoder.height())?;
+        let (width, height) = filter.output_size();
+        println!("Filtres vidéo: {} ({}x{})", spec, width, height);
+        self.filter = Some(filter);
+        self.set_output_size(width, height)
+    }
+
+    // Ralenti avec interpolation: la cadence d'affichage devient celle des images produites par le filtre
+    pub fn enable_interpolation(&mut self, mode: InterpolationMode, time_base: ffmpeg::Rational) -> Result<()> {
+        let output_rate = ffmpeg::Rational::from(f64::from(self.frame_rate) / self.speed);
+        println!(
+            "Interpolation ({:?}): {:.3} fps -> {:.3} fps pour le ralenti x{:.2}",
+            mode,
+            f64::from(self.frame_rate),
+            f64::from(output_rate),
+            self.speed
+        );
+        self.add_filter(&mode.filter_spec(output_rate), time_base)?;
+        self.frame_rate = output_rate;
+        self.interpolation_rate = Some(output_rate);
+        Ok(())
+    }
+
+    pub fn send_packet(&mut self, packet: &ffmpeg::Packet) -> Result<()> {
+        self.decoder.send_packet(packet)?;
+        if !self.keyframes_only || packet.is_key() {
+            self.queued_packets += 1;
+        }
+        Ok(())
+    }
+
+    // Après un seek: vide le décodeur et les images en attente dans les filtres
+    pub fn flush(&mut self) {
+        self.decoder.flush();
+        self.queued_packets = 0;
+        self.seek_target = None;
+        if let Some(ref mut filter) = self.filter {
+            filter.reset();
+        }
+    }
+
+    pub fn receive_frame_yuv(&mut self, frame: &mut Video) -> Result<bool> {
+        let received = if self.filter.is_some() {
+            self.receive_filtered(frame)?
+        } else {
+            self.receive_decoded(frame)?
+        };
+        if !received {
+            return Ok(false);
+        }
+        let coded_size = (frame.width(), frame.height());
+        let display_size = player::sar_display_size(coded_size.0, coded_size.1, frame.aspect_ratio());
+        self.anamorphic_size = (display_size != coded_size).then_some(display_size);
+
+        // Rendu direct: le GPU convertit le NV12 à l'affichage, la frame garde son format
+        let output = self.scaler.output();
+        if bypasses_scaler(self.direct_nv12, frame.format(), (frame.width(), frame.height()), (output.width, output.height)) {
+            let pts = presentation_pts(frame.timestamp(), frame.pts(), None);
+            frame.set_pts(pts);
+            return Ok(true);
+        }
+
+        // Une frame rapatriée du GPU arrive en général en NV12, et une chaîne de filtres peut changer
+        // le format ou la taille
+        let input = self.scaler.input();
+        let current = (self.scaler_format, input.width, input.height);
+        let received = (frame.format(), frame.width(), frame.height());
+        let (output_width, output_height) = (self.scaler.output().width, self.scaler.output().height);
+        let rebuilt = refresh_scaler(&mut self.scaler, current, received, || {
+            Ok(ScalingContext::get(
+                frame.format(),
+                frame.width(),
+                frame.height(),
+                Pixel::YUV420P,
+                output_width,
+                output_height,
+                Flags::BILINEAR,
+            )?)
+        })?;
+        if rebuilt {
+            self.scaler_format = frame.format();
+        }
+
+        let mut yuv_frame = Video::empty();
+        self.scaler.run(frame, &mut yuv_frame)?;
+        // Le scaler ne recopie pas l'horodatage de la frame décodée. Une image filtrée n'a qu'un PTS
+        yuv_frame.set_pts(presentation_pts(frame.timestamp(), frame.pts(), None));
+        frame.clone_from(&yuv_frame);
+        Ok(true)
+    }
+
+    fn receive_decoded(&mut self, frame: &mut Video) -> Result<bool> {
+        match self.decoder.receive_frame(frame) {
+            Ok(_) => {
+                self.queued_packets = self.queued_packets.saturating_sub(1);
+                hwaccel::download_frame(frame)?;
+                self.last_frame_info = Some(FrameInfo::of(frame));
+                Ok(true)
+            }
+            Err(ffmpeg::Error::Other { errno: ffmpeg::error::EAGAIN }) | Err(ffmpeg::Error::Eof) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Les images filtrées en attente passent avant toute nouvelle frame décodée
+    fn receive_filtered(&mut self, frame: &mut Video) -> Result<bool> {
+        let Some(ref mut filter) = self.filter else {
+            return Ok(false);
+        };
+        loop {
+            if filter.pull(frame)? {
+                return Ok(true);
+            }
+            match self.decoder.receive_frame(frame) {
+                Ok(_) => {
+                    self.queued_packets = self.queued_packets.saturating_sub(1);
+                    hwaccel::download_frame(frame)?;
+                    self.last_frame_info = Some(FrameInfo::of(frame));
+                    // Le filtre a besoin d'un PTS sur chaque frame
+                    let timestamp = frame.timestamp();
+                    frame.set_pts(timestamp);
+                    filter.push(frame)?;
+                }
+                Err(ffmpeg::Error::Other { errno: ffmpeg::error::EAGAIN }) => return Ok(false),
+                Err(ffmpeg::Error::Eof) => {
+                    filter.finish()?;
+                    return filter.pull(frame);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    // Aperçu rapide: le décodeur ignore tout ce qui n'est pas image clé (AVDISCARD_NONKEY).
+    // Le cadencement repart de zéro dans les deux sens
+    pub fn set_keyframes_only(&mut self, enabled: bool) {
+        self.decoder
+            .skip_frame(if enabled { ffmpeg::Discard::NonKey } else { ffmpeg::Discard::Default });
+        self.keyframes_only = enabled;
+        self.queued_packets = 0;
+        self.reset_clock();
+    }
+
+    // Cadence des frames source en temps réel, vitesse de lecture comprise
+    fn source_rate(&self) -> f64 {
+        f64::from(self.frame_rate) * self.speed
+    }
+
+    // Au-delà de la cadence d'affichage, les frames sont sautées régulièrement pour la suivre
+    pub fn set_display_rate(&mut self, display_rate: f64) {
+        let source_rate = self.source_rate();
+        self.display_phase = 0.0;
+        self.display_rate = (display_rate > 0.0 && source_rate > display_rate).then_some(display_rate);
+        if self.display_rate.is_some() {
+            println!(
+                "Cadence source {:.2} fps supérieure à l'affichage: réduite à {:.2} fps ({:.0}% des frames)",
+                source_rate,
+                display_rate,
+                display_rate / source_rate * 100.0
+            );
+        }
+    }
+
+    // Instant d'affichage de la frame `index` depuis le départ, à la vitesse de lecture
+    pub fn frame_target(&self, index: u64) -> Duration {
+        frame_offset(self.frame_rate, index).div_f64(self.speed)
+    }
+
+    pub fn should_display_frame(&mut self, pts: i64) -> bool {
+        self.display_frame_at(pts, Instant::now(), |target, max_wait| {
+            wait_until(target, max_wait);
+            Instant::now()
+        })
+    }
+
+    // Décision d'affichage d'une frame arrivée à `now`. `wait` attend une échéance (au plus la durée
+    // donnée) et renvoie l'heure atteinte: la lecture dort, --verify-sync avance son horloge simulée
+    pub fn display_frame_at(&mut self, pts: i64, mut now: Instant, wait: impl FnOnce(Instant, Duration) -> Instant) -> bool {
+        if self.start_time.is_none() {
+            self.start_time = Some(now);
+            self.last_frame_time = Some(now);
+            self.target_index = 1;
+            self.next_frame_target = Some(now + self.frame_target(1));
+            println!("Première frame - Démarrage à {:?}", now);
+            return true;
+        }
+
+        // Sous-échantillonnage régulier: 240 -> 60 garde une frame sur 4, 100 -> 60 trois sur 5.
+        // La frame sautée avance l'échéancier comme si elle avait été affichée, le son n'est pas touché
+        if let Some(display_rate) = self.display_rate {
+            self.display_phase += display_rate / self.source_rate();
+            if self.display_phase < 1.0 {
+                self.decimated_frames += 1;
+                self.target_index += 1;
+                self.next_frame_target = Some(self.start_time.unwrap() + self.frame_target(self.target_index));
+                return false;
+            }
+        }
+
+        // Calculer le temps vidéo en utilisant le time_base (1/16000), ramené à la vitesse de lecture
+        let video_time = Duration::from_secs_f64(pts as f64 * self.time_base / self.speed);
+
+        // Vérifier si nous avons atteint le temps cible pour la prochaine frame
+        let target_time = self.next_frame_target.unwrap();
+        if now < target_time {
+            // Trop tôt: attente de l'échéance, sans jamais attendre plus d'une frame. Encore trop tôt
+            // après une frame d'attente, la frame est abandonnée
+            now = wait(target_time, self.frame_target(1));
+            if now < target_time {
+                return false;
+            }
+        }
+        let elapsed = now.duration_since(self.start_time.unwrap());
+
+        // Calculer l'intervalle depuis la dernière frame
+        let frame_interval = if let Some(last) = self.last_frame_time {
+            now.duration_since(last)
+        } else {
+            Duration::ZERO
+        };
+
+        if self.frame_intervals.len() == fps_overlay::HISTORY_LEN {
+            self.frame_intervals.pop_front();
+        }
+        self.frame_intervals.push_back(frame_interval);
+
+        // Mettre à jour les compteurs
+        if self.display_rate.is_some() {
+            self.display_phase = (self.display_phase - 1.0).fract();
+        }
+        self.frame_count += 1;
+        self.last_frame_time = Some(now);
+        self.target_index += 1;
+        self.next_frame_target = Some(self.start_time.unwrap() + self.frame_target(self.target_index));
+
+        // Log toutes les 30 frames, remplacé par la ligne de --progress
+        if self.log_stats && self.frame_count % 30 == 0 {
+            let current_fps = 1.0 / frame_interval.as_secs_f64();
+            println!("Frame {} - Stats:", self.frame_count);
+            println!("  Intervalle: {:.2}ms", frame_interval.as_secs_f64() * 1000.0);
+            println!("  FPS actuel: {:.2}", current_fps);
+            println!("  Vitesse: x{:.2}", self.speed);
+            if let Some(interpolation_rate) = self.interpolation_rate {
+                println!("  Interpolation: {:.2} fps", f64::from(interpolation_rate));
+            }
+            if let Some(display_rate) = self.display_rate {
+                println!("  Cadence affichée: {:.2} fps (source {:.2} fps)", display_rate, self.source_rate());
+            }
+            println!("  Temps vidéo: {:.2}ms", video_time.as_secs_f64() * 1000.0);
+            println!("  Temps réel: {:.2}ms", elapsed.as_secs_f64() * 1000.0);
+            println!("  PTS: {}", pts);
+            println!("  Écart à la cible: {:.3}ms", now.duration_since(target_time).as_secs_f64() * 1000.0);
+
+            if elapsed > video_time {
+                println!("  Retard: {:.2}ms", (elapsed - video_time).as_secs_f64() * 1000.0);
+            } else {
+                println!("  Avance: {:.2}ms", (video_time - elapsed).as_secs_f64() * 1000.0);
+            }
+        }
+
+        true
+    }
+}
+
+// Attend `target` (au plus `max_wait`), en dormant puis en cédant la main pour la dernière milliseconde
+fn wait_until(target: Instant, max_wait: Duration) {
+    let now = Instant::now();
+    let deadline = now + target.saturating_duration_since(now).min(max_wait);
+    let remaining = deadline.duration_since(now);
+    if remaining > SPIN_THRESHOLD {
+        std::thread::sleep(remaining - SPIN_THRESHOLD);
+    }
+    while Instant::now() < deadline {
+        std::thread::yield_now();
+    }
+}
+
+// Reconstruit le scaler quand le format ou la taille des frames change, jamais à chaque frame.
+// current et received: (format, largeur, hauteur) de construction et de la frame reçue
+fn refresh_scaler<S>(
+    scaler: &mut S,
+    current: (Pixel, u32, u32),
+    received: (Pixel, u32, u32),
+    build: impl FnOnce() -> Result<S>,
+) -> Result<bool> {
+    if received == current {
+        return Ok(false);
+    }
+    if received.0 != current.0 {
+        println!("Format des frames: {:?} -> {:?}", current.0, received.0);
+    }
+    *scaler = build()?;
+    Ok(true)
+}
+
+// Horodatage d'une frame décodée: best_effort_timestamp, sinon son PTS. Avec des B-frames, l'ordre
+// de décodage n'est pas celui d'affichage et le paquet envoyé n'est pas celui de la frame reçue:
+// son PTS ne sert qu'en dernier recours
+pub fn presentation_pts(best_effort: Option<i64>, pts: Option<i64>, packet_pts: Option<i64>) -> Option<i64> {
+    best_effort.or(pts).or(packet_pts)
+}
+
+// Saut de PTS en avant ou en arrière au-delà de PTS_DISCONTINUITY_THRESHOLD: remise à zéro de
+// l'horloge d'une capture TS, fichiers concaténés...
+pub fn is_discontinuity(expected: f64, pts_time: f64) -> bool {
+    (pts_time - expected).abs() > PTS_DISCONTINUITY_THRESHOLD
+}
+
+// Un flux malformé peut annoncer 0x0 ou 1x1, que ni le scaler ni SDL n'acceptent
+fn check_video_dimensions(width: u32, height: u32) -> Result<()> {
+    if width < MIN_VIDEO_DIMENSION || height < MIN_VIDEO_DIMENSION {
+        anyhow::bail!("Dimensions vidéo invalides: {}x{}", width, height);
+    }
+    Ok(())
+}
+
+fn is_valid_frame_rate(frame_rate: ffmpeg::Rational) -> bool {
+    frame_rate.numerator() > 0 && frame_rate.denominator() > 0
+}
+
+// Cadence du flux: r_frame_rate, sinon la cadence moyenne, sinon DEFAULT_FRAME_RATE.
+// Avec 0/0, la durée d'une frame serait infinie et tout le cadencement serait faux
+fn stream_frame_rate(stream: &ffmpeg::Stream) -> ffmpeg::Rational {
+    let (rate, average) = (stream.rate(), stream.avg_frame_rate());
+    let chosen = choose_frame_rate(rate, average);
+    if chosen == average && chosen != rate {
+        println!("Attention: cadence du flux invalide ({}), utilisation de la cadence moyenne {}", rate, average);
+    } else if chosen != rate {
+        println!(
+            "Attention: le flux n'annonce aucune cadence, {} fps supposés (--framerate pour la forcer)",
+            f64::from(chosen)
+        );
+    }
+    chosen
+}
+
+fn choose_frame_rate(rate: ffmpeg::Rational, average: ffmpeg::Rational) -> ffmpeg::Rational {
+    [rate, average]
+        .into_iter()
+        .find(|&candidate| is_valid_frame_rate(candidate))
+        .unwrap_or(ffmpeg::Rational::new(DEFAULT_FRAME_RATE.0, DEFAULT_FRAME_RATE.1))
+}
+
+// Décalage exact de la frame `index` depuis le départ, en arithmétique entière sur le rationnel
+pub fn frame_offset(frame_rate: ffmpeg::Rational, index: u64) -> Duration {
+    let numerator = frame_rate.numerator().max(1) as u128;
+    let denominator = frame_rate.denominator().max(1) as u128;
+    let nanos = u128::from(index) * denominator * 1_000_000_000 / numerator;
+    Duration::from_nanos(nanos as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Options;
+    use crate::player::Player;
+
+    #[test]
+    fn frame_offset_does_not_drift_at_ntsc_rates() {
+        for (numerator, denominator) in [(24000, 1001), (30000, 1001)] {
+            let rate = ffmpeg::Rational::new(numerator, denominator);
+            let exact_step = f64::from(denominator) * 1e9 / f64::from(numerator);
+            let mut previous = Duration::ZERO;
+            for index in 1..=10_000u64 {
+                let offset = frame_offset(rate, index);
+                // PTS de la frame dans la base de temps 1/numerator: index * denominator
+                let pts = u128::from(index) * denominator as u128;
+                assert_eq!(offset.as_nanos(), pts * 1_000_000_000 / numerator as u128);
+                // Chaque pas reste à moins d'une nanoseconde de la durée exacte d'une frame
+                let step = (offset - previous).as_nanos() as f64;
+                assert!((step - exact_step).abs() < 1.0, "frame {}: pas de {} ns", index, step);
+                previous = offset;
+            }
+            // numerator frames durent exactement denominator secondes
+            assert_eq!(frame_offset(rate, numerator as u64), Duration::from_secs(denominator as u64));
+        }
+    }
+
+    #[test]
+    fn wait_until_is_never_early_and_lands_close() {
+        let mut lateness = Vec::new();
+        for _ in 0..50 {
+            let target = Instant::now() + Duration::from_millis(3);
+            wait_until(target, Duration::from_secs(1));
+            let now = Instant::now();
+            assert!(now >= target);
+            lateness.push(now - target);
+        }
+        lateness.sort();
+        // Médiane: tolérante à une machine chargée, mais loin de la granularité d'un sleep seul
+        assert!(lateness[lateness.len() / 2] < SPIN_THRESHOLD, "{:?}", lateness);
+    }
+
+    #[test]
+    fn wait_until_caps_the_wait() {
+        let start = Instant::now();
+        wait_until(start + Duration::from_secs(10), Duration::from_millis(5));
+        let waited = start.elapsed();
+        assert!(waited >= Duration::from_millis(5) && waited < Duration::from_secs(1), "{:?}", waited);
+    }
+
+    #[test]
+    fn zero_or_one_pixel_streams_are_rejected() {
+        let error = check_video_dimensions(0, 0).unwrap_err();
+        assert_eq!(error.to_string(), "Dimensions vidéo invalides: 0x0");
+        assert!(check_video_dimensions(1, 1).is_err());
+        assert!(check_video_dimensions(640, 0).is_err());
+        assert!(check_video_dimensions(0, 480).is_err());
+        assert!(check_video_dimensions(2, 2).is_ok());
+        assert!(check_video_dimensions(1920, 1080).is_ok());
+    }
+
+    #[test]
+    fn pts_reset_and_wraparound_are_discontinuities() {
+        let frame = 1.0 / 25.0;
+        // Progression normale, et gigue d'horodatage de quelques millisecondes
+        assert!(!is_discontinuity(10.0 + frame, 10.0 + frame));
+        assert!(!is_discontinuity(10.0 + frame, 10.0 + frame + 0.005));
+        // Horloge d'une capture TS remise à zéro
+        assert!(is_discontinuity(3600.0 + frame, 0.0));
+        // PTS MPEG-TS sur 33 bits à 90 kHz: il repart de zéro après 2^33 / 90000 s
+        let wrap = (1u64 << 33) as f64 / 90000.0;
+        assert!(is_discontinuity(wrap - 0.02 + frame, 0.02));
+        // Saut en avant de fichiers concaténés
+        assert!(is_discontinuity(60.0 + frame, 120.0));
+    }
+
+    // Nécessite les bibliothèques ffmpeg. H.264 Main 16x16 en FLV, GOP I P B B P B B à 25 i/s: les
+    // paquets arrivent en ordre de décodage (PTS 40 160 80 120 280 200 240 ms), et le décodeur
+    // retient une frame (max_num_reorder_frames = 1 dans le SPS)
+    #[test]
+    fn b_frames_are_paced_in_presentation_order() {
+        ffmpeg::init().unwrap();
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/b_frames.flv");
+        let options = Options { video_path: path.to_string(), ..Options::default() };
+        let mut player = Player::open(&options).unwrap();
+        let mut decoder = player.decoder.take().unwrap();
+        decoder.log_stats = false;
+
+        // Même enchaînement que la boucle de lecture, jusqu'au vidage du décodeur en fin de flux
+        let mut frame = Video::empty();
+        let mut packet_order = Vec::new();
+        let mut displayed_pts = Vec::new();
+        loop {
+            let packet = player::read_packet(&mut player.ictx).unwrap();
+            let mut packet_pts = match packet {
+                Some(ref packet) => {
+                    decoder.send_packet(packet).unwrap();
+                    packet_order.extend(packet.pts());
+                    packet.pts()
+                }
+                None => {
+                    decoder.decoder.send_eof().unwrap();
+                    None
+                }
+            };
+            while decoder.receive_frame_yuv(&mut frame).unwrap() {
+                let pts = presentation_pts(None, frame.pts(), packet_pts.take()).unwrap();
+                decoder.should_display_frame(pts);
+                displayed_pts.push(pts);
+            }
+            if packet.is_none() {
+                break;
+            }
+        }
+
+        // Cadencé sur les paquets, l'affichage reculerait
+        assert!(packet_order.windows(2).any(|pair| pair[1] < pair[0]));
+        assert_eq!(displayed_pts, [40, 80, 120, 160, 200, 240, 280]);
+    }
+
+    #[test]
+    fn only_nv12_frames_at_display_size_bypass_the_scaler() {
+        assert!(bypasses_scaler(true, Pixel::NV12, (1920, 1080), (1920, 1080)));
+        // Rendu direct désactivé, ou renderer sans NV12
+        assert!(!bypasses_scaler(false, Pixel::NV12, (1920, 1080), (1920, 1080)));
+        // Décodage logiciel, ou autre format rapatrié du GPU: conversion habituelle
+        assert!(!bypasses_scaler(true, Pixel::YUV420P, (1920, 1080), (1920, 1080)));
+        assert!(!bypasses_scaler(true, Pixel::P010LE, (1920, 1080), (1920, 1080)));
+        // Fenêtre redimensionnée (--scale-to-window): le scaler change la taille
+        assert!(!bypasses_scaler(true, Pixel::NV12, (1920, 1080), (1280, 720)));
+    }
+    #[test]
+    fn packet_pts_is_a_last_resort() {
+        assert_eq!(presentation_pts(Some(10), Some(12), Some(3)), Some(10));
+        assert_eq!(presentation_pts(None, Some(12), Some(3)), Some(12));
+        assert_eq!(presentation_pts(None, None, Some(3)), Some(3));
+        assert_eq!(presentation_pts(None, None, None), None);
+    }
+
+    // Frames reçues tour à tour, avec pour « scaler » le format pour lequel il a été construit
+    fn count_scaler_rebuilds(frames: &[(Pixel, u32, u32)]) -> usize {
+        let mut scaler = frames[0];
+        let mut rebuilds = 0;
+        for &received in frames {
+            let current = scaler;
+            if refresh_scaler(&mut scaler, current, received, || Ok(received)).unwrap() {
+                rebuilds += 1;
+            }
+            assert_eq!(scaler, received);
+        }
+        rebuilds
+    }
+
+    #[test]
+    fn scaler_rebuilds_once_per_format_change() {
+        // Décodage matériel qui bascule sur le logiciel: NV12 puis YUV420P
+        let mut frames = vec![(Pixel::NV12, 1920, 1080); 5];
+        frames.extend([(Pixel::YUV420P, 1920, 1080); 5]);
+        assert_eq!(count_scaler_rebuilds(&frames), 1);
+
+        // Aller-retour, puis changement de taille seul
+        frames.extend([(Pixel::NV12, 1920, 1080); 3]);
+        frames.extend([(Pixel::NV12, 1280, 720); 3]);
+        assert_eq!(count_scaler_rebuilds(&frames), 3);
+    }
+
+    #[test]
+    fn failed_scaler_rebuild_keeps_previous_scaler() {
+        let current = (Pixel::NV12, 64, 64);
+        let mut scaler = current;
+        let result = refresh_scaler(&mut scaler, current, (Pixel::YUV420P, 64, 64), || anyhow::bail!("format non supporté"));
+        assert!(result.is_err());
+        assert_eq!(scaler, (Pixel::NV12, 64, 64));
+    }
+
+    #[test]
+    fn frame_rate_falls_back_from_stream_rate_to_average_to_default() {
+        let rational = ffmpeg::Rational::new;
+        // r_frame_rate est préféré même quand la cadence moyenne diffère (horodatages irréguliers)
+        assert_eq!(choose_frame_rate(rational(25, 1), rational(2997, 125)), rational(25, 1));
+        assert_eq!(choose_frame_rate(rational(0, 0), rational(30000, 1001)), rational(30000, 1001));
+        assert_eq!(choose_frame_rate(rational(0, 1), rational(24, 1)), rational(24, 1));
+        let fallback = choose_frame_rate(rational(0, 0), rational(0, 0));
+        assert_eq!(fallback, rational(DEFAULT_FRAME_RATE.0, DEFAULT_FRAME_RATE.1));
+        // Cadencement sain: 40 ms par frame à 25 fps
+        assert_eq!(frame_offset(fallback, 1), Duration::from_millis(40));
+    }
+
+    #[test]
+    fn zero_or_negative_rationals_are_invalid_frame_rates() {
+        let rational = ffmpeg::Rational::new;
+        for invalid in [rational(0, 0), rational(0, 1), rational(25, 0), rational(-25, 1), rational(25, -1)] {
+            assert!(!is_valid_frame_rate(invalid), "{}", invalid);
+        }
+        assert!(is_valid_frame_rate(rational(24000, 1001)));
+    }
+}
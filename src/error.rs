@@ -0,0 +1,153 @@
+use ffmpeg_next as ffmpeg;
+use std::fmt;
+
+// Erreurs de l'API de Player, pour qu'une application hôte distingue un fichier sans vidéo d'une erreur
+// de décodage. Le code interne reste en anyhow: la conversion se fait à la sortie de l'API, et chaque
+// variante garde l'erreur d'origine avec son contexte
+#[derive(Debug)]
+pub enum PlayerError {
+    // Fichier introuvable, droits insuffisants, coupure réseau...
+    Io(anyhow::Error),
+    // Conteneur ou codec que ffmpeg ne sait pas lire
+    UnsupportedFormat(anyhow::Error),
+    // Opération qui demande un flux vidéo sur un fichier audio seul
+    NoVideoStream,
+    // Ni vidéo ni audio dans le fichier
+    NoStream,
+    Decode(anyhow::Error),
+    Seek(anyhow::Error),
+    Other(anyhow::Error),
+}
+
+impl PlayerError {
+    // Classe une erreur interne d'après la première erreur ffmpeg ou d'E/S de sa chaîne.
+    // Une PlayerError déjà typée plus bas (NoStream, Seek...) est rendue telle quelle
+    pub fn classify(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<PlayerError>() {
+            Ok(player_error) => return player_error,
+            Err(error) => error,
+        };
+        if error.chain().any(|cause| cause.is::<std::io::Error>()) {
+            return PlayerError::Io(error);
+        }
+        let ffmpeg_error = error.chain().find_map(|cause| cause.downcast_ref::<ffmpeg::Error>()).copied();
+        match ffmpeg_error {
+            Some(
+                ffmpeg::Error::DecoderNotFound
+                | ffmpeg::Error::DemuxerNotFound
+                | ffmpeg::Error::ProtocolNotFound
+                | ffmpeg::Error::InvalidData
+                | ffmpeg::Error::PatchWelcome,
+            ) => PlayerError::UnsupportedFormat(error),
+            // AVERROR(errno) d'un protocole: erreur système (ENOENT, EACCES, ECONNRESET...)
+            Some(ffmpeg::Error::Other { .. } | ffmpeg::Error::Exit)
+            | Some(
+                ffmpeg::Error::HttpBadRequest
+                | ffmpeg::Error::HttpUnauthorized
+                | ffmpeg::Error::HttpForbidden
+                | ffmpeg::Error::HttpNotFound
+                | ffmpeg::Error::HttpOther4xx
+                | ffmpeg::Error::HttpServerError,
+            ) => PlayerError::Io(error),
+            Some(_) => PlayerError::Decode(error),
+            None => PlayerError::Other(error),
+        }
+    }
+}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerError::NoVideoStream => write!(f, "Aucun flux vidéo"),
+            PlayerError::NoStream => write!(f, "Aucun flux vidéo ni audio trouvé"),
+            PlayerError::Io(error)
+            | PlayerError::UnsupportedFormat(error)
+            | PlayerError::Decode(error)
+            | PlayerError::Seek(error)
+            | PlayerError::Other(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for PlayerError {
+    // Le message est celui de l'erreur d'origine: la source est la suite de sa chaîne
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlayerError::NoVideoStream | PlayerError::NoStream => None,
+            PlayerError::Io(error)
+            | PlayerError::UnsupportedFormat(error)
+            | PlayerError::Decode(error)
+            | PlayerError::Seek(error)
+            | PlayerError::Other(error) => error.source(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    // Erreur ffmpeg remontée comme dans le lecteur: avec le contexte ajouté par l'appelant
+    fn failed(error: ffmpeg::Error) -> anyhow::Error {
+        Err::<(), _>(error).context("Impossible d'ouvrir film.mkv").unwrap_err()
+    }
+
+    #[test]
+    fn unknown_container_or_codec_is_unsupported_format() {
+        for error in [
+            ffmpeg::Error::DecoderNotFound,
+            ffmpeg::Error::DemuxerNotFound,
+            ffmpeg::Error::ProtocolNotFound,
+            ffmpeg::Error::InvalidData,
+            ffmpeg::Error::PatchWelcome,
+        ] {
+            assert!(matches!(PlayerError::classify(failed(error)), PlayerError::UnsupportedFormat(_)));
+        }
+    }
+
+    #[test]
+    fn system_and_http_errors_are_io() {
+        // AVERROR(ENOENT) d'avformat_open_input sur un fichier absent
+        let missing = ffmpeg::Error::from(-ffmpeg::error::ENOENT);
+        assert!(matches!(missing, ffmpeg::Error::Other { errno: ffmpeg::error::ENOENT }));
+        for error in [missing, ffmpeg::Error::Exit, ffmpeg::Error::HttpNotFound, ffmpeg::Error::HttpServerError] {
+            assert!(matches!(PlayerError::classify(failed(error)), PlayerError::Io(_)));
+        }
+
+        let io_error = std::fs::File::open("/inexistant/film.mkv").context("Impossible de lire le fichier").unwrap_err();
+        assert!(matches!(PlayerError::classify(io_error), PlayerError::Io(_)));
+    }
+
+    #[test]
+    fn other_ffmpeg_errors_are_decode_errors() {
+        for error in [ffmpeg::Error::Bug, ffmpeg::Error::Unknown, ffmpeg::Error::BufferTooSmall] {
+            assert!(matches!(PlayerError::classify(failed(error)), PlayerError::Decode(_)));
+        }
+    }
+
+    #[test]
+    fn errors_without_ffmpeg_or_io_cause_are_other() {
+        let error = PlayerError::classify(anyhow::anyhow!("Texture introuvable"));
+        assert!(matches!(error, PlayerError::Other(_)));
+        assert_eq!(error.to_string(), "Texture introuvable");
+    }
+
+    #[test]
+    fn typed_errors_are_kept_through_context() {
+        let error = Err::<(), _>(PlayerError::NoVideoStream).context("Miniature impossible").unwrap_err();
+        let error = PlayerError::classify(error);
+        assert!(matches!(error, PlayerError::NoVideoStream));
+        assert_eq!(error.to_string(), "Aucun flux vidéo");
+
+        let seek = PlayerError::Seek(anyhow::anyhow!("Position hors du fichier"));
+        assert!(matches!(PlayerError::classify(seek.into()), PlayerError::Seek(_)));
+    }
+
+    #[test]
+    fn message_and_source_come_from_the_original_error() {
+        let error = PlayerError::classify(failed(ffmpeg::Error::InvalidData));
+        assert_eq!(error.to_string(), "Impossible d'ouvrir film.mkv");
+        assert!(std::error::Error::source(&error).is_some_and(|source| source.is::<ffmpeg::Error>()));
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+use std::collections::VecDeque;
+use std::time::Duration;
+use crate::bitmap_font;
+
+// Nombre d'intervalles entre frames affichés par le graphe
+pub const HISTORY_LEN: usize = 100;
+const BAR_WIDTH: u32 = 2;
+const GRAPH_HEIGHT: u32 = 48;
+const MARGIN: i32 = 8;
+const PADDING: i32 = 4;
+const FONT_SCALE: u32 = 2;
+
+const BACKGROUND: Color = Color::RGBA(0, 0, 0, 160);
+const TEXT: Color = Color::RGB(255, 255, 255);
+const ON_TIME: Color = Color::RGB(80, 220, 80);
+const LATE: Color = Color::RGB(240, 200, 40);
+const DROPPED: Color = Color::RGB(240, 60, 60);
+
+// Graphe des derniers intervalles entre frames et FPS moyen, dans le coin supérieur gauche.
+// Une barre pleine correspond à deux fois l'intervalle visé.
+pub fn draw(canvas: &mut Canvas<Window>, intervals: &VecDeque<Duration>, target: Duration) -> Result<()> {
+    let average = if intervals.is_empty() {
+        Duration::ZERO
+    } else {
+        intervals.iter().sum::<Duration>() / intervals.len() as u32
+    };
+    let fps = if average.is_zero() { 0.0 } else { 1.0 / average.as_secs_f64() };
+    let label = format!("{:.1} FPS", fps);
+
+    let graph_width = HISTORY_LEN as u32 * BAR_WIDTH;
+    let text_height = bitmap_font::text_height(FONT_SCALE);
+    let width = graph_width.max(bitmap_font::text_width(&label, FONT_SCALE)) + 2 * PADDING as u32;
+    let height = text_height + GRAPH_HEIGHT + 3 * PADDING as u32;
+
+    // La couleur courante sert aussi à canvas.clear(): elle est restaurée à la fin
+    let previous_color = canvas.draw_color();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKGROUND);
+    canvas
+        .fill_rect(Rect::new(MARGIN, MARGIN, width, height))
+        .map_err(|e| anyhow::anyhow!(e))?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(TEXT);
+    bitmap_font::draw_text(canvas, MARGIN + PADDING, MARGIN + PADDING, FONT_SCALE, &label)?;
+
+    let graph_bottom = MARGIN + height as i32 - PADDING;
+    let full_scale = (target * 2).as_secs_f64().max(f64::EPSILON);
+    for (index, interval) in intervals.iter().enumerate() {
+        let ratio = interval.as_secs_f64() / full_scale;
+        let bar_height = ((ratio.min(1.0) * f64::from(GRAPH_HEIGHT)) as u32).max(1);
+        let color = if *interval <= target.mul_f64(1.5) {
+            ON_TIME
+        } else if *interval <= target * 2 {
+            LATE
+        } else {
+            DROPPED
+        };
+        canvas.set_draw_color(color);
+        canvas
+            .fill_rect(Rect::new(
+                MARGIN + PADDING + (index as u32 * BAR_WIDTH) as i32,
+                graph_bottom - bar_height as i32,
+                BAR_WIDTH,
+                bar_height,
+            ))
+            .map_err(|e| anyhow::anyhow!(e))?;
+    }
+    canvas.set_draw_color(previous_color);
+    Ok(())
+}
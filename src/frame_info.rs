@@ -0,0 +1,85 @@
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+use ffmpeg::format::Pixel;
+use ffmpeg::util::frame::video::Video;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+use crate::bitmap_font;
+
+const MARGIN: i32 = 8;
+const PADDING: i32 = 4;
+const FONT_SCALE: u32 = 2;
+// Espace entre deux lignes, en pixels de police
+const LINE_SPACING: u32 = 2;
+
+const BACKGROUND: Color = Color::RGBA(0, 0, 0, 160);
+const TEXT: Color = Color::RGB(255, 255, 255);
+const KEY_FRAME: Color = Color::RGB(240, 200, 40);
+
+// Métadonnées de la frame décodée, relevées avant la conversion: le scaler ne les recopie pas
+#[derive(Clone, Copy, Debug)]
+pub struct FrameInfo {
+    pub pts: Option<i64>,
+    pub kind: ffmpeg::picture::Type,
+    pub key: bool,
+    pub width: u32,
+    pub height: u32,
+    pub format: Pixel,
+}
+
+impl FrameInfo {
+    pub fn of(frame: &Video) -> Self {
+        Self {
+            pts: frame.pts(),
+            kind: frame.kind(),
+            key: frame.is_key(),
+            width: frame.width(),
+            height: frame.height(),
+            format: frame.format(),
+        }
+    }
+}
+
+// Bloc de texte dans le coin supérieur droit: PTS brut et en secondes, type d'image, image clé,
+// taille et format décodés. Le texte passe en jaune sur une image clé.
+pub fn draw(canvas: &mut Canvas<Window>, info: &FrameInfo, time_base: f64) -> Result<()> {
+    let pts = match info.pts {
+        Some(pts) => format!("PTS {} ({:.3}S)", pts, pts as f64 * time_base),
+        None => "PTS -".to_string(),
+    };
+    let lines = [
+        pts,
+        format!("TYPE {:?}  CLE {}", info.kind, if info.key { "OUI" } else { "NON" }),
+        format!("{}X{} {:?}", info.width, info.height, info.format),
+    ];
+
+    let line_height = bitmap_font::text_height(FONT_SCALE) + LINE_SPACING * FONT_SCALE;
+    let text_width = lines
+        .iter()
+        .map(|line| bitmap_font::text_width(line, FONT_SCALE))
+        .max()
+        .unwrap_or(0);
+    let width = text_width + 2 * PADDING as u32;
+    let height = lines.len() as u32 * line_height - LINE_SPACING * FONT_SCALE + 2 * PADDING as u32;
+    let (canvas_width, _) = canvas.output_size().map_err(|e| anyhow::anyhow!(e))?;
+    let x = canvas_width as i32 - width as i32 - MARGIN;
+
+    // Même précaution que fps_overlay: la couleur courante sert à canvas.clear()
+    let previous_color = canvas.draw_color();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKGROUND);
+    canvas
+        .fill_rect(Rect::new(x, MARGIN, width, height))
+        .map_err(|e| anyhow::anyhow!(e))?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(if info.key { KEY_FRAME } else { TEXT });
+    for (row, line) in lines.iter().enumerate() {
+        let y = MARGIN + PADDING + (row as u32 * line_height) as i32;
+        bitmap_font::draw_text(canvas, x + PADDING, y, FONT_SCALE, line)?;
+    }
+    canvas.set_draw_color(previous_color);
+    Ok(())
+}
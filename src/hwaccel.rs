@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use ffmpeg_next as ffmpeg;
+use ffmpeg::ffi;
+use ffmpeg::util::frame::video::Video;
+use std::ffi::CString;
+use std::ptr;
+
+// API d'accélération matérielle choisie avec --hwaccel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HwAccel {
+    None,
+    Cuda,
+    Vaapi,
+    Qsv,
+    VideoToolbox,
+}
+
+impl HwAccel {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "none" => Ok(HwAccel::None),
+            "cuda" => Ok(HwAccel::Cuda),
+            "vaapi" => Ok(HwAccel::Vaapi),
+            "qsv" => Ok(HwAccel::Qsv),
+            "videotoolbox" => Ok(HwAccel::VideoToolbox),
+            _ => anyhow::bail!(
+                "Accélération matérielle inconnue: {} (cuda, vaapi, qsv, videotoolbox ou none)",
+                value
+            ),
+        }
+    }
+
+    // Nom attendu par av_hwdevice_find_type_by_name
+    pub fn name(self) -> &'static str {
+        match self {
+            HwAccel::None => "none",
+            HwAccel::Cuda => "cuda",
+            HwAccel::Vaapi => "vaapi",
+            HwAccel::Qsv => "qsv",
+            HwAccel::VideoToolbox => "videotoolbox",
+        }
+    }
+}
+
+// AV_CODEC_HW_CONFIG_METHOD_HW_DEVICE_CTX: le décodeur accepte un hw_device_ctx fourni par l'appelant
+const HW_CONFIG_METHOD_HW_DEVICE_CTX: i32 = 0x01;
+
+// Crée le périphérique matériel et le confie au contexte, à faire avant l'ouverture du décodeur.
+// Un décodeur sans configuration pour ce périphérique l'ignorerait et décoderait en logiciel sans
+// le dire: c'est refusé ici pour que l'appelant se replie explicitement
+pub fn attach_device(context: &mut ffmpeg::codec::Context, accel: HwAccel) -> Result<()> {
+    let name = CString::new(accel.name())?;
+    let codec = ffmpeg::codec::decoder::find(context.id())
+        .ok_or(ffmpeg::Error::DecoderNotFound)
+        .context("Aucun décodeur disponible pour ce codec")?;
+    unsafe {
+        let device_type = ffi::av_hwdevice_find_type_by_name(name.as_ptr());
+        if device_type == ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_NONE {
+            anyhow::bail!("{} n'est pas pris en charge par cette version de ffmpeg", accel.name());
+        }
+        let mut configs = (0..)
+            .map(|index| ffi::avcodec_get_hw_config(codec.as_ptr(), index))
+            .take_while(|config| !config.is_null());
+        if !configs.any(|config| accepts_device(&*config, device_type)) {
+            anyhow::bail!("le décodeur {} ne sait pas décoder avec {}", codec.name(), accel.name());
+        }
+
+        let mut device = ptr::null_mut();
+        let ret = ffi::av_hwdevice_ctx_create(&mut device, device_type, ptr::null(), ptr::null_mut(), 0);
+        if ret < 0 {
+            return Err(ffmpeg::Error::from(ret))
+                .with_context(|| format!("Impossible d'initialiser le périphérique {}", accel.name()));
+        }
+
+        // Le contexte du codec devient propriétaire de la référence et la libère avec lui
+        (*context.as_mut_ptr()).hw_device_ctx = device;
+    }
+    Ok(())
+}
+
+fn accepts_device(config: &ffi::AVCodecHWConfig, device_type: ffi::AVHWDeviceType) -> bool {
+    config.device_type == device_type && config.methods & HW_CONFIG_METHOD_HW_DEVICE_CTX != 0
+}
+
+// Rapatrie en mémoire système une frame restée sur le GPU, une frame logicielle est laissée telle quelle
+pub fn download_frame(frame: &mut Video) -> Result<()> {
+    unsafe {
+        if (*frame.as_ptr()).hw_frames_ctx.is_null() {
+            return Ok(());
+        }
+
+        let mut software = Video::empty();
+        let ret = ffi::av_hwframe_transfer_data(software.as_mut_ptr(), frame.as_ptr(), 0);
+        if ret < 0 {
+            return Err(ffmpeg::Error::from(ret)).context("Échec du transfert de la frame depuis le GPU");
+        }
+        ffi::av_frame_copy_props(software.as_mut_ptr(), frame.as_ptr());
+        *frame = software;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ffi::AVHWDeviceType::{AV_HWDEVICE_TYPE_CUDA, AV_HWDEVICE_TYPE_VAAPI};
+
+    fn config(methods: i32, device_type: ffi::AVHWDeviceType) -> ffi::AVCodecHWConfig {
+        ffi::AVCodecHWConfig { pix_fmt: ffmpeg::format::Pixel::VAAPI.into(), methods, device_type }
+    }
+
+    #[test]
+    fn device_must_match_and_be_accepted_as_context() {
+        assert!(accepts_device(&config(HW_CONFIG_METHOD_HW_DEVICE_CTX, AV_HWDEVICE_TYPE_VAAPI), AV_HWDEVICE_TYPE_VAAPI));
+        assert!(!accepts_device(&config(HW_CONFIG_METHOD_HW_DEVICE_CTX, AV_HWDEVICE_TYPE_CUDA), AV_HWDEVICE_TYPE_VAAPI));
+        // Méthodes internes ou ad hoc seulement: le périphérique fourni serait ignoré
+        assert!(!accepts_device(&config(0x04 | 0x08, AV_HWDEVICE_TYPE_VAAPI), AV_HWDEVICE_TYPE_VAAPI));
+    }
+}
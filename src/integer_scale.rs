@@ -0,0 +1,72 @@
+use anyhow::Result;
+use sdl2::rect::Rect;
+use sdl2::render::Texture;
+use sdl2::sys::{SDL_ScaleMode, SDL_SetTextureScaleMode, SDL_Texture};
+
+// Rectangle de destination en mode « pixel perfect »: le plus grand multiple entier de la taille
+// de l'image qui tient dans la fenêtre, centré. Les bords restent de la couleur du clear (noir).
+// Une fenêtre plus petite que l'image garde le facteur 1: l'image déborde, recadrée au centre
+pub fn dest_rect(output_width: u32, output_height: u32, image_width: u32, image_height: u32) -> Rect {
+    let factor = (output_width / image_width.max(1))
+        .min(output_height / image_height.max(1))
+        .max(1);
+    centered(output_width, output_height, image_width * factor, image_height * factor)
+}
+
+// Sans --integer-scale: la plus grande image aux proportions d'affichage qui tient dans la
+// fenêtre, centrée entre des bandes noires. Sert aux pixels non carrés (SAR), qu'un étirement
+// sur toute la fenêtre déformerait
+pub fn aspect_rect(output_width: u32, output_height: u32, display_width: u32, display_height: u32) -> Rect {
+    let scale = (f64::from(output_width) / f64::from(display_width.max(1)))
+        .min(f64::from(output_height) / f64::from(display_height.max(1)));
+    let width = ((f64::from(display_width) * scale).round() as u32).clamp(1, output_width.max(1));
+    let height = ((f64::from(display_height) * scale).round() as u32).clamp(1, output_height.max(1));
+    centered(output_width, output_height, width, height)
+}
+
+fn centered(output_width: u32, output_height: u32, width: u32, height: u32) -> Rect {
+    Rect::new(
+        (output_width as i32 - width as i32) / 2,
+        (output_height as i32 - height as i32) / 2,
+        width,
+        height,
+    )
+}
+
+// Échantillonnage au plus proche voisin pour garder les pixels nets, bilinéaire sinon.
+// sdl2 0.35 n'expose pas SDL_SetTextureScaleMode: appel direct à SDL
+pub fn set_nearest(texture: &mut Texture, nearest: bool) -> Result<()> {
+    set_nearest_raw(texture.raw(), nearest)
+}
+
+// Même réglage pour une texture gérée directement par l'API C (NV12, voir nv12_texture)
+pub(crate) fn set_nearest_raw(texture: *mut SDL_Texture, nearest: bool) -> Result<()> {
+    let mode = if nearest {
+        SDL_ScaleMode::SDL_ScaleModeNearest
+    } else {
+        SDL_ScaleMode::SDL_ScaleModeLinear
+    };
+    if unsafe { SDL_SetTextureScaleMode(texture, mode) } != 0 {
+        anyhow::bail!("Impossible de changer le filtrage de la texture: {}", sdl2::get_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dest_rect_uses_the_largest_whole_factor() {
+        assert_eq!(dest_rect(1920, 1080, 320, 240), Rect::new(320, 60, 1280, 960));
+        // Fenêtre trop petite: facteur 1, image recadrée au centre
+        assert_eq!(dest_rect(200, 200, 320, 240), Rect::new(-60, -20, 320, 240));
+    }
+
+    #[test]
+    fn aspect_rect_letterboxes_and_pillarboxes() {
+        assert_eq!(aspect_rect(1280, 720, 1280, 720), Rect::new(0, 0, 1280, 720));
+        assert_eq!(aspect_rect(800, 800, 1280, 720), Rect::new(0, 175, 800, 450));
+        assert_eq!(aspect_rect(1280, 720, 720, 720), Rect::new(280, 0, 720, 720));
+    }
+}
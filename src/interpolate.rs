@@ -0,0 +1,38 @@
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+
+// Méthode de minterpolate: compensation de mouvement (lourde) ou simple fondu entre deux images
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterpolationMode {
+    MotionCompensated,
+    Blend,
+}
+
+impl InterpolationMode {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "mci" => Ok(InterpolationMode::MotionCompensated),
+            "blend" => Ok(InterpolationMode::Blend),
+            _ => anyhow::bail!("Mode d'interpolation inconnu: {} (attendu mci ou blend)", value),
+        }
+    }
+
+    fn filter_value(self) -> &'static str {
+        match self {
+            InterpolationMode::MotionCompensated => "mci",
+            InterpolationMode::Blend => "blend",
+        }
+    }
+
+    // Ralenti lissé (--interpolate): le filtre minterpolate de ffmpeg crée les images intermédiaires pour
+    // que l'affichage garde la cadence d'origine au lieu de montrer chaque image plus longtemps.
+    // minterpolate ne travaille qu'en YUV planaire: les frames NV12 du décodage matériel sont converties
+    pub fn filter_spec(self, output_rate: ffmpeg::Rational) -> String {
+        format!(
+            "format=yuv420p,minterpolate=fps={}/{}:mi_mode={}",
+            output_rate.numerator(),
+            output_rate.denominator(),
+            self.filter_value()
+        )
+    }
+}
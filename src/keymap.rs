@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use sdl2::keyboard::Keycode;
+use std::collections::HashMap;
+use std::fs;
+
+// Commandes du lecteur accessibles au clavier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    TogglePause,
+    // Point A, point B, puis suppression de la boucle
+    LoopPoint,
+    PreviousChapter,
+    NextChapter,
+    ToggleHwAccel,
+    // Canal audio 1 à 8 coupé ou rétabli, 0 pour tous les rétablir
+    ToggleChannel(u8),
+    CycleVisualization,
+    ToggleFpsOverlay,
+    ToggleFrameInfo,
+    ToggleBufferStats,
+    ToggleIntegerScale,
+    CycleSubtitles,
+    TogglePip,
+    // Décodage des seules images clés, pour parcourir rapidement un long fichier
+    ToggleKeyframesOnly,
+    StepForward,
+    StepBackward,
+}
+
+impl Action {
+    // Nom utilisé dans le fichier de --keymap
+    pub fn parse(name: &str) -> Result<Self> {
+        let action = match name {
+            "quit" => Action::Quit,
+            "pause" => Action::TogglePause,
+            "loop" => Action::LoopPoint,
+            "chapter-prev" => Action::PreviousChapter,
+            "chapter-next" => Action::NextChapter,
+            "hwaccel" => Action::ToggleHwAccel,
+            "channels-reset" => Action::ToggleChannel(0),
+            "visualization" => Action::CycleVisualization,
+            "fps-overlay" => Action::ToggleFpsOverlay,
+            "frame-info" => Action::ToggleFrameInfo,
+            "buffer-stats" => Action::ToggleBufferStats,
+            "integer-scale" => Action::ToggleIntegerScale,
+            "subtitles" => Action::CycleSubtitles,
+            "pip" => Action::TogglePip,
+            "keyframes-only" => Action::ToggleKeyframesOnly,
+            "step-forward" => Action::StepForward,
+            "step-back" => Action::StepBackward,
+            _ => match name.strip_prefix("channel-").and_then(|channel| channel.parse::<u8>().ok()) {
+                Some(channel @ 1..=8) => Action::ToggleChannel(channel),
+                _ => anyhow::bail!("Action inconnue: {}", name),
+            },
+        };
+        Ok(action)
+    }
+}
+
+// Association touche -> action consultée par la boucle d'événements. Plusieurs touches peuvent
+// déclencher la même action
+pub struct KeyBindings {
+    bindings: HashMap<Keycode, Action>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let mut bindings = HashMap::from([
+            (Keycode::Escape, Action::Quit),
+            (Keycode::Space, Action::TogglePause),
+            (Keycode::L, Action::LoopPoint),
+            (Keycode::PageUp, Action::PreviousChapter),
+            (Keycode::PageDown, Action::NextChapter),
+            (Keycode::H, Action::ToggleHwAccel),
+            (Keycode::V, Action::CycleVisualization),
+            (Keycode::G, Action::ToggleFpsOverlay),
+            (Keycode::K, Action::ToggleFrameInfo),
+            (Keycode::B, Action::ToggleBufferStats),
+            (Keycode::I, Action::ToggleIntegerScale),
+            (Keycode::S, Action::CycleSubtitles),
+            (Keycode::P, Action::TogglePip),
+            (Keycode::J, Action::ToggleKeyframesOnly),
+            (Keycode::Period, Action::StepForward),
+            (Keycode::Comma, Action::StepBackward),
+        ]);
+        // Touches 0 à 8 du clavier principal
+        let channel_keys = [
+            Keycode::Num0,
+            Keycode::Num1,
+            Keycode::Num2,
+            Keycode::Num3,
+            Keycode::Num4,
+            Keycode::Num5,
+            Keycode::Num6,
+            Keycode::Num7,
+            Keycode::Num8,
+        ];
+        for (channel, key) in channel_keys.into_iter().enumerate() {
+            bindings.insert(key, Action::ToggleChannel(channel as u8));
+        }
+        Self { bindings }
+    }
+}
+
+impl KeyBindings {
+    // Touches par défaut, modifiées par un fichier de lignes `touche=action`. Les noms de touches
+    // sont ceux de SDL (Space, Escape, PageUp, F, 1...), `touche=none` retire une touche
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path).with_context(|| format!("Impossible de lire {}", path))?;
+        let mut keymap = Self::default();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse = || -> Result<(Keycode, Option<Action>)> {
+                let (key, action) = line.split_once('=').context("Ligne attendue: touche=action")?;
+                let key = Keycode::from_name(key.trim())
+                    .with_context(|| format!("Touche inconnue: {}", key.trim()))?;
+                let action = match action.trim() {
+                    "none" => None,
+                    action => Some(Action::parse(action)?),
+                };
+                Ok((key, action))
+            };
+            let (key, action) = parse().with_context(|| format!("{}, ligne {}", path, number + 1))?;
+            match action {
+                Some(action) => keymap.bindings.insert(key, action),
+                None => keymap.bindings.remove(&key),
+            };
+        }
+        Ok(keymap)
+    }
+
+    pub fn action(&self, key: Keycode) -> Option<Action> {
+        self.bindings.get(&key).copied()
+    }
+}
@@ -0,0 +1,38 @@
+// Lecteur multimédia: Player ouvre un fichier ou un flux et le décode sans fenêtre, pour une
+// application hôte (rendu RGBA, miniatures). Le lecteur en ligne de commande s'appuie sur les
+// mêmes modules
+pub mod audio_filter;
+pub mod audio_ops;
+pub mod avio;
+pub mod bitmap_font;
+pub mod buffer_stats;
+pub mod decoder;
+pub mod error;
+pub mod fps_overlay;
+pub mod frame_info;
+pub mod hwaccel;
+pub mod integer_scale;
+pub mod interpolate;
+pub mod keymap;
+pub mod loop_overlay;
+pub mod mpris;
+pub mod nv12_texture;
+pub mod options;
+pub mod pip;
+pub mod player;
+pub mod recorder;
+pub mod resume;
+pub mod sample_simd;
+pub mod subtitle;
+pub mod subtitle_file;
+pub mod trace;
+pub mod video_filter;
+pub mod visualizer;
+pub mod watchdog;
+pub mod yuv_upload;
+
+pub use error::PlayerError;
+pub use options::Options;
+pub use player::{DecodedFrame, Player};
+
+pub const WINDOW_TITLE: &str = "Lecteur Vidéo Rust";
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+use crate::bitmap_font;
+
+const MARGIN: i32 = 8;
+const PADDING: i32 = 4;
+const FONT_SCALE: u32 = 2;
+// Espace entre deux lignes, en pixels de police
+const LINE_SPACING: u32 = 2;
+
+const BACKGROUND: Color = Color::RGBA(0, 0, 0, 160);
+const TEXT: Color = Color::RGB(255, 255, 255);
+
+// Passages restants, puis le point A demandé, l'image clé d'où repart chaque passage quand elle le
+// précède, et le point B
+fn lines(start: f64, keyframe: Option<f64>, end: f64, remaining: Option<u32>) -> [String; 2] {
+    let passes = match remaining {
+        Some(remaining) => format!("BOUCLE A-B: {} RESTANTE(S)", remaining),
+        None => "BOUCLE A-B: INFINIE".to_string(),
+    };
+    let points = match keyframe {
+        Some(keyframe) if keyframe < start => format!("A {:.2}S (IMAGE CLÉ {:.2}S) B {:.2}S", start, keyframe, end),
+        _ => format!("A {:.2}S B {:.2}S", start, end),
+    };
+    [passes, points]
+}
+
+// Boucle A-B dans le coin inférieur droit tant qu'elle est active
+pub fn draw(canvas: &mut Canvas<Window>, start: f64, keyframe: Option<f64>, end: f64, remaining: Option<u32>) -> Result<()> {
+    let lines = lines(start, keyframe, end, remaining);
+    let line_height = bitmap_font::text_height(FONT_SCALE) + LINE_SPACING * FONT_SCALE;
+    let text_width = lines
+        .iter()
+        .map(|line| bitmap_font::text_width(line, FONT_SCALE))
+        .max()
+        .unwrap_or(0);
+    let width = text_width + 2 * PADDING as u32;
+    let height = lines.len() as u32 * line_height - LINE_SPACING * FONT_SCALE + 2 * PADDING as u32;
+    let (canvas_width, canvas_height) = canvas.output_size().map_err(|e| anyhow::anyhow!(e))?;
+    let x = canvas_width as i32 - width as i32 - MARGIN;
+    let y = canvas_height as i32 - height as i32 - MARGIN;
+
+    // Même précaution que fps_overlay: la couleur courante sert à canvas.clear()
+    let previous_color = canvas.draw_color();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKGROUND);
+    canvas
+        .fill_rect(Rect::new(x, y, width, height))
+        .map_err(|e| anyhow::anyhow!(e))?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(TEXT);
+    for (row, line) in lines.iter().enumerate() {
+        let line_y = y + PADDING + (row as u32 * line_height) as i32;
+        bitmap_font::draw_text(canvas, x + PADDING, line_y, FONT_SCALE, line)?;
+    }
+    canvas.set_draw_color(previous_color);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapped_keyframe_is_shown_next_to_the_requested_point() {
+        let [passes, points] = lines(12.5, Some(10.0), 15.0, Some(2));
+        assert_eq!(passes, "BOUCLE A-B: 2 RESTANTE(S)");
+        assert_eq!(points, "A 12.50S (IMAGE CLÉ 10.00S) B 15.00S");
+        // Point A sur l'image clé, ou démuxeur sans index: rien à signaler
+        assert_eq!(lines(10.0, Some(10.0), 15.0, None)[1], "A 10.00S B 15.00S");
+        assert_eq!(lines(12.5, None, 15.0, None), ["BOUCLE A-B: INFINIE", "A 12.50S B 15.00S"]);
+    }
+}
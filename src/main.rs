@@ -1,28 +1,146 @@
 use anyhow::{Context, Result};
 use ffmpeg_next as ffmpeg;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::pixels::PixelFormatEnum;
-use sdl2::audio::{AudioCallback, AudioSpecDesired};
+use sdl2::audio::{AudioCallback, AudioDevice, AudioFormat, AudioSpecDesired, AudioStatus};
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
 use std::time::{Duration, Instant};
 use std::env;
-use ffmpeg::software::scaling::{context::Context as ScalingContext, flag::Flags};
 use ffmpeg::util::frame::video::Video;
 use ffmpeg::format::Pixel;
 use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use rust_media_player::decoder::{frame_offset, is_discontinuity, presentation_pts, Decoder, MIN_VIDEO_DIMENSION};
+use rust_media_player::options::Options;
+use rust_media_player::{
+    avio, buffer_stats, fps_overlay, frame_info, integer_scale, loop_overlay, mpris, nv12_texture, player,
+    video_filter, visualizer, watchdog, yuv_upload, WINDOW_TITLE,
+};
+use rust_media_player::audio_ops::{AudioResampler, DriftCorrector, SampleConverter};
+use rust_media_player::buffer_stats::BufferStats;
+use rust_media_player::hwaccel::HwAccel;
+use rust_media_player::interpolate::InterpolationMode;
+use rust_media_player::keymap::{Action, KeyBindings};
+use rust_media_player::mpris::{MediaCommand, Mpris};
+use rust_media_player::nv12_texture::Nv12Texture;
+use rust_media_player::pip::{PipOverlay, PipPosition};
+use rust_media_player::player::{Chapter, Player};
+use rust_media_player::recorder::OutputRecorder;
+use rust_media_player::resume::{FileKey, ResumeStore};
+use rust_media_player::subtitle::Subtitles;
+use rust_media_player::subtitle_file::SubtitleFile;
+use rust_media_player::trace::{FrameTrace, TraceRow};
+use rust_media_player::visualizer::{SampleHistory, VisualizationMode, Visualizer};
+use rust_media_player::watchdog::Watchdog;
 
 const AUDIO_BUFFER_SIZE: usize = 16384;
 const AUDIO_SAMPLE_RATE: i32 = 44100;
 const AUDIO_CHANNELS: u8 = 2;
 const AUDIO_SYNC_THRESHOLD: f64 = 0.1;
 const AUDIO_BUFFER_MIN_SIZE: usize = 8192;
+// Taille maximale du buffer audio agrandi après des sous-alimentations, en échantillons par canal
+const AUDIO_BUFFER_MAX_SIZE: usize = 65536;
+// Sous-alimentations audio tolérées avant chaque agrandissement du buffer
+const UNDERRUNS_BEFORE_WIDEN: u64 = 3;
+// Taille demandée pour le buffer du callback SDL, en échantillons par canal
+const AUDIO_DEVICE_SAMPLES: u16 = 4096;
+// Le buffer du lecteur garde au moins autant de callbacks d'avance, et en accumule la moitié avant de démarrer
+const AUDIO_BUFFER_CALLBACKS: usize = 4;
 const VIDEO_SYNC_THRESHOLD: Duration = Duration::from_millis(5);
+// Fréquence d'affichage supposée quand SDL ne connaît pas celle de l'écran
 const TARGET_FPS: f64 = 60.0;
 const SYNC_THRESHOLD: Duration = Duration::from_millis(2);
+// Période de mesure du remplissage audio pour la correction de dérive
+const DRIFT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+// Erreurs de lecture consécutives tolérées avant d'abandonner (ou de se reconnecter)
+const MAX_READ_ERRORS: u32 = 5;
+const READ_RETRY_DELAY: Duration = Duration::from_millis(200);
+// Période des lignes de --progress
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+// Période de rafraîchissement de la visualisation audio
+const VISUALIZATION_INTERVAL: Duration = Duration::from_millis(33);
+// Attente maximale de la fin du son en file à la fin du fichier
+const AUDIO_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+// Taille de la fenêtre quand le fichier ne contient que de l'audio
+const AUDIO_ONLY_WINDOW_SIZE: (u32, u32) = (640, 360);
+// Bornes de --pip-scale
+const MIN_PIP_SCALE: f64 = 0.1;
+const MAX_PIP_SCALE: f64 = 0.5;
+// Bornes de --speed
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 4.0;
+
+struct LoopRange {
+    start: f64,
+    end: f64,
+    // Image clé qui précède le point A: chaque passage y repart, puis les frames jusqu'au point A
+    // sont décodées sans être affichées. None si le démuxeur n'a pas d'index
+    keyframe: Option<f64>,
+    // Nombre de passages avant de reprendre la lecture normale (0 = infini)
+    repeat_count: u32,
+    passes: u32,
+}
+
+impl LoopRange {
+    fn new(start: f64, end: f64, repeat_count: u32, keyframe: Option<f64>) -> Self {
+        Self {
+            start,
+            end,
+            keyframe,
+            repeat_count,
+            passes: 0,
+        }
+    }
+
+    // Enregistre un passage complet, retourne true s'il faut revenir au point A
+    fn complete_pass(&mut self) -> bool {
+        self.passes += 1;
+        self.repeat_count == 0 || self.passes < self.repeat_count
+    }
+
+    // Passages qui restent avant de reprendre la lecture normale, None pour une boucle infinie
+    fn remaining(&self) -> Option<u32> {
+        (self.repeat_count > 0).then(|| self.repeat_count.saturating_sub(self.passes))
+    }
+
+    fn describe(&self) -> String {
+        let remaining = match self.remaining() {
+            Some(remaining) => format!("{} restante(s)", remaining),
+            None => "infinie".to_string(),
+        };
+        let start = match self.keyframe {
+            Some(keyframe) if keyframe < self.start => format!("{:.2}s (image clé {:.2}s)", self.start, keyframe),
+            _ => format!("{:.2}s", self.start),
+        };
+        format!("Boucle A-B [{} - {:.2}s] ({})", start, self.end, remaining)
+    }
+}
 
 struct AudioState {
-    current_time: f64,
+    // Temps média du premier échantillon mis en file depuis le dernier vidage du buffer
+    base_time: Option<f64>,
+    // Échantillons réellement consommés par le callback depuis base_time, mis à jour sans verrou
+    played_samples: Arc<AtomicU64>,
+    samples_per_second: f64,
+}
+
+impl AudioState {
+    // Horloge audio continue, qui avance au rythme du périphérique et non des paquets
+    fn current_time(&self) -> f64 {
+        let played = self.played_samples.load(Ordering::Relaxed) as f64;
+        self.base_time.unwrap_or(0.0) + played / self.samples_per_second
+    }
+
+    fn restart(&mut self, base_time: Option<f64>) {
+        self.base_time = base_time;
+        self.played_samples.store(0, Ordering::Relaxed);
+    }
 }
 
 struct AudioPlayer {
@@ -30,381 +148,2718 @@ struct AudioPlayer {
     channels: u8,
     time_base: f64,
     state: Arc<Mutex<AudioState>>,
+    played_samples: Arc<AtomicU64>,
     sample_rate: i32,
+    // Un bit par canal coupé, appliqué dans le callback
+    muted_channels: u32,
+    // Copie des derniers échantillons joués, pour la visualisation
+    history: SampleHistory,
+    // Copie de la sortie vers un WAV (--record-output)
+    recorder: Option<OutputRecorder>,
+    // Retour au début d'une boucle sans coupure: les prochains échantillons réancrent l'horloge
+    loop_pending: bool,
+    // Seek précis: les échantillons qui se terminent avant cet instant sont écartés
+    skip_until: Option<f64>,
+    // Taille du buffer, et remplissage avant démarrage, en échantillons par canal
+    capacity_frames: usize,
+    primed_frames: usize,
+    // Remplissage et sous-alimentations, publiés sans verrou pour l'affichage et les ajustements
+    stats: Arc<BufferStats>,
+    // Buffer vide sans que ce soit une sous-alimentation: avant le démarrage, après un vidage
+    // volontaire ou une tranche en pause, ou déjà signalé depuis les derniers échantillons reçus
+    underrun_expected: bool,
 }
 
 impl AudioPlayer {
-    fn new(channels: u8, time_base: f64, sample_rate: i32) -> Self {
+    // device_samples: taille du buffer du callback réellement obtenue de SDL
+    fn new(
+        channels: u8,
+        time_base: f64,
+        sample_rate: i32,
+        device_samples: u16,
+        speed: f64,
+        history: SampleHistory,
+        stats: Arc<BufferStats>,
+    ) -> Self {
+        // Un callback plus gros que prévu viderait le buffer d'un coup: la marge suit sa taille
+        let device_samples = usize::from(device_samples);
+        let capacity_frames = AUDIO_BUFFER_SIZE.max(device_samples * AUDIO_BUFFER_CALLBACKS);
+        let primed_frames = AUDIO_BUFFER_MIN_SIZE.max(device_samples * AUDIO_BUFFER_CALLBACKS / 2);
+        let played_samples = Arc::new(AtomicU64::new(0));
+        let state = AudioState {
+            base_time: None,
+            played_samples: played_samples.clone(),
+            // En accéléré, chaque échantillon joué couvre plus de temps média
+            samples_per_second: f64::from(sample_rate) * f64::from(channels) / speed,
+        };
         Self {
-            buffer: VecDeque::with_capacity(AUDIO_BUFFER_SIZE * channels as usize),
+            buffer: VecDeque::with_capacity(capacity_frames * channels as usize),
             channels,
             time_base,
-            state: Arc::new(Mutex::new(AudioState { current_time: 0.0 })),
+            state: Arc::new(Mutex::new(state)),
+            played_samples,
             sample_rate,
+            muted_channels: 0,
+            history,
+            recorder: None,
+            loop_pending: false,
+            skip_until: None,
+            capacity_frames,
+            primed_frames,
+            stats,
+            underrun_expected: true,
         }
     }
 
     fn add_samples(&mut self, samples: &[f32], pts: i64) {
-        let current_time = pts as f64 * self.time_base;
+        // Seul le premier PTS après un vidage sert d'ancrage, l'horloge avance ensuite par comptage
+        let pts_time = pts as f64 * self.time_base;
         if let Ok(mut state) = self.state.lock() {
-            state.current_time = current_time;
+            // Échantillons encore avant le point visé par un seek précis: ils n'ancrent pas l'horloge
+            if let Some(target) = self.skip_until {
+                if pts_time + samples.len() as f64 / state.samples_per_second <= target {
+                    return;
+                }
+                self.skip_until = None;
+            }
+            match state.base_time {
+                None => state.restart(Some(pts_time)),
+                Some(_) if self.loop_pending => {
+                    // La nouvelle passe commence après tout ce qui reste à jouer de la précédente
+                    let queued = state.played_samples.load(Ordering::Relaxed) as f64 + self.buffer.len() as f64;
+                    state.base_time = Some(pts_time - queued / state.samples_per_second);
+                    self.loop_pending = false;
+                }
+                Some(base_time) => {
+                    // Temps média attendu pour ces échantillons: tout ce qui a été joué ou est en file
+                    let queued = state.played_samples.load(Ordering::Relaxed) as f64 + self.buffer.len() as f64;
+                    let expected = base_time + queued / state.samples_per_second;
+                    if is_discontinuity(expected, pts_time) {
+                        println!("Discontinuité audio: {:.3}s attendu, {:.3}s reçu", expected, pts_time);
+                        state.base_time = Some(pts_time - queued / state.samples_per_second);
+                    }
+                }
+            }
         }
 
         // Gestion du buffer avec contrôle de dépassement
-        let buffer_space = AUDIO_BUFFER_SIZE * self.channels as usize - self.buffer.len();
-        let samples_to_add = samples.len().min(buffer_space);
+        let buffer_space = self.capacity().saturating_sub(self.buffer.len());
+        let mut samples_to_add = samples.len().min(buffer_space);
+        // Uniquement des frames entières, sinon les canaux se décaleraient dans le buffer
+        samples_to_add -= samples_to_add % self.channels.max(1) as usize;
 
+        self.underrun_expected = false;
         // Ajouter les échantillons au buffer
         for &sample in samples.iter().take(samples_to_add) {
-            if self.buffer.len() < AUDIO_BUFFER_SIZE * self.channels as usize {
+            if self.buffer.len() < self.capacity() {
                 self.buffer.push_back(sample);
             }
         }
     }
 
+    // Remplace le contenu du buffer par une tranche complète, sans limite de taille
+    fn load_slice(&mut self, samples: &[f32], start_time: f64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.restart(Some(start_time));
+        }
+        self.buffer.clear();
+        self.buffer.extend(samples.iter().copied());
+        // La tranche se termine volontairement par un buffer vide
+        self.underrun_expected = true;
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity_frames * self.channels as usize
+    }
+
+    // Double la taille du buffer et le remplissage avant démarrage, jusqu'à AUDIO_BUFFER_MAX_SIZE.
+    // Renvoie la nouvelle taille par canal, None si le maximum est déjà atteint
+    fn widen(&mut self) -> Option<usize> {
+        if self.capacity_frames >= AUDIO_BUFFER_MAX_SIZE {
+            return None;
+        }
+        self.capacity_frames = (self.capacity_frames * 2).min(AUDIO_BUFFER_MAX_SIZE);
+        self.primed_frames = self.capacity_frames / 2;
+        self.buffer.reserve(self.capacity().saturating_sub(self.buffer.len()));
+        Some(self.capacity_frames)
+    }
+
+    // Sans vidéo pour cadencer la lecture, on attend que le buffer se vide à moitié
+    fn has_enough_buffered(&self) -> bool {
+        self.buffer.len() >= self.capacity() / 2
+    }
+
     fn get_state(&self) -> Arc<Mutex<AudioState>> {
         self.state.clone()
     }
+
+    fn set_muted_channels(&mut self, muted_channels: u32) {
+        self.muted_channels = muted_channels;
+    }
+
+    fn set_recorder(&mut self, recorder: OutputRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    fn mark_loop(&mut self) {
+        self.loop_pending = true;
+    }
+
+    fn skip_until(&mut self, target: f64) {
+        self.skip_until = Some(target);
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.loop_pending = false;
+        self.skip_until = None;
+        self.underrun_expected = true;
+        if let Ok(mut state) = self.state.lock() {
+            state.restart(None);
+        }
+    }
 }
 
 impl AudioCallback for AudioPlayer {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
-        for sample in out.iter_mut() {
+        let channels = self.channels.max(1) as usize;
+        let mut played = 0;
+        // out et le buffer commencent tous deux sur une frame entière: l'index donne le canal
+        for (index, sample) in out.iter_mut().enumerate() {
             if self.buffer.is_empty() {
                 *sample = 0.0;
             } else {
                 *sample = self.buffer.pop_front().unwrap();
+                if self.muted_channels & (1 << (index % channels)) != 0 {
+                    *sample = 0.0;
+                }
+                played += 1;
             }
         }
+        self.played_samples.fetch_add(played, Ordering::Relaxed);
+        // Une seule sous-alimentation comptée tant que de nouveaux échantillons n'arrivent pas
+        if played < out.len() as u64 && !self.underrun_expected {
+            self.underrun_expected = true;
+            self.stats.record_underrun();
+        }
+        let samples_per_second = f64::from(self.sample_rate) * channels as f64;
+        self.stats.record_audio_fill(self.buffer.len(), self.capacity(), samples_per_second);
+        visualizer::record(&self.history, out, channels);
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record(out);
+        }
     }
 }
 
-struct Decoder {
-    decoder: ffmpeg::codec::decoder::Video,
-    scaler: ScalingContext,
-    time_base: f64,
-    frame_rate: f64,
-    start_time: Option<Instant>,
-    frame_duration: Duration,
-    frame_count: u64,
-    last_frame_time: Option<Instant>,
-    next_frame_target: Option<Instant>,
-    total_drift: Duration,
-}
-
-impl Decoder {
-    fn new(decoder: ffmpeg::codec::decoder::Video, stream: &ffmpeg::Stream) -> Result<Self> {
-        let time_base = f64::from(stream.time_base());
-        let frame_rate = f64::from(stream.rate());
-        let frame_duration = Duration::from_secs_f64(1.0 / frame_rate);
-
-        println!("Initialisation décodeur vidéo:");
-        println!("  Time base: {}", time_base);
-        println!("  Frame rate: {} fps", frame_rate);
-        println!("  Frame duration: {:?}", frame_duration);
-
-        let scaler = ScalingContext::get(
-            decoder.format(),
-            decoder.width(),
-            decoder.height(),
-            Pixel::YUV420P,
-            decoder.width(),
-            decoder.height(),
-            Flags::BILINEAR,
-        )?;
+// Assez de sous-alimentations depuis le dernier agrandissement pour agrandir encore le buffer audio
+fn widen_due(underruns: u64, handled_underruns: u64) -> bool {
+    underruns >= handled_underruns + UNDERRUNS_BEFORE_WIDEN
+}
 
-        Ok(Self {
-            decoder,
-            scaler,
-            time_base,
-            frame_rate,
-            start_time: None,
-            frame_duration,
-            frame_count: 0,
-            last_frame_time: None,
-            next_frame_target: None,
-            total_drift: Duration::ZERO,
-        })
+// "-" lit le média sur l'entrée standard, au fil de l'eau: la mémoire reste bornée quelle que soit
+// la durée, mais l'entrée n'est pas positionnable (seekable() faux)
+fn open_player(options: &Options) -> Result<Player> {
+    if options.video_path != "-" {
+        return Ok(Player::open(options)?);
     }
+    println!("Entrée standard: lecture en continu, sans recherche");
+    Ok(Player::open_reader(avio::Sequential(std::io::stdin()), options)?)
+}
 
-    fn receive_frame_yuv(&mut self, frame: &mut Video) -> Result<bool> {
-        match self.decoder.receive_frame(frame) {
-            Ok(_) => {
-                let mut yuv_frame = Video::empty();
-                self.scaler.run(frame, &mut yuv_frame)?;
-                frame.clone_from(&yuv_frame);
-                Ok(true)
-            }
-            Err(ffmpeg::Error::Other { errno: ffmpeg::error::EAGAIN }) => Ok(false),
-            Err(e) => Err(e.into()),
-        }
+fn init_ffmpeg() -> Result<()> {
+    ffmpeg::init()?;
+    Ok(())
+}
+
+// Repositionne la lecture et vide les décodeurs ainsi que le buffer audio
+fn seek_to(
+    ictx: &mut ffmpeg::format::context::Input,
+    decoder: Option<&mut Decoder>,
+    pip: &mut Option<PipOverlay>,
+    audio_decoder: &mut Option<ffmpeg::codec::decoder::Audio>,
+    audio_device: &mut Option<(AudioDevice<AudioPlayer>, Arc<Mutex<AudioState>>)>,
+    mpris: Option<&Mpris>,
+    seconds: f64,
+) -> Result<()> {
+    let timestamp = (seconds / f64::from(ffmpeg::rescale::TIME_BASE)) as i64;
+    ictx.seek(timestamp, ..timestamp)
+        .with_context(|| format!("Impossible de se positionner à {:.2}s", seconds))?;
+
+    if let Some(decoder) = decoder {
+        decoder.flush();
+        decoder.reset_clock();
+    }
+    if let Some(ref mut pip) = pip {
+        pip.flush();
+    }
+    if let Some(ref mut audio_dec) = audio_decoder {
+        audio_dec.flush();
+    }
+    if let Some((ref mut device, _)) = audio_device {
+        // Le buffer vidé doit être réamorcé avant de reprendre la lecture
+        device.pause();
+        device.lock().clear();
+    }
+    if let Some(mpris) = mpris {
+        mpris.seeked(seconds);
+    }
+    Ok(())
+}
+
+// Ne démarre le périphérique qu'une fois primed_frames échantillons par canal en file:
+// sinon le callback commence par du silence (clic, sous-alimentation) au démarrage ou après un seek
+fn resume_when_primed(device: &mut AudioDevice<AudioPlayer>) {
+    if device.status() == AudioStatus::Playing {
+        return;
+    }
+    let primed = {
+        let audio_player = device.lock();
+        audio_player.buffer.len() >= audio_player.primed_frames * audio_player.channels as usize
+    };
+    if primed {
+        device.resume();
     }
+}
 
-    fn should_display_frame(&mut self, pts: i64) -> bool {
-        let now = Instant::now();
+// Décode l'audio de l'intervalle demandé, pour l'avance pas à pas en pause
+fn decode_audio_slice(
+    ictx: &mut ffmpeg::format::context::Input,
+    audio_stream_index: usize,
+    audio_dec: &mut ffmpeg::codec::decoder::Audio,
+    resampler: &mut AudioResampler,
+    converter: &SampleConverter,
+    volume: f32,
+    window: Range<f64>,
+) -> Result<Vec<f32>> {
+    let time_base = ictx
+        .stream(audio_stream_index)
+        .map(|stream| f64::from(stream.time_base()))
+        .context("Flux audio introuvable")?;
+    let rate = f64::from(resampler.output_rate());
+    let channels = resampler.output_channels() as usize;
+    let wanted = ((window.end - window.start) * rate) as usize * channels;
 
-        if self.start_time.is_none() {
-            self.start_time = Some(now);
-            self.last_frame_time = Some(now);
-            self.next_frame_target = Some(now + self.frame_duration);
-            println!("Première frame - Démarrage à {:?}", now);
-            return true;
-        }
+    let mut slice = Vec::with_capacity(wanted);
+    let mut audio_frame = ffmpeg::frame::Audio::empty();
+    let mut samples = Vec::new();
 
-        // Calculer le temps vidéo en utilisant le time_base (1/16000)
-        let video_time = Duration::from_secs_f64(pts as f64 * self.time_base);
-        let elapsed = self.start_time.unwrap().elapsed();
+    while slice.len() < wanted {
+        let packet = match player::read_packet(ictx)? {
+            Some(packet) => packet,
+            None => break,
+        };
+        if packet.stream() != audio_stream_index {
+            continue;
+        }
 
-        // Vérifier si nous avons atteint le temps cible pour la prochaine frame
-        let target_time = self.next_frame_target.unwrap();
-        if now < target_time {
-            // Trop tôt pour afficher la frame suivante
-            std::thread::sleep(target_time.duration_since(now));
-            return false;
+        audio_dec.send_packet(&packet)?;
+        while audio_dec.receive_frame(&mut audio_frame).is_ok() {
+            resampler.process(converter, &audio_frame, volume, &mut samples)?;
+            let pts = presentation_pts(audio_frame.timestamp(), audio_frame.pts(), packet.pts());
+            let frame_time = pts.unwrap_or(0) as f64 * time_base;
+            // Le seek s'arrête sur le paquet précédent: on saute ce qui précède le début
+            let skip = ((window.start - frame_time).max(0.0) * rate) as usize * channels;
+            slice.extend_from_slice(&samples[skip.min(samples.len())..]);
         }
+    }
+
+    slice.truncate(wanted);
+    Ok(slice)
+}
+
+// Mode sans affichage: décode toute la vidéo et liste les changements de plan
+fn run_scene_detect(ictx: &mut ffmpeg::format::context::Input, decoder: &mut Decoder, threshold: f64) -> Result<()> {
+    let mut frame = Video::empty();
+    let mut detector = SceneDetector::new(threshold);
+    let mut detected = 0;
 
-        // Calculer l'intervalle depuis la dernière frame
-        let frame_interval = if let Some(last) = self.last_frame_time {
-            now.duration_since(last)
-        } else {
-            Duration::ZERO
+    loop {
+        let finished = match player::read_packet(ictx)? {
+            Some(packet) => {
+                if packet.stream() != decoder.stream_index {
+                    continue;
+                }
+                decoder.decoder.send_packet(&packet)?;
+                false
+            }
+            None => {
+                // Vide les frames encore retenues par le décodeur
+                decoder.decoder.send_eof()?;
+                true
+            }
         };
 
-        // Mettre à jour les compteurs
-        self.frame_count += 1;
-        self.last_frame_time = Some(now);
-        self.next_frame_target = Some(target_time + self.frame_duration);
-
-        // Log toutes les 30 frames
-        if self.frame_count % 30 == 0 {
-            let current_fps = 1.0 / frame_interval.as_secs_f64();
-            println!("Frame {} - Stats:", self.frame_count);
-            println!("  Intervalle: {:.2}ms", frame_interval.as_secs_f64() * 1000.0);
-            println!("  FPS actuel: {:.2}", current_fps);
-            println!("  Temps vidéo: {:.2}ms", video_time.as_secs_f64() * 1000.0);
-            println!("  Temps réel: {:.2}ms", elapsed.as_secs_f64() * 1000.0);
-            println!("  PTS: {}", pts);
-
-            if elapsed > video_time {
-                println!("  Retard: {:.2}ms", (elapsed - video_time).as_secs_f64() * 1000.0);
-            } else {
-                println!("  Avance: {:.2}ms", (video_time - elapsed).as_secs_f64() * 1000.0);
+        while decoder.receive_frame_yuv(&mut frame)? {
+            let luma = copy_luma(frame.data(0), frame.stride(0), frame.width() as usize, frame.height() as usize);
+            if let Some(score) = detector.push(luma) {
+                let seconds = frame.pts().unwrap_or(0) as f64 * decoder.time_base;
+                println!("{:.3}: {:.4}", seconds, score);
+                detected += 1;
             }
         }
 
-        true
+        if finished {
+            break;
+        }
     }
-}
 
-fn init_ffmpeg() -> Result<()> {
-    ffmpeg::init()?;
+    eprintln!("{} changement(s) de plan détecté(s)", detected);
     Ok(())
 }
 
-fn open_decoders(path: &str) -> Result<(ffmpeg::format::context::Input, Decoder, Option<ffmpeg::codec::decoder::Audio>)> {
-    let ictx = ffmpeg::format::input(&path)?;
+// Compare chaque plan Y au précédent (--scene-detect)
+struct SceneDetector {
+    threshold: f64,
+    previous: Option<Vec<u8>>,
+}
+
+impl SceneDetector {
+    fn new(threshold: f64) -> Self {
+        Self { threshold, previous: None }
+    }
+
+    // Score de la frame si elle marque un changement de plan, None sinon et pour la première
+    fn push(&mut self, luma: Vec<u8>) -> Option<f64> {
+        let score = self.previous.as_ref().map(|previous| luma_difference(previous, &luma));
+        self.previous = Some(luma);
+        score.filter(|&score| score > self.threshold)
+    }
+}
 
-    let video_stream = ictx
-        .streams()
-        .best(ffmpeg::media::Type::Video)
-        .context("Aucun flux vidéo trouvé")?;
+// Copie le plan Y sans le padding de fin de ligne
+fn copy_luma(plane: &[u8], stride: usize, width: usize, height: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(width * height);
+    for row in plane.chunks(stride).take(height) {
+        output.extend_from_slice(&row[..width]);
+    }
+    output
+}
 
-    println!("Information flux vidéo:");
-    println!("  Time base: {}", video_stream.time_base());
-    println!("  Frame rate: {}", video_stream.rate());
-    println!("  Duration: {} secondes", video_stream.duration() as f64 * f64::from(video_stream.time_base()));
+// Différence absolue moyenne entre deux plans Y, ramenée entre 0 et 1
+fn luma_difference(previous: &[u8], current: &[u8]) -> f64 {
+    if previous.len() != current.len() || current.is_empty() {
+        return 1.0;
+    }
+    let total: u64 = previous
+        .iter()
+        .zip(current)
+        .map(|(&a, &b)| u64::from(a.abs_diff(b)))
+        .sum();
+    total as f64 / (current.len() as f64 * 255.0)
+}
 
-    let context = ffmpeg::codec::Context::from_parameters(video_stream.parameters())?;
-    let codec_id = context.id();
-    println!("  Codec: {:?}", codec_id);
+// Mode sans affichage: décode toute la piste audio et l'écrit dans un fichier WAV en f32
+fn run_audio_dump(
+    ictx: &mut ffmpeg::format::context::Input,
+    audio_dec: &mut ffmpeg::codec::decoder::Audio,
+    audio_stream_index: usize,
+    options: &Options,
+    path: &str,
+) -> Result<()> {
+    let rate = options.wav_rate.unwrap_or_else(|| audio_dec.rate());
+    let channels = options.wav_channels.unwrap_or_else(|| audio_dec.channels());
 
-    // Liste des décodeurs matériels pour H.264 et H.265
-    let hw_decoders = match codec_id {
-        ffmpeg::codec::id::Id::H264 => vec!["h264_nvdec", "h264_vaapi", "h264_qsv"],
-        ffmpeg::codec::id::Id::HEVC => vec!["hevc_nvdec", "hevc_vaapi", "hevc_qsv"],
-        ffmpeg::codec::id::Id::AV1 => vec!["av1_nvdec", "av1_vaapi", "av1_qsv"],
-        _ => vec![],
+    let mut resampler = AudioResampler::new(audio_dec, rate, channels)?;
+    if let Some(ref spec) = options.audio_filter {
+        resampler.set_filter(spec)?;
+    }
+    let converter = SampleConverter::new();
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate: rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
     };
+    let mut writer = hound::WavWriter::create(path, spec)
+        .with_context(|| format!("Impossible de créer {}", path))?;
+
+    let mut audio_frame = ffmpeg::frame::Audio::empty();
+    let mut samples = Vec::new();
+    loop {
+        let finished = match player::read_packet(ictx)? {
+            Some(packet) => {
+                if packet.stream() != audio_stream_index {
+                    continue;
+                }
+                audio_dec.send_packet(&packet)?;
+                false
+            }
+            None => {
+                // Sans send_eof, les dernières frames resteraient dans le décodeur
+                audio_dec.send_eof()?;
+                true
+            }
+        };
 
-    let mut found_hw_decoder = false;
-    let mut decoder_name = "";
+        while audio_dec.receive_frame(&mut audio_frame).is_ok() {
+            resampler.process(&converter, &audio_frame, 1.0, &mut samples)?;
+            for &sample in &samples {
+                writer.write_sample(sample)?;
+            }
+        }
 
-    for &name in hw_decoders.iter() {
-        if let Some(_) = ffmpeg::codec::decoder::find_by_name(name) {
-            println!("Décodeur matériel trouvé: {}", name);
-            found_hw_decoder = true;
-            decoder_name = name;
+        if finished {
             break;
         }
     }
 
-    if !found_hw_decoder {
-        println!("Aucun décodeur matériel disponible, utilisation du décodage logiciel");
+    resampler.drain(&converter, 1.0, &mut samples)?;
+    for &sample in &samples {
+        writer.write_sample(sample)?;
     }
 
-    let video_decoder = context.decoder().video()?;
-    let decoder = Decoder::new(video_decoder, &video_stream)?;
+    // finalize() réécrit l'en-tête avec le nombre d'échantillons final
+    let frames = writer.duration();
+    writer.finalize()?;
+    println!(
+        "Audio extrait dans {}: {:.2}s, {} Hz, {} canaux",
+        path,
+        f64::from(frames) / f64::from(rate),
+        rate,
+        channels
+    );
+    Ok(())
+}
 
-    let audio_decoder = ictx
-        .streams()
-        .best(ffmpeg::media::Type::Audio)
-        .and_then(|stream| {
-            println!("Information flux audio:");
-            println!("  Time base: {}", stream.time_base());
-            let context = ffmpeg::codec::Context::from_parameters(stream.parameters()).ok()?;
-            let audio_dec = context.decoder().audio().ok()?;
-            let sample_rate = audio_dec.rate() as i32;
-            println!("  Channels: {}", audio_dec.channels());
-            println!("  Sample format: {:?}", audio_dec.format());
-            println!("  Sample rate: {} Hz", sample_rate);
-            Some((audio_dec, sample_rate))
-        });
+// Mesure EBU R128 sur tout le fichier: sonie intégrée, plage de sonie et true peak
+fn run_loudness(
+    ictx: &mut ffmpeg::format::context::Input,
+    audio_dec: &mut ffmpeg::codec::decoder::Audio,
+    audio_stream_index: usize,
+) -> Result<()> {
+    let rate = audio_dec.rate();
+    let channels = audio_dec.channels();
 
-    Ok((ictx, decoder, audio_decoder.map(|(dec, _)| dec)))
-}
+    // Conversion en f32 entrelacé sans changer la fréquence, pour ne pas fausser le true peak
+    let mut resampler = AudioResampler::new(audio_dec, rate, channels)?;
+    let converter = SampleConverter::new();
+    let mut meter = loudness_meter(channels, rate)?;
 
-fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} <chemin_video>", args[0]);
-        std::process::exit(1);
+    let mut audio_frame = ffmpeg::frame::Audio::empty();
+    let mut samples = Vec::new();
+    loop {
+        let finished = match player::read_packet(ictx)? {
+            Some(packet) => {
+                if packet.stream() != audio_stream_index {
+                    continue;
+                }
+                audio_dec.send_packet(&packet)?;
+                false
+            }
+            None => {
+                audio_dec.send_eof()?;
+                true
+            }
+        };
+
+        while audio_dec.receive_frame(&mut audio_frame).is_ok() {
+            resampler.process(&converter, &audio_frame, 1.0, &mut samples)?;
+            meter.add_frames_f32(&samples)?;
+        }
+
+        if finished {
+            break;
+        }
     }
-    let video_path = &args[1];
 
-    init_ffmpeg()?;
+    resampler.drain(&converter, 1.0, &mut samples)?;
+    meter.add_frames_f32(&samples)?;
 
-    let (mut ictx, mut decoder, mut audio_decoder) = open_decoders(video_path)?;
-    let video_stream_index = ictx
-        .streams()
-        .best(ffmpeg::media::Type::Video)
-        .context("Aucun flux vidéo trouvé")?
-        .index();
+    let loudness = Loudness::measure(&meter, channels)?;
+    println!("Sonie intégrée: {:.1} LUFS", loudness.integrated);
+    println!("Plage de sonie: {:.1} LU", loudness.range);
+    println!("True peak: {:.1} dBTP", loudness.true_peak);
+    Ok(())
+}
 
-    let audio_stream_index = ictx
-        .streams()
-        .best(ffmpeg::media::Type::Audio)
-        .map(|stream| stream.index());
+fn loudness_meter(channels: u16, rate: u32) -> Result<ebur128::EbuR128> {
+    Ok(ebur128::EbuR128::new(
+        u32::from(channels),
+        rate,
+        ebur128::Mode::I | ebur128::Mode::LRA | ebur128::Mode::TRUE_PEAK,
+    )?)
+}
 
-    let sdl_context = sdl2::init().map_err(|e| anyhow::anyhow!(e))?;
-    let video_subsystem = sdl_context.video().map_err(|e| anyhow::anyhow!(e))?;
-    let audio_subsystem = sdl_context.audio().map_err(|e| anyhow::anyhow!(e))?;
+// Résultat de --loudness: LUFS, LU et dBTP (plus haut true peak de tous les canaux)
+struct Loudness {
+    integrated: f64,
+    range: f64,
+    true_peak: f64,
+}
 
-    let mut audio_device = if let Some(ref audio_dec) = audio_decoder {
-        let channels = audio_dec.channels() as u8;
-        let audio_stream = ictx
-            .streams()
-            .best(ffmpeg::media::Type::Audio)
-            .context("No audio stream found")?;
-        let audio_time_base = f64::from(audio_stream.time_base());
-        let sample_rate = audio_dec.rate() as i32;
+impl Loudness {
+    fn measure(meter: &ebur128::EbuR128, channels: u16) -> Result<Self> {
+        let mut true_peak: f64 = 0.0;
+        for channel in 0..u32::from(channels) {
+            true_peak = true_peak.max(meter.true_peak(channel)?);
+        }
+        Ok(Self {
+            integrated: meter.loudness_global()?,
+            range: meter.loudness_range()?,
+            true_peak: 20.0 * true_peak.log10(),
+        })
+    }
+}
 
-        println!("Configuration audio:");
-        println!("  Channels: {}", channels);
-        println!("  Sample rate: {} Hz", sample_rate);
-        println!("  Buffer size: {}", AUDIO_BUFFER_SIZE);
+// Mode sans affichage: chaque frame en RGBA brut, à la suite, comme ffmpeg -f rawvideo -pix_fmt rgba.
+// Passe par Player::render_to, le rendu hors fenêtre offert aux applications hôtes
+fn run_frame_dump(mut player: Player, path: &str) -> Result<()> {
+    let (width, height) = player.video_size().context("L'export des frames nécessite un flux vidéo")?;
+    let stride = width as usize * 4;
+    let mut buffer = vec![0; stride * height as usize];
+    let file = File::create(path).with_context(|| format!("Impossible de créer {}", path))?;
+    let mut output = BufWriter::new(file);
 
-        let desired_spec = AudioSpecDesired {
-            freq: Some(sample_rate),
-            channels: Some(channels),
-            samples: Some(4096),
-        };
+    let mut frames = 0u64;
+    while player.render_to(&mut buffer, stride)? {
+        output.write_all(&buffer)?;
+        frames += 1;
+    }
+    output.flush()?;
+    println!("{} frames {}x{} RGBA écrites dans {}", frames, width, height, path);
+    Ok(())
+}
 
-        let audio_player = AudioPlayer::new(channels, audio_time_base, sample_rate);
-        let audio_state = audio_player.get_state();
-        let device = audio_subsystem.open_playback(None, &desired_spec, |_| audio_player)
-            .map_err(|e| anyhow::anyhow!(e))?;
-        Some((device, audio_state))
-    } else {
-        None
-    };
+// Mode sans affichage: miniature de Player::poster_frame en PPM
+fn run_thumbnail(mut player: Player, path: &str) -> Result<()> {
+    let poster = player.poster_frame()?;
+    let file = File::create(path).with_context(|| format!("Impossible de créer {}", path))?;
+    let mut output = BufWriter::new(file);
+    poster.write_ppm(&mut output)?;
+    output.flush()?;
+    println!("Miniature {}x{} écrite dans {}", poster.width, poster.height, path);
+    Ok(())
+}
 
-    let window = video_subsystem
-        .window("Lecteur Vidéo Rust", decoder.decoder.width() as u32, decoder.decoder.height() as u32)
-        .position_centered()
-        .build()
-        .map_err(|e| anyhow::anyhow!(e))?;
+// Horloge audio simulée: comme AudioState, elle avance au rythme du périphérique depuis un ancrage,
+// réancré sur une discontinuité de PTS. Chaque ancrage est (instant de lecture, temps média)
+struct SimulatedAudioClock {
+    anchors: Vec<(f64, f64)>,
+    // Durée de lecture de tout l'audio décodé jusqu'ici
+    queued: f64,
+    speed: f64,
+}
 
-    let mut canvas = window.into_canvas()
-        .build()
-        .map_err(|e| anyhow::anyhow!(e))?;
+impl SimulatedAudioClock {
+    fn push(&mut self, pts_time: f64, duration: f64) {
+        let expected = self
+            .anchors
+            .last()
+            .map(|&(wall, media)| media + (self.queued - wall) * self.speed);
+        let continuous = expected.is_some_and(|expected| !is_discontinuity(expected, pts_time));
+        if !continuous {
+            self.anchors.push((self.queued, pts_time));
+        }
+        self.queued += duration / self.speed;
+    }
 
-    canvas.set_draw_color(sdl2::pixels::Color::BLACK);
-    canvas.clear();
-    canvas.present();
+    fn at(&self, wall: f64) -> Option<f64> {
+        let index = self.anchors.partition_point(|&(start, _)| start <= wall);
+        let (start, media) = self.anchors[index.checked_sub(1)?];
+        Some(media + (wall - start) * self.speed)
+    }
+}
 
-    let texture_creator = canvas.texture_creator();
-    let mut texture = texture_creator
-        .create_texture_streaming(
-            PixelFormatEnum::IYUV,
-            decoder.decoder.width() as u32,
-            decoder.decoder.height() as u32
-        )
-        .map_err(|e| anyhow::anyhow!(e))?;
+// Réception d'une frame: EAGAIN (paquet suivant attendu) et la fin du flux ne sont pas des erreurs
+fn frame_received(result: Result<(), ffmpeg::Error>) -> Result<bool> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(ffmpeg::Error::Other { errno: ffmpeg::error::EAGAIN }) | Err(ffmpeg::Error::Eof) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
 
-    let mut event_pump = sdl_context.event_pump().map_err(|e| anyhow::anyhow!(e))?;
+// Rejoue l'affichage de frames décodées instantanément par Decoder::display_frame_at, sur une horloge
+// qui n'avance que pendant les attentes. Renvoie (instant d'affichage, temps média) de chaque frame
+// affichée et le nombre de frames abandonnées, hors sous-échantillonnage
+fn simulate_display(decoder: &mut Decoder, frames: &[i64]) -> (Vec<(f64, f64)>, u64) {
+    let start = Instant::now();
+    let mut now = start;
+    let mut displayed = Vec::new();
+    let mut dropped = 0;
+    for &pts in frames {
+        let decimated = decoder.decimated_frames;
+        let mut reached = now;
+        let shown = decoder.display_frame_at(pts, now, |target, max_wait| {
+            reached = target.min(now + max_wait);
+            reached
+        });
+        now = reached;
+        if shown {
+            displayed.push(((now - start).as_secs_f64(), pts as f64 * decoder.time_base));
+        } else if decoder.decimated_frames == decimated {
+            dropped += 1;
+        }
+    }
+    (displayed, dropped)
+}
 
+// Mode sans affichage: décode les deux flux et simule la lecture, chaque frame passant par la même
+// décision d'affichage que le lecteur, puis comparée à l'horloge audio. Le décodage est supposé
+// instantané et la latence de démarrage du périphérique ignorée: seul l'écart dû aux horodatages du
+// fichier est mesuré
+fn run_verify_sync(
+    ictx: &mut ffmpeg::format::context::Input,
+    decoder: &mut Decoder,
+    audio_dec: &mut ffmpeg::codec::decoder::Audio,
+    audio_stream_index: usize,
+    speed: f64,
+) -> Result<()> {
+    let audio_time_base = f64::from(
+        ictx.stream(audio_stream_index)
+            .context("Flux audio introuvable")?
+            .time_base(),
+    );
+    let mut audio_clock = SimulatedAudioClock { anchors: Vec::new(), queued: 0.0, speed };
+    // PTS de chaque frame vidéo, dans l'ordre d'affichage
+    let mut video_frames = Vec::new();
     let mut frame = Video::empty();
     let mut audio_frame = ffmpeg::frame::Audio::empty();
 
-    if let Some((ref device, _)) = audio_device {
-        device.resume();
+    loop {
+        let finished = match player::read_packet(ictx)? {
+            Some(packet) if packet.stream() == decoder.stream_index => {
+                decoder.decoder.send_packet(&packet)?;
+                false
+            }
+            Some(packet) if packet.stream() == audio_stream_index => {
+                audio_dec.send_packet(&packet)?;
+                false
+            }
+            Some(_) => continue,
+            None => {
+                decoder.decoder.send_eof()?;
+                audio_dec.send_eof()?;
+                true
+            }
+        };
+
+        // Sans conversion ni rapatriement: seuls les horodatages comptent
+        while frame_received(decoder.decoder.receive_frame(&mut frame))? {
+            video_frames.extend(frame.timestamp());
+        }
+        while frame_received(audio_dec.receive_frame(&mut audio_frame))? {
+            if let Some(pts) = audio_frame.timestamp() {
+                let duration = audio_frame.samples() as f64 / f64::from(audio_frame.rate().max(1));
+                audio_clock.push(pts as f64 * audio_time_base, duration);
+            }
+        }
+
+        if finished {
+            break;
+        }
     }
 
-    'running: loop {
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } |
-                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                    break 'running;
+    decoder.log_stats = false;
+    let (displayed, dropped) = simulate_display(decoder, &video_frames);
+
+    // Écart vidéo - audio à chaque frame affichée: positif, la vidéo est en avance sur le son;
+    // négatif, elle est en retard
+    let deltas: Vec<f64> = displayed
+        .iter()
+        .filter_map(|&(wall, pts_time)| audio_clock.at(wall).map(|audio| pts_time - audio))
+        .collect();
+    if deltas.is_empty() {
+        anyhow::bail!("Aucune frame vidéo horodatée pendant la lecture audio: rien à comparer");
+    }
+    let count = deltas.len() as f64;
+    let mean = deltas.iter().sum::<f64>() / count;
+    let variance = deltas.iter().map(|delta| (delta - mean).powi(2)).sum::<f64>() / count;
+    let min = deltas.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = deltas.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    // Le lecteur ne corrige pas la vidéo sur le son: le seuil ne sert qu'au verdict
+    let in_sync = dropped == 0 && min >= -AUDIO_SYNC_THRESHOLD && max <= AUDIO_SYNC_THRESHOLD;
+
+    // JSON sur une ligne, pour les scripts
+    println!(
+        "{{\"video_frames\":{},\"compared_frames\":{},\"audio_discontinuities\":{},\"threshold\":{},\
+         \"delta\":{{\"min\":{:.6},\"max\":{:.6},\"mean\":{:.6},\"stddev\":{:.6}}},\
+         \"dropped\":{},\"in_sync\":{}}}",
+        video_frames.len(),
+        deltas.len(),
+        audio_clock.anchors.len().saturating_sub(1),
+        AUDIO_SYNC_THRESHOLD,
+        min,
+        max,
+        mean,
+        variance.sqrt(),
+        dropped,
+        in_sync
+    );
+    Ok(())
+}
+
+// Erreurs relevées par --null-output, avec l'instant de la première s'il est connu
+struct DecodeErrors {
+    count: u64,
+    first: Option<f64>,
+}
+
+impl DecodeErrors {
+    fn record(&mut self, what: &str, time: Option<f64>, error: &dyn std::fmt::Display) {
+        self.count += 1;
+        match time {
+            Some(time) => eprintln!("Erreur {} à {:.3}s: {}", what, time, error),
+            None => eprintln!("Erreur {}: {}", what, error),
+        }
+        if self.count == 1 {
+            self.first = time;
+        }
+    }
+
+    // Envoi d'un paquet (ou de la fin du flux) puis réception des frames prêtes. Chaque erreur est
+    // relevée sans arrêter le décodage; EAGAIN et la fin du flux n'en sont pas. Renvoie le nombre
+    // de frames reçues
+    fn decode(
+        &mut self,
+        what: &str,
+        time: Option<f64>,
+        sent: Result<(), ffmpeg::Error>,
+        mut receive: impl FnMut() -> Result<(), ffmpeg::Error>,
+    ) -> u64 {
+        if let Err(e) = sent {
+            self.record(what, time, &e);
+        }
+        let mut frames = 0;
+        loop {
+            match receive() {
+                Ok(()) => frames += 1,
+                Err(ffmpeg::Error::Other { errno: ffmpeg::error::EAGAIN }) | Err(ffmpeg::Error::Eof) => break,
+                Err(e) => {
+                    self.record(what, time, &e);
+                    break;
                 }
-                _ => {}
             }
         }
+        frames
+    }
 
-        match ictx.packets().next() {
-            Some((stream, packet)) => {
-                if stream.index() == video_stream_index {
-                    let packet_pts = packet.pts().unwrap_or(0);
-                    decoder.decoder.send_packet(&packet)?;
-
-                    if decoder.receive_frame_yuv(&mut frame)? {
-                        if decoder.should_display_frame(packet_pts) {
-                            texture.update_yuv(
-                                None,
-                                frame.data(0),
-                                frame.stride(0),
-                                frame.data(1),
-                                frame.stride(1),
-                                frame.data(2),
-                                frame.stride(2)
-                            ).map_err(|e| anyhow::anyhow!(e))?;
-
-                            canvas.clear();
-                            canvas.copy(&texture, None, None)
-                                .map_err(|e| anyhow::anyhow!(e))?;
-                            canvas.present();
-                        }
-                    }
-                } else if Some(stream.index()) == audio_stream_index {
-                    if let Some(ref mut audio_dec) = audio_decoder {
-                        audio_dec.send_packet(&packet)?;
-
-                        while audio_dec.receive_frame(&mut audio_frame).is_ok() {
-                            if let Some((ref mut device, _)) = audio_device {
-                                let mut audio_player = device.lock();
-                                let samples = audio_frame.plane::<f32>(0);
-                                let pts = packet.pts().unwrap_or(0);
-                                audio_player.add_samples(samples, pts);
-                            }
-                        }
-                    }
+    // Résumé de fin, et code de sortie non nul s'il y a eu des erreurs
+    fn finish(&self, video_frames: u64, audio_frames: u64) -> Result<()> {
+        println!("Frames vidéo décodées: {}", video_frames);
+        println!("Frames audio décodées: {}", audio_frames);
+        println!("Erreurs: {}", self.count);
+        if let Some(first) = self.first {
+            println!("Première erreur: {:.3}s", first);
+        }
+        if self.count > 0 {
+            anyhow::bail!("{} erreur(s) de décodage", self.count);
+        }
+        Ok(())
+    }
+}
+
+// Mode sans affichage: décode entièrement la vidéo et l'audio sans rien afficher ni jouer, pour
+// vérifier qu'un fichier est lisible de bout en bout. Les erreurs sont relevées sans arrêter le
+// décodage, et le code de sortie est non nul s'il y en a eu
+fn run_null_output(
+    ictx: &mut ffmpeg::format::context::Input,
+    mut decoder: Option<&mut Decoder>,
+    mut audio: Option<(&mut ffmpeg::codec::decoder::Audio, usize)>,
+) -> Result<()> {
+    let audio_time_base = match audio {
+        Some((_, index)) => f64::from(ictx.stream(index).context("Flux audio introuvable")?.time_base()),
+        None => 0.0,
+    };
+    let mut errors = DecodeErrors { count: 0, first: None };
+    let mut video_frames = 0u64;
+    let mut audio_frames = 0u64;
+    let mut read_errors = 0u32;
+    let mut frame = Video::empty();
+    let mut audio_frame = ffmpeg::frame::Audio::empty();
+
+    loop {
+        // Mêmes tentatives que la lecture normale avant de considérer le fichier tronqué
+        let packet = match player::read_packet(ictx) {
+            Ok(packet) => {
+                read_errors = 0;
+                packet
+            }
+            Err(e) => {
+                read_errors += 1;
+                if read_errors < MAX_READ_ERRORS {
+                    std::thread::sleep(READ_RETRY_DELAY * read_errors);
+                    continue;
                 }
+                errors.record("de lecture", None, &e);
+                None
             }
-            None => break,
+        };
+        let finished = packet.is_none();
+        let stream_index = packet.as_ref().map(|packet| packet.stream());
+
+        // En fin de fichier, les deux décodeurs sont vidés
+        let for_video = decoder.as_ref().is_some_and(|decoder| finished || stream_index == Some(decoder.stream_index));
+        let for_audio = audio.as_ref().is_some_and(|&(_, index)| finished || stream_index == Some(index));
+
+        if let Some(decoder) = decoder.as_deref_mut().filter(|_| for_video) {
+            let time = packet.as_ref().and_then(|packet| packet.pts()).map(|pts| pts as f64 * decoder.time_base);
+            let sent = match packet {
+                Some(ref packet) => decoder.decoder.send_packet(packet),
+                None => decoder.decoder.send_eof(),
+            };
+            video_frames += errors.decode("de décodage vidéo", time, sent, || decoder.decoder.receive_frame(&mut frame));
+        }
+
+        if let Some((audio_dec, _)) = audio.as_mut().filter(|_| for_audio) {
+            let time = packet.as_ref().and_then(|packet| packet.pts()).map(|pts| pts as f64 * audio_time_base);
+            let sent = match packet {
+                Some(ref packet) => audio_dec.send_packet(packet),
+                None => audio_dec.send_eof(),
+            };
+            audio_frames += errors.decode("de décodage audio", time, sent, || audio_dec.receive_frame(&mut audio_frame));
+        }
+
+        if finished {
+            break;
         }
     }
 
-    Ok(())
+    errors.finish(video_frames, audio_frames)
+}
+
+// Copie l'image courante sur le canvas: la texture NV12 du rendu direct si elle porte l'image
+// affichée, la texture IYUV sinon. Centrée à un multiple entier de sa taille avec --integer-scale
+fn copy_video_texture(
+    canvas: &mut Canvas<Window>,
+    texture: &sdl2::render::Texture,
+    nv12_texture: Option<&Nv12Texture>,
+    anamorphic_size: Option<(u32, u32)>,
+    integer_scaling: bool,
+) -> Result<()> {
+    let texture_size = match nv12_texture {
+        Some(nv12_texture) => nv12_texture.size(),
+        None => {
+            let query = texture.query();
+            (query.width, query.height)
+        }
+    };
+    let output_size = canvas.output_size().map_err(|e| anyhow::anyhow!(e))?;
+    let destination = video_destination(output_size, texture_size, anamorphic_size, integer_scaling);
+    match nv12_texture {
+        Some(nv12_texture) => nv12_texture.copy(canvas, destination),
+        None => canvas.copy(texture, None, destination).map_err(|e| anyhow::anyhow!(e)),
+    }
+}
+
+// Rectangle où afficher la texture. Des pixels non carrés sont affichés à la taille corrigée
+// par le SAR, avec des bandes noires; des pixels carrés remplissent la fenêtre comme avant
+fn video_destination(
+    output_size: (u32, u32),
+    texture_size: (u32, u32),
+    anamorphic_size: Option<(u32, u32)>,
+    integer_scaling: bool,
+) -> Option<Rect> {
+    let (output_width, output_height) = output_size;
+    match (anamorphic_size, integer_scaling) {
+        (Some((width, height)), true) => Some(integer_scale::dest_rect(output_width, output_height, width, height)),
+        (Some((width, height)), false) => Some(integer_scale::aspect_rect(output_width, output_height, width, height)),
+        (None, true) => Some(integer_scale::dest_rect(output_width, output_height, texture_size.0, texture_size.1)),
+        (None, false) => None,
+    }
+}
+
+// Crée la fenêtre et son canvas avec le pilote de --render-driver. Si le pilote ne peut pas
+// s'initialiser (VM, bureau à distance...), on repasse sur le rendu logiciel.
+fn create_canvas(
+    video_subsystem: &sdl2::VideoSubsystem,
+    options: &Options,
+    width: u32,
+    height: u32,
+) -> Result<Canvas<Window>> {
+    let driver_index = match options.render_driver.as_deref() {
+        None => None,
+        Some(name) => {
+            let index = sdl2::render::drivers().position(|info| info.name == name).with_context(|| {
+                let available: Vec<&str> = sdl2::render::drivers().map(|info| info.name).collect();
+                format!("Pilote de rendu inconnu: {} (disponibles: {})", name, available.join(", "))
+            })?;
+            Some(index as u32)
+        }
+    };
+
+    // Un canvas qui échoue consomme sa fenêtre: chaque tentative a besoin d'une nouvelle fenêtre
+    let build_window = || -> Result<Window> {
+        let mut window_builder = video_subsystem.window(WINDOW_TITLE, width, height);
+        window_builder.position_centered();
+        if options.scale_to_window || options.integer_scale {
+            // La fenêtre doit pouvoir être redimensionnée pour que le décodage suive sa taille,
+            // ou pour agrandir l'image par multiples entiers
+            window_builder.resizable();
+        }
+        window_builder.build().map_err(|e| anyhow::anyhow!(e))
+    };
+
+    let mut canvas_builder = build_window()?.into_canvas();
+    if let Some(index) = driver_index {
+        canvas_builder = canvas_builder.index(index);
+    }
+    let canvas = match canvas_builder.build() {
+        Ok(canvas) => canvas,
+        Err(e) if options.render_driver.as_deref() != Some("software") => {
+            println!("Pilote de rendu indisponible ({}), repli sur le rendu logiciel", e);
+            build_window()?
+                .into_canvas()
+                .software()
+                .build()
+                .map_err(|e| anyhow::anyhow!(e))?
+        }
+        Err(e) => return Err(anyhow::anyhow!(e)),
+    };
+    println!("Pilote de rendu: {}", canvas.info().name);
+    Ok(canvas)
+}
+
+// Cadence d'affichage: fréquence de l'écran qui porte la fenêtre, plafonnée par --max-fps
+fn display_rate(video_subsystem: &sdl2::VideoSubsystem, canvas: &Canvas<Window>, options: &Options) -> f64 {
+    let refresh_rate = canvas
+        .window()
+        .display_index()
+        .and_then(|index| video_subsystem.current_display_mode(index))
+        .map(|mode| mode.refresh_rate)
+        .unwrap_or(0);
+    let refresh_rate = if refresh_rate > 0 { f64::from(refresh_rate) } else { TARGET_FPS };
+    options.max_fps.map_or(refresh_rate, |max_fps| max_fps.min(refresh_rate))
+}
+
+// Format stable de --progress, une ligne par seconde:
+// progress time=<s> duration=<s|N/A> fps=<images/s> dropped=<total>
+fn print_progress(time: f64, duration: Option<f64>, fps: f64, dropped: u64) {
+    let duration = duration.map_or_else(|| "N/A".to_string(), |duration| format!("{:.3}", duration));
+    println!("progress time={:.3} duration={} fps={:.2} dropped={}", time, duration, fps, dropped);
+}
+
+// Masque avec un bit par canal de sortie
+fn all_channels_mask(channels: u8) -> u32 {
+    (1u32 << channels.min(31)) - 1
+}
+
+fn describe_muted_channels(muted: u32, channels: u8) -> String {
+    let muted: Vec<String> = (0..channels)
+        .filter(|channel| muted & (1 << channel) != 0)
+        .map(|channel| (channel + 1).to_string())
+        .collect();
+    if muted.is_empty() {
+        "Tous les canaux actifs".to_string()
+    } else {
+        format!("Canaux coupés: {}", muted.join(", "))
+    }
+}
+
+fn update_window_title(
+    canvas: &mut Canvas<Window>,
+    loop_range: Option<&LoopRange>,
+    chapter: Option<(usize, &Chapter)>,
+    decoder: Option<&Decoder>,
+    speed: f64,
+    muted_channels: Option<(u32, u8)>,
+    subtitle: Option<String>,
+) {
+    let mut title = WINDOW_TITLE.to_string();
+    if speed != 1.0 {
+        title.push_str(&format!(" - x{:.2}", speed));
+    }
+    if let Some((muted, channels)) = muted_channels.filter(|&(muted, _)| muted != 0) {
+        title.push_str(&format!(" - {}", describe_muted_channels(muted, channels)));
+    }
+    if let Some(subtitle) = subtitle {
+        title.push_str(&format!(" - {}", subtitle));
+    }
+    if let Some((index, chapter)) = chapter {
+        title.push_str(&format!(" - {}", chapter.describe(index)));
+    }
+    if let Some(decoder) = decoder.filter(|decoder| decoder.hw_accel != HwAccel::None) {
+        title.push_str(&format!(" - {}", decoder.describe()));
+    }
+    if let Some(range) = loop_range {
+        title.push_str(&format!(" - {}", range.describe()));
+    }
+    let _ = canvas.window_mut().set_title(&title);
+}
+
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} [options] <chemin_video>", program);
+    eprintln!("       <chemin_video> = - pour lire le média sur l'entrée standard");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --scale-to-window    Décoder à la taille de la fenêtre au lieu de la résolution native");
+    eprintln!("  --integer-scale      Agrandir par multiples entiers sans lissage, pour le pixel art (touche I)");
+    eprintln!("  --output-size <LxH>  Taille fixe des miniatures (--thumbnail) et des frames (--dump-frames)");
+    eprintln!("  --keep-aspect        Avec --output-size: conserver les proportions avec des bandes noires");
+    eprintln!("  --loop-range <A-B>   Boucler entre A et B (en secondes, ex: 12.5-20)");
+    eprintln!("  --loop-gapless       Reprendre au début en fin de fichier, sans coupure de l'image ni du son");
+    eprintln!("  --resume             Reprendre à la position où la dernière lecture du fichier s'est arrêtée");
+    eprintln!("  --ab-repeat-count <n>");
+    eprintln!("                       Nombre de passages dans la boucle A-B avant de continuer (0 = infini)");
+    eprintln!("  --volume <0-200>     Volume en pourcentage (100 par défaut)");
+    eprintln!("  --probe-size <octets>");
+    eprintln!("                       Quantité de données analysée pour détecter les flux");
+    eprintln!("  --analyze-duration <microsecondes>");
+    eprintln!("                       Durée analysée pour détecter les flux (ex: captures MPEG-TS)");
+    eprintln!("  --opt <clé=valeur>   Option ffmpeg transmise au démuxeur et aux décodeurs (répétable)");
+    eprintln!("                       ex: --opt fflags=+genpts, --opt threads=4, --opt rtsp_transport=tcp");
+    eprintln!("  --hwaccel <api>      Décodage matériel: cuda, vaapi, qsv, videotoolbox ou none (défaut)");
+    eprintln!("  --direct-nv12        Afficher les frames NV12 sans conversion CPU, si le renderer le permet");
+    eprintln!("  --speed <facteur>    Vitesse de lecture initiale, entre 0.25 et 4 (ex: 1.5)");
+    eprintln!("  --video-stream <n>   Lire le flux vidéo d'index n au lieu du choix automatique");
+    eprintln!("  --audio-stream <n>   Lire le flux audio d'index n au lieu du choix automatique");
+    eprintln!("  --strict-streams     Ne jamais choisir de flux automatiquement: --video-stream et/ou --audio-stream requis");
+    eprintln!("  --pip                Incruster le second flux vidéo du fichier dans un coin (touche P)");
+    eprintln!("  --pip-position <coin>");
+    eprintln!("                       top-left, top-right, bottom-left ou bottom-right (défaut)");
+    eprintln!("  --pip-scale <0.1-0.5>");
+    eprintln!("                       Largeur de l'incrustation en fraction de la fenêtre (0.25 par défaut)");
+    eprintln!("  --progress           Une ligne par seconde pour les scripts: progress time=... duration=... fps=... dropped=...");
+    eprintln!("  --fps-overlay        Afficher le FPS et le graphe des intervalles entre frames (touche G)");
+    eprintln!("  --buffer-stats       Afficher le remplissage des tampons audio et vidéo (touche B)");
+    eprintln!("  --forced-subs        Afficher d'office la piste de sous-titres forcés (touche S pour changer de piste)");
+    eprintln!("  --sub <fichier>      Afficher les sous-titres d'un fichier WebVTT (.vtt) ou SRT (.srt)");
+    eprintln!("  --mpris              Linux: contrôle par les touches média et les widgets du bureau (MPRIS)");
+    eprintln!("  --init-segment <fichier>");
+    eprintln!("                       Segment d'initialisation fMP4 (DASH/HLS) à lire avant le segment média donné");
+    eprintln!("  --vf <filtres>       Chaîne de filtres vidéo ffmpeg appliquée avant l'affichage (ex: hflip,eq=contrast=1.2)");
+    eprintln!("  --af <filtres>       Chaîne de filtres audio ffmpeg appliquée avant la sortie (ex: volume=2,highpass=f=200)");
+    eprintln!("  --list-filters       Lister les filtres vidéo disponibles pour --vf");
+    eprintln!("  --interpolate        Ralenti lissé: images intermédiaires par compensation de mouvement (--speed < 1)");
+    eprintln!("  --interpolate-mode <mode>");
+    eprintln!("                       Méthode d'interpolation: mci (défaut, lourd) ou blend (fondu), active --interpolate");
+    eprintln!("  --max-fps <fps>      Cadence d'affichage maximale (fréquence de l'écran par défaut)");
+    eprintln!("  --keyframes-only     Aperçu rapide: ne décoder et n'afficher que les images clés, sans le son (touche J)");
+    eprintln!("  --keymap <fichier>   Raccourcis clavier personnalisés, une ligne touche=action (ex: F=pause)");
+    eprintln!("  --read-timeout <ms>  Signaler un blocage sans nouvelle frame, puis se reconnecter (réseau) ou abandonner");
+    eprintln!("  --render-driver <nom>");
+    eprintln!("                       Pilote de rendu SDL: auto (défaut), opengl, opengles2, direct3d, metal, software...");
+    eprintln!("  --solo <canal>       N'écouter qu'un canal audio (1 = gauche, 2 = droite...)");
+    eprintln!("  --framerate <fps>    Cadence de lecture (ex: 24, 23.976 ou 24000/1001), pour les séquences d'images");
+    eprintln!("  --scene-detect <0-1> Sans affichage: lister les changements de plan au-delà du seuil");
+    eprintln!("  --dump-audio <fichier.wav>");
+    eprintln!("                       Sans affichage: extraire l'audio décodé dans un fichier WAV");
+    eprintln!("  --wav-rate <Hz>      Fréquence du WAV extrait (celle du flux par défaut)");
+    eprintln!("  --wav-channels <n>   Nombre de canaux du WAV extrait (celui du flux par défaut)");
+    eprintln!("  --dump-frames <fichier>");
+    eprintln!("                       Sans affichage: écrire chaque frame en RGBA brut (ffmpeg -f rawvideo -pix_fmt rgba)");
+    eprintln!("  --thumbnail <fichier.ppm>");
+    eprintln!("                       Sans affichage: écrire une miniature représentative (à 10% de la durée)");
+    eprintln!("  --loudness           Sans affichage: mesurer la sonie EBU R128 (LUFS, LRA, true peak)");
+    eprintln!("  --verify-sync        Sans affichage: simuler la lecture et donner l'écart A/V de chaque frame en JSON");
+    eprintln!("  --null-output        Sans affichage: tout décoder et lister les erreurs, code de sortie non nul s'il y en a");
+    eprintln!("  --trace-csv <fichier>");
+    eprintln!("                       Écrire le cadencement de chaque frame (PTS, affichage, horloge audio) en CSV");
+    eprintln!("  --record-output <fichier.wav>");
+    eprintln!("                       Enregistrer le son joué (après volume et canaux coupés) dans un fichier WAV");
+    eprintln!("  --audio-step <ms>    Pas des touches . et , en pause sur un fichier audio (1000 par défaut)");
+}
+
+fn next_value<'a>(iter: &mut impl Iterator<Item = &'a String>, option: &str) -> Result<&'a String> {
+    iter.next().with_context(|| format!("L'option {} attend une valeur", option))
+}
+
+fn parse_output_size(value: &str) -> Result<(u32, u32)> {
+    let (width, height) = value
+        .split_once(['x', 'X'])
+        .with_context(|| format!("Taille invalide: {} (attendu LxH, ex: 320x180)", value))?;
+    let width: u32 = width.trim().parse().with_context(|| format!("Largeur invalide: {}", width))?;
+    let height: u32 = height.trim().parse().with_context(|| format!("Hauteur invalide: {}", height))?;
+    // Même plancher que les dimensions vidéo acceptées par le scaler
+    if width < MIN_VIDEO_DIMENSION || height < MIN_VIDEO_DIMENSION {
+        anyhow::bail!("La taille doit être d'au moins {}x{}", MIN_VIDEO_DIMENSION, MIN_VIDEO_DIMENSION);
+    }
+    Ok((width, height))
+}
+
+fn parse_loop_range(value: &str) -> Result<(f64, f64)> {
+    let (start, end) = value
+        .split_once('-')
+        .with_context(|| format!("Intervalle de boucle invalide: {} (attendu A-B)", value))?;
+    let start: f64 = start.trim().parse().with_context(|| format!("Point A invalide: {}", start))?;
+    let end: f64 = end.trim().parse().with_context(|| format!("Point B invalide: {}", end))?;
+    if !(start >= 0.0 && end > start) {
+        anyhow::bail!("Intervalle de boucle invalide: il faut 0 <= A < B");
+    }
+    Ok((start, end))
+}
+
+fn parse_frame_rate(value: &str) -> Result<ffmpeg::Rational> {
+    let frame_rate = match value.split_once('/') {
+        Some((numerator, denominator)) => {
+            let numerator: i32 = numerator.trim().parse().with_context(|| format!("Cadence invalide: {}", value))?;
+            let denominator: i32 = denominator.trim().parse().with_context(|| format!("Cadence invalide: {}", value))?;
+            ffmpeg::Rational::new(numerator, denominator)
+        }
+        None => {
+            let fps: f64 = value.parse().with_context(|| format!("Cadence invalide: {}", value))?;
+            ffmpeg::Rational::from(fps)
+        }
+    };
+    if frame_rate.numerator() <= 0 || frame_rate.denominator() <= 0 {
+        anyhow::bail!("La cadence doit être strictement positive");
+    }
+    Ok(frame_rate)
+}
+
+fn parse_args(args: &[String]) -> Result<Options> {
+    let mut options = Options::default();
+    let mut video_path = None;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--scale-to-window" => options.scale_to_window = true,
+            "--integer-scale" => options.integer_scale = true,
+            "--output-size" => options.output_size = Some(parse_output_size(next_value(&mut iter, arg)?)?),
+            "--keep-aspect" => options.keep_aspect = true,
+            "--loop-range" => {
+                options.loop_range = Some(parse_loop_range(next_value(&mut iter, arg)?)?);
+            }
+            "--ab-repeat-count" => {
+                let value = next_value(&mut iter, arg)?;
+                options.ab_repeat_count = value
+                    .parse()
+                    .with_context(|| format!("Nombre de répétitions invalide: {}", value))?;
+            }
+            "--volume" => {
+                let value = next_value(&mut iter, arg)?;
+                let percent: f32 = value
+                    .parse()
+                    .with_context(|| format!("Volume invalide: {}", value))?;
+                if !(0.0..=200.0).contains(&percent) {
+                    anyhow::bail!("Le volume doit être compris entre 0 et 200");
+                }
+                options.volume = percent / 100.0;
+            }
+            "--probe-size" => {
+                let value = next_value(&mut iter, arg)?;
+                let probe_size: u64 = value
+                    .parse()
+                    .with_context(|| format!("Taille de sonde invalide: {}", value))?;
+                // ffmpeg refuse une sonde de moins de 32 octets
+                if probe_size < 32 {
+                    anyhow::bail!("La taille de sonde doit être d'au moins 32 octets");
+                }
+                options.probe_size = Some(probe_size);
+            }
+            "--analyze-duration" => {
+                let value = next_value(&mut iter, arg)?;
+                options.analyze_duration = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("Durée d'analyse invalide: {}", value))?,
+                );
+            }
+            "--opt" => {
+                let value = next_value(&mut iter, arg)?;
+                let (key, value) = value
+                    .split_once('=')
+                    .with_context(|| format!("Option ffmpeg invalide: {} (attendu clé=valeur)", value))?;
+                if key.is_empty() {
+                    anyhow::bail!("Option ffmpeg sans nom: ={}", value);
+                }
+                options.ffmpeg_options.push((key.to_string(), value.to_string()));
+            }
+            "--hwaccel" => options.hwaccel = HwAccel::parse(next_value(&mut iter, arg)?)?,
+            "--direct-nv12" => options.direct_nv12 = true,
+            "--speed" => {
+                let value = next_value(&mut iter, arg)?;
+                let speed: f64 = value
+                    .parse()
+                    .with_context(|| format!("Vitesse invalide: {}", value))?;
+                if !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+                    anyhow::bail!("La vitesse doit être comprise entre {} et {}", MIN_SPEED, MAX_SPEED);
+                }
+                options.speed = speed;
+            }
+            "--video-stream" => {
+                let value = next_value(&mut iter, arg)?;
+                options.video_stream = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("Index de flux invalide: {}", value))?,
+                );
+            }
+            "--audio-stream" => {
+                let value = next_value(&mut iter, arg)?;
+                options.audio_stream = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("Index de flux invalide: {}", value))?,
+                );
+            }
+            "--strict-streams" => options.strict_streams = true,
+            "--pip" => options.pip = true,
+            "--pip-position" => options.pip_position = PipPosition::parse(next_value(&mut iter, arg)?)?,
+            "--pip-scale" => {
+                let value = next_value(&mut iter, arg)?;
+                let scale: f64 = value
+                    .parse()
+                    .with_context(|| format!("Taille d'incrustation invalide: {}", value))?;
+                if !(MIN_PIP_SCALE..=MAX_PIP_SCALE).contains(&scale) {
+                    anyhow::bail!("La taille d'incrustation doit être comprise entre {} et {}", MIN_PIP_SCALE, MAX_PIP_SCALE);
+                }
+                options.pip_scale = scale;
+            }
+            "--progress" => options.progress = true,
+            "--fps-overlay" => options.fps_overlay = true,
+            "--buffer-stats" => options.buffer_stats = true,
+            "--forced-subs" => options.forced_subs = true,
+            "--sub" => options.subtitle_file = Some(next_value(&mut iter, arg)?.clone()),
+            "--mpris" => options.mpris = true,
+            "--init-segment" => options.init_segment = Some(next_value(&mut iter, arg)?.clone()),
+            "--loop-gapless" => options.loop_gapless = true,
+            "--resume" => options.resume = true,
+            "--vf" => options.video_filter = Some(next_value(&mut iter, arg)?.clone()),
+            "--af" => options.audio_filter = Some(next_value(&mut iter, arg)?.clone()),
+            "--list-filters" => options.list_filters = true,
+            "--interpolate" => options.interpolate = Some(InterpolationMode::MotionCompensated),
+            "--interpolate-mode" => {
+                options.interpolate = Some(InterpolationMode::parse(next_value(&mut iter, arg)?)?);
+            }
+            "--max-fps" => {
+                let value = next_value(&mut iter, arg)?;
+                let fps: f64 = value
+                    .parse()
+                    .with_context(|| format!("Cadence maximale invalide: {}", value))?;
+                if fps.is_nan() || fps < 1.0 {
+                    anyhow::bail!("La cadence maximale doit être d'au moins 1 fps");
+                }
+                options.max_fps = Some(fps);
+            }
+            "--keyframes-only" => options.keyframes_only = true,
+            "--keymap" => options.keymap = Some(next_value(&mut iter, arg)?.clone()),
+            "--read-timeout" => {
+                let value = next_value(&mut iter, arg)?;
+                let millis: u64 = value.parse().with_context(|| format!("Délai invalide: {}", value))?;
+                if millis == 0 {
+                    anyhow::bail!("Le délai de lecture doit être d'au moins 1 ms");
+                }
+                options.read_timeout = Some(Duration::from_millis(millis));
+            }
+            "--render-driver" => {
+                let value = next_value(&mut iter, arg)?;
+                options.render_driver = (value != "auto").then(|| value.clone());
+            }
+            "--solo" => {
+                let value = next_value(&mut iter, arg)?;
+                let channel: u8 = value
+                    .parse()
+                    .with_context(|| format!("Canal invalide: {}", value))?;
+                if channel == 0 {
+                    anyhow::bail!("Les canaux sont numérotés à partir de 1");
+                }
+                options.solo_channel = Some(channel - 1);
+            }
+            "--framerate" => {
+                options.frame_rate = Some(parse_frame_rate(next_value(&mut iter, arg)?)?);
+            }
+            "--scene-detect" => {
+                let value = next_value(&mut iter, arg)?;
+                let threshold: f64 = value
+                    .parse()
+                    .with_context(|| format!("Seuil de détection invalide: {}", value))?;
+                if !(0.0..=1.0).contains(&threshold) {
+                    anyhow::bail!("Le seuil de détection doit être compris entre 0 et 1");
+                }
+                options.scene_detect = Some(threshold);
+            }
+            "--dump-audio" => options.dump_audio = Some(next_value(&mut iter, arg)?.clone()),
+            "--wav-rate" => {
+                let value = next_value(&mut iter, arg)?;
+                let rate: u32 = value
+                    .parse()
+                    .with_context(|| format!("Fréquence invalide: {}", value))?;
+                if rate == 0 {
+                    anyhow::bail!("La fréquence du WAV doit être strictement positive");
+                }
+                options.wav_rate = Some(rate);
+            }
+            "--wav-channels" => {
+                let value = next_value(&mut iter, arg)?;
+                let channels: u16 = value
+                    .parse()
+                    .with_context(|| format!("Nombre de canaux invalide: {}", value))?;
+                if channels == 0 {
+                    anyhow::bail!("Le WAV doit avoir au moins un canal");
+                }
+                options.wav_channels = Some(channels);
+            }
+            "--dump-frames" => options.dump_frames = Some(next_value(&mut iter, arg)?.clone()),
+            "--thumbnail" => options.thumbnail = Some(next_value(&mut iter, arg)?.clone()),
+            "--loudness" => options.loudness = true,
+            "--verify-sync" => options.verify_sync = true,
+            "--null-output" => options.null_output = true,
+            "--trace-csv" => options.trace_csv = Some(next_value(&mut iter, arg)?.clone()),
+            "--record-output" => options.record_output = Some(next_value(&mut iter, arg)?.clone()),
+            "--audio-step" => {
+                let value = next_value(&mut iter, arg)?;
+                let millis: u64 = value
+                    .parse()
+                    .with_context(|| format!("Pas audio invalide: {}", value))?;
+                if millis == 0 {
+                    anyhow::bail!("Le pas audio doit être d'au moins 1 ms");
+                }
+                options.audio_step = Duration::from_millis(millis);
+            }
+            _ if arg.starts_with("--") => anyhow::bail!("Option inconnue: {}", arg),
+            _ => {
+                if video_path.is_some() {
+                    anyhow::bail!("Un seul chemin vidéo est accepté");
+                }
+                video_path = Some(arg.clone());
+            }
+        }
+    }
+
+    options.video_path = match video_path {
+        Some(path) => path,
+        // --list-filters n'ouvre aucun fichier
+        None if options.list_filters => String::new(),
+        None => anyhow::bail!("Aucun chemin vidéo fourni"),
+    };
+    if options.keep_aspect && options.output_size.is_none() {
+        anyhow::bail!("--keep-aspect nécessite --output-size");
+    }
+    if options.output_size.is_some() && options.thumbnail.is_none() && options.dump_frames.is_none() {
+        println!("--output-size: sans effet sans --thumbnail ni --dump-frames, la fenêtre garde sa taille");
+    }
+    if options.integer_scale && options.scale_to_window {
+        // Décodée à la taille de la fenêtre, l'image n'a plus de taille propre à multiplier
+        anyhow::bail!("--integer-scale et --scale-to-window sont incompatibles");
+    }
+    Ok(options)
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Erreur: {}", e);
+            print_usage(&args[0]);
+            std::process::exit(1);
+        }
+    };
+
+    // Une erreur dans le fichier de raccourcis est signalée avant d'ouvrir quoi que ce soit
+    let key_bindings = match options.keymap {
+        Some(ref path) => KeyBindings::load(path)?,
+        None => KeyBindings::default(),
+    };
+    let subtitle_file = options.subtitle_file.as_deref().map(SubtitleFile::load).transpose()?;
+
+    init_ffmpeg()?;
+    // Avant l'ouverture: une connexion qui ne répond pas est aussi surveillée
+    watchdog::set_read_timeout(options.read_timeout);
+
+    if options.list_filters {
+        video_filter::print_video_filters();
+        return Ok(());
+    }
+
+    let player = open_player(&options)?;
+    let seekable = player.seekable();
+    let chapters = player.chapters();
+    println!("Capacités de l'entrée:");
+    println!("  Vidéo: {}, audio: {}, recherche: {}", player.has_video(), player.has_audio(), seekable);
+    for (index, chapter) in chapters.iter().enumerate() {
+        println!("  {} ({:.2}s - {:.2}s)", chapter.describe(index), chapter.start, chapter.end);
+    }
+    for track in player.tracks() {
+        println!(
+            "  Piste {}: {:?} {:?} ({})",
+            track.index,
+            track.medium,
+            track.codec,
+            track.language.as_deref().unwrap_or("langue inconnue")
+        );
+    }
+    if let Some(ref path) = options.dump_frames {
+        return run_frame_dump(player, path);
+    }
+    if let Some(ref path) = options.thumbnail {
+        return run_thumbnail(player, path);
+    }
+    // Le callback audio et la boucle de lecture écrivent dans les diagnostics du lecteur
+    let buffer_stats = player.buffer_stats();
+    let Player {
+        mut ictx,
+        mut decoder,
+        pip_decoder,
+        mut audio_decoder,
+        audio_stream_index,
+        subtitle_tracks,
+        subtitle_track,
+        ..
+    } = player;
+
+    if let Some(threshold) = options.scene_detect {
+        let decoder = decoder.as_mut().context("La détection de plans nécessite un flux vidéo")?;
+        return run_scene_detect(&mut ictx, decoder, threshold);
+    }
+
+    if let Some(ref path) = options.dump_audio {
+        let audio_dec = audio_decoder.as_mut().context("L'extraction audio nécessite un flux audio")?;
+        let index = audio_stream_index.context("L'extraction audio nécessite un flux audio")?;
+        return run_audio_dump(&mut ictx, audio_dec, index, &options, path);
+    }
+
+    if options.loudness {
+        let audio_dec = audio_decoder.as_mut().context("La mesure de sonie nécessite un flux audio")?;
+        let index = audio_stream_index.context("La mesure de sonie nécessite un flux audio")?;
+        return run_loudness(&mut ictx, audio_dec, index);
+    }
+
+    if options.verify_sync {
+        let decoder = decoder.as_mut().context("La vérification de synchronisation nécessite un flux vidéo")?;
+        let audio_dec = audio_decoder
+            .as_mut()
+            .context("La vérification de synchronisation nécessite un flux audio")?;
+        let index = audio_stream_index.context("La vérification de synchronisation nécessite un flux audio")?;
+        return run_verify_sync(&mut ictx, decoder, audio_dec, index, options.speed);
+    }
+
+    if options.null_output {
+        return run_null_output(&mut ictx, decoder.as_mut(), audio_decoder.as_mut().zip(audio_stream_index));
+    }
+
+    let sdl_context = sdl2::init().map_err(|e| anyhow::anyhow!(e))?;
+    let video_subsystem = sdl_context.video().map_err(|e| anyhow::anyhow!(e))?;
+    let audio_subsystem = sdl_context.audio().map_err(|e| anyhow::anyhow!(e))?;
+
+    let sample_history = visualizer::new_history();
+    let (mut audio_device, mut audio_resampler) = if let Some(ref audio_dec) = audio_decoder {
+        let channels = audio_dec.channels() as u8;
+        let audio_stream = audio_stream_index
+            .and_then(|index| ictx.stream(index))
+            .context("No audio stream found")?;
+        let audio_time_base = f64::from(audio_stream.time_base());
+        let sample_rate = audio_dec.rate() as i32;
+
+        println!("Configuration audio:");
+        println!("  Channels: {}", channels);
+        println!("  Sample rate: {} Hz", sample_rate);
+
+        let desired_spec = AudioSpecDesired {
+            freq: Some(sample_rate),
+            channels: Some(channels),
+            samples: Some(AUDIO_DEVICE_SAMPLES),
+        };
+
+        // SDL peut renégocier la spec: le lecteur est construit à partir de celle obtenue
+        let mut device = audio_subsystem
+            .open_playback(None, &desired_spec, |spec| {
+                AudioPlayer::new(
+                    spec.channels,
+                    audio_time_base,
+                    spec.freq,
+                    spec.samples,
+                    options.speed,
+                    sample_history.clone(),
+                    buffer_stats.clone(),
+                )
+            })
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        // Copie: le lecteur est verrouillé plus bas alors que la spec sert encore.
+        // sdl2 ouvre le périphérique sans autoriser de changement de format: le format obtenu est
+        // toujours f32, SDL convertit lui-même vers le format natif de la carte (s16 par exemple).
+        // Une sortie s16 native demanderait SDL_GetAudioDeviceSpec, absent des bindings de sdl2-sys
+        // 0.35, et une chaîne entière en s16: volume, filtres, niveaux et enregistrement sont en f32
+        let obtained = *device.spec();
+        if obtained.freq != sample_rate
+            || obtained.channels != channels
+            || obtained.format != AudioFormat::f32_sys()
+        {
+            println!("Attention: spec audio obtenue différente de la demande:");
+            println!("  Demandé: {} Hz, {} canaux, {:?}", sample_rate, channels, AudioFormat::f32_sys());
+            println!("  Obtenu: {} Hz, {} canaux, {:?}", obtained.freq, obtained.channels, obtained.format);
+        }
+        // Un buffer de callback différent ne change que la latence: simple détail de configuration
+        if obtained.samples != AUDIO_DEVICE_SAMPLES {
+            println!("  Callback: {} échantillons demandés, {} obtenus", AUDIO_DEVICE_SAMPLES, obtained.samples);
+        }
+        {
+            let audio_player = device.lock();
+            let rate = f64::from(obtained.freq.max(1));
+            println!(
+                "  Callback: {} échantillons ({:.1} ms de latence de sortie)",
+                obtained.samples,
+                f64::from(obtained.samples) / rate * 1000.0
+            );
+            println!(
+                "  Buffer: {} échantillons par canal ({:.0} ms), démarrage après {} ({:.0} ms)",
+                audio_player.capacity_frames,
+                audio_player.capacity_frames as f64 / rate * 1000.0,
+                audio_player.primed_frames,
+                audio_player.primed_frames as f64 / rate * 1000.0
+            );
+        }
+
+        // La vitesse passe par le rééchantillonnage: moins d'échantillons par seconde média en accéléré,
+        // avec un changement de hauteur du son
+        let output_rate = (f64::from(obtained.freq) / options.speed).round() as u32;
+        let mut resampler = AudioResampler::new(audio_dec, output_rate, u16::from(obtained.channels))?;
+        if decoder.is_some() {
+            // Sans vidéo, la contre-pression suffit: le buffer ne dérive pas
+            resampler.enable_drift_correction()?;
+        }
+        if let Some(ref spec) = options.audio_filter {
+            resampler.set_filter(spec)?;
+        }
+        if let Some(ref path) = options.record_output {
+            // Le WAV suit la spec obtenue: c'est ce format que reçoit la carte son
+            let block_len = usize::from(obtained.samples) * usize::from(obtained.channels);
+            let recorder = OutputRecorder::create(path, u16::from(obtained.channels), obtained.freq as u32, block_len)?;
+            device.lock().set_recorder(recorder);
+        }
+        if let Some(solo) = options.solo_channel {
+            let output_channels = obtained.channels;
+            if solo >= output_channels {
+                anyhow::bail!("Canal {} inexistant: la sortie a {} canaux", solo + 1, output_channels);
+            }
+            device.lock().set_muted_channels(all_channels_mask(output_channels) & !(1 << solo));
+        }
+        let audio_state = device.lock().get_state();
+        (Some((device, audio_state)), Some(resampler))
+    } else {
+        (None, None)
+    };
+
+    let (width, height) = decoder
+        .as_ref()
+        .map(Decoder::frame_size)
+        .unwrap_or(AUDIO_ONLY_WINDOW_SIZE);
+    let mut canvas = create_canvas(&video_subsystem, &options, width, height)?;
+    let display_rate = display_rate(&video_subsystem, &canvas, &options);
+    if let Some(ref mut decoder) = decoder {
+        decoder.set_display_rate(display_rate);
+        if options.direct_nv12 {
+            decoder.direct_nv12 = nv12_texture::is_supported(&canvas);
+            if decoder.direct_nv12 {
+                println!("Rendu direct NV12: les frames NV12 ne passent plus par le scaler");
+            } else {
+                println!("Rendu direct NV12 indisponible: le renderer n'accepte pas les textures NV12");
+            }
+        }
+    }
+    let mut pip = pip_decoder
+        .map(|pip_decoder| PipOverlay::new(pip_decoder, options.pip_position, options.pip_scale, width))
+        .transpose()?;
+    let mut subtitles = Subtitles::new(&mut ictx, subtitle_tracks, subtitle_track, subtitle_file)?;
+    if subtitles.is_active() {
+        println!("{}", subtitles.describe());
+    }
+
+    canvas.set_draw_color(sdl2::pixels::Color::BLACK);
+    canvas.clear();
+    canvas.present();
+
+    let texture_creator = canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_streaming(PixelFormatEnum::IYUV, width, height)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let mut integer_scaling = options.integer_scale;
+    let mut keyframes_only = options.keyframes_only && decoder.is_some();
+    if let Some(decoder) = decoder.as_mut().filter(|_| keyframes_only) {
+        decoder.set_keyframes_only(true);
+        println!("Images clés seulement: aperçu rapide sans le son");
+    }
+    if integer_scaling {
+        integer_scale::set_nearest(&mut texture, true)?;
+    }
+    // Texture des frames NV12 en rendu direct, créée à la première frame. showing_nv12 indique
+    // laquelle des deux textures porte l'image affichée
+    let mut nv12_texture: Option<Nv12Texture> = None;
+    let mut showing_nv12 = false;
+    let mut pip_texture = match pip {
+        Some(ref pip) => {
+            let (pip_width, pip_height) = pip.size();
+            Some(
+                texture_creator
+                    .create_texture_streaming(PixelFormatEnum::IYUV, pip_width, pip_height)
+                    .map_err(|e| anyhow::anyhow!(e))?,
+            )
+        }
+        None => None,
+    };
+
+    let mut event_pump = sdl_context.event_pump().map_err(|e| anyhow::anyhow!(e))?;
+
+    let video_stream_index = decoder.as_ref().map(|decoder| decoder.stream_index);
+    let mut loop_range = options.loop_range.map(|(start, end)| {
+        let keyframe = video_stream_index.and_then(|index| player::keyframe_before(&mut ictx, index, start));
+        LoopRange::new(start, end, options.ab_repeat_count, keyframe)
+    });
+    if loop_range.is_some() && !seekable {
+        println!("Entrée non positionnable: boucle A-B ignorée");
+        loop_range = None;
+    }
+    // Point A posé avec la touche L, en attente du point B
+    let mut pending_loop_start: Option<f64> = None;
+    let mut current_time = 0.0;
+    let mut paused = false;
+    // Position de départ du dernier pas audio, et instant où remettre en pause après la tranche
+    let mut audio_position = 0.0;
+    let mut step_pause_at: Option<Instant> = None;
+    let mut current_chapter = player::chapter_at(&chapters, 0.0);
+    let mut title_dirty = true;
+    let mut reached_end = false;
+    // Fin du fichier atteinte une fois: les décodeurs ont reçu send_eof et rendu leurs dernières frames
+    let mut draining = false;
+    // PTS des derniers échantillons mis en file, pour ceux que rendent les filtres en fin de flux
+    let mut last_audio_pts = 0;
+    let mut muted_channels = audio_device.as_mut().map_or(0, |(device, _)| device.lock().muted_channels);
+
+    let mut frame = Video::empty();
+    let mut audio_frame = ffmpeg::frame::Audio::empty();
+    let sample_converter = SampleConverter::new();
+    let mut audio_samples = Vec::new();
+    let mut drift_corrector = DriftCorrector::new();
+    let frame_trace = match options.trace_csv {
+        Some(ref path) => Some(FrameTrace::create(path)?),
+        None => None,
+    };
+    let mut traced_frames = 0u64;
+    let mut read_errors = 0u32;
+    let mut watchdog = Watchdog::new(options.read_timeout);
+    // Paquets lus depuis le dernier retour au début: une passe vide arrête la boucle
+    let mut packets_since_loop = 0u64;
+    let mut last_drift_check = Instant::now();
+    println!("Conversion audio: {:?}", sample_converter.level());
+
+    let mut visualizer = Visualizer::new(sample_history);
+    let mut show_fps_overlay = options.fps_overlay;
+    let mut show_frame_info = false;
+    let mut show_buffer_stats = options.buffer_stats;
+    // Sous-alimentations déjà prises en compte par le dernier agrandissement du buffer audio
+    let mut handled_underruns = 0u64;
+    let duration = (ictx.duration() > 0).then(|| ictx.duration() as f64 * f64::from(ffmpeg::rescale::TIME_BASE));
+    let mut last_progress = Instant::now();
+    let mut progress_frames = 0u64;
+    let mut dropped_frames = 0u64;
+    let mut last_visualization = Instant::now();
+    let mut loop_overlay_shown = false;
+    let mpris = if options.mpris {
+        let title = ictx
+            .metadata()
+            .get("title")
+            .map(str::to_string)
+            .or_else(|| {
+                std::path::Path::new(&options.video_path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| options.video_path.clone());
+        let status = mpris::Status {
+            title,
+            duration,
+            position: 0.0,
+            paused: false,
+            rate: options.speed,
+            seekable,
+            has_chapters: !chapters.is_empty(),
+        };
+        // Sans bus de session (console, conteneur), la lecture continue sans contrôle du bureau
+        match Mpris::start(status) {
+            Ok(mpris) => Some(mpris),
+            Err(e) => {
+                eprintln!("Attention: MPRIS désactivé: {:#}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    // Position mémorisée par fichier local, reprise avec --resume
+    let resume_key = FileKey::of(&options.video_path).filter(|_| seekable && options.init_segment.is_none());
+    let mut resume_store = match resume_key {
+        Some(_) => match ResumeStore::open() {
+            Ok(store) => Some(store),
+            Err(e) => {
+                eprintln!("Positions de reprise indisponibles: {:#}", e);
+                None
+            }
+        },
+        None => None,
+    };
+    if options.resume {
+        let position = resume_store
+            .as_ref()
+            .zip(resume_key.as_ref())
+            .and_then(|(store, key)| store.position(key, duration));
+        match position {
+            Some(position) => {
+                println!("Reprise à {:.2}s", position);
+                seek_to(&mut ictx, decoder.as_mut(), &mut pip, &mut audio_decoder, &mut audio_device, mpris.as_ref(), position)?;
+                current_time = position;
+            }
+            None => println!("Aucune position mémorisée pour ce fichier: lecture depuis le début"),
+        }
+    }
+    // Le périphérique audio ne démarre qu'une fois le buffer amorcé, voir resume_when_primed
+
+    'running: loop {
+        // Pause ou lecture demandée par Espace ou par MPRIS, appliquée après les événements
+        let mut requested_pause = None;
+        for event in event_pump.poll_iter() {
+            // Les touches passent par la table de --keymap: seules les actions sont traitées ensuite
+            let action = match event {
+                Event::Quit { .. } => Some(Action::Quit),
+                Event::KeyDown { keycode: Some(key), .. } => key_bindings.action(key),
+                Event::Window { win_event: WindowEvent::SizeChanged(width, height), .. }
+                    if options.scale_to_window && width > 0 && height > 0 =>
+                {
+                    let (width, height) = (width as u32, height as u32);
+                    if let Some(ref mut decoder) = decoder {
+                        decoder.set_output_size(width, height)?;
+                    }
+                    texture = texture_creator
+                        .create_texture_streaming(PixelFormatEnum::IYUV, width, height)
+                        .map_err(|e| anyhow::anyhow!(e))?;
+                    println!("Redimensionnement: décodage à {}x{}", width, height);
+                    None
+                }
+                _ => None,
+            };
+            let Some(action) = action else {
+                continue;
+            };
+            match action {
+                Action::Quit => {
+                    break 'running;
+                }
+                Action::LoopPoint if seekable => {
+                    // Premier appui: point A, deuxième: point B, troisième: suppression
+                    if loop_range.is_some() {
+                        loop_range = None;
+                        pending_loop_start = None;
+                        println!("Boucle A-B désactivée");
+                    } else if let Some(start) = pending_loop_start.take() {
+                        if current_time > start {
+                            let keyframe = decoder
+                                .as_ref()
+                                .and_then(|decoder| player::keyframe_before(&mut ictx, decoder.stream_index, start));
+                            let range = LoopRange::new(start, current_time, options.ab_repeat_count, keyframe);
+                            println!("{}", range.describe());
+                            loop_range = Some(range);
+                        }
+                    } else {
+                        pending_loop_start = Some(current_time);
+                        println!("Point A de la boucle: {:.2}s", current_time);
+                    }
+                    title_dirty = true;
+                }
+                Action::PreviousChapter | Action::NextChapter if seekable && !chapters.is_empty() => {
+                    if let Some(start) = player::chapter_start(&chapters, current_time, action == Action::NextChapter) {
+                        seek_to(&mut ictx, decoder.as_mut(), &mut pip, &mut audio_decoder, &mut audio_device, mpris.as_ref(), start)?;
+                        current_time = start;
+                    }
+                }
+                Action::ToggleHwAccel if options.hwaccel != HwAccel::None => {
+                    if let Some(ref mut decoder) = decoder {
+                        // Bascule entre l'API demandée par --hwaccel et le décodage logiciel
+                        let accel = if decoder.hw_accel == HwAccel::None { options.hwaccel } else { HwAccel::None };
+                        let output = decoder.scaler.output();
+                        let mut reopened = player::reopen_video_decoder(&ictx, decoder.stream_index, &options, accel)?;
+                        reopened.set_output_size(output.width, output.height)?;
+                        reopened.set_display_rate(display_rate);
+                        reopened.direct_nv12 = decoder.direct_nv12;
+                        if keyframes_only {
+                            reopened.set_keyframes_only(true);
+                        }
+                        *decoder = reopened;
+                        println!("{}", decoder.describe());
+
+                        // Le nouveau décodeur repart d'une image clé: on revient à la position courante
+                        if seekable {
+                            seek_to(&mut ictx, Some(decoder), &mut pip, &mut audio_decoder, &mut audio_device, mpris.as_ref(), current_time)?;
+                        }
+                        title_dirty = true;
+                    }
+                }
+                Action::ToggleChannel(channel) => {
+                    if let Some((ref mut device, _)) = audio_device {
+                        // 1 à 8 coupent ou rétablissent le canal, 0 les rétablit tous
+                        let channels = device.spec().channels;
+                        muted_channels = match channel {
+                            0 => 0,
+                            channel if channel <= channels => muted_channels ^ (1 << (channel - 1)),
+                            _ => muted_channels,
+                        };
+                        device.lock().set_muted_channels(muted_channels);
+                        println!("{}", describe_muted_channels(muted_channels, channels));
+                        title_dirty = true;
+                    }
+                }
+                Action::CycleVisualization if decoder.is_none() && audio_device.is_some() => {
+                    visualizer.cycle_mode();
+                    println!("Visualisation: {}", visualizer.mode.name());
+                    if visualizer.mode == VisualizationMode::Off {
+                        canvas.set_draw_color(sdl2::pixels::Color::BLACK);
+                        canvas.clear();
+                        canvas.present();
+                    }
+                }
+                Action::ToggleFpsOverlay if decoder.is_some() => {
+                    show_fps_overlay = !show_fps_overlay;
+                }
+                Action::ToggleFrameInfo if decoder.is_some() => {
+                    show_frame_info = !show_frame_info;
+                }
+                Action::ToggleBufferStats if decoder.is_some() => {
+                    show_buffer_stats = !show_buffer_stats;
+                }
+                Action::ToggleIntegerScale if decoder.is_some() && !options.scale_to_window => {
+                    integer_scaling = !integer_scaling;
+                    integer_scale::set_nearest(&mut texture, integer_scaling)?;
+                    if let Some(ref mut nv12_texture) = nv12_texture {
+                        nv12_texture.set_nearest(integer_scaling)?;
+                    }
+                    println!("Agrandissement par multiples entiers: {}", if integer_scaling { "activé" } else { "désactivé" });
+                }
+                Action::CycleSubtitles if decoder.is_some() => {
+                    subtitles.cycle(&mut ictx)?;
+                    println!("{}", subtitles.describe());
+                    title_dirty = true;
+                }
+                Action::TogglePip => {
+                    if let Some(ref mut pip) = pip {
+                        pip.visible = !pip.visible;
+                        println!("Incrustation {}", if pip.visible { "affichée" } else { "masquée" });
+                    }
+                }
+                Action::ToggleKeyframesOnly if decoder.is_some() => {
+                    keyframes_only = !keyframes_only;
+                    if let Some(ref mut decoder) = decoder {
+                        decoder.set_keyframes_only(keyframes_only);
+                    }
+                    if keyframes_only {
+                        // L'image défile au rythme du décodage: le son n'est plus lu
+                        if let Some((ref mut device, _)) = audio_device {
+                            device.pause();
+                            device.lock().clear();
+                        }
+                    } else if seekable {
+                        // Retour au décodage complet à la position affichée, son compris
+                        seek_to(&mut ictx, decoder.as_mut(), &mut pip, &mut audio_decoder, &mut audio_device, mpris.as_ref(), current_time)?;
+                    }
+                    println!("Images clés seulement: {}", if keyframes_only { "activé" } else { "désactivé" });
+                }
+                Action::TogglePause => {
+                    requested_pause = Some(!paused);
+                }
+                Action::StepForward | Action::StepBackward if paused && seekable && decoder.is_none() => {
+                    let step = options.audio_step.as_secs_f64();
+                    let start = if action == Action::StepForward {
+                        audio_position + step
+                    } else {
+                        (audio_position - step).max(0.0)
+                    };
+                    seek_to(&mut ictx, None, &mut pip, &mut audio_decoder, &mut audio_device, mpris.as_ref(), start)?;
+
+                    if let (Some(stream_index), Some(audio_dec), Some(resampler), Some((device, _))) =
+                        (audio_stream_index, audio_decoder.as_mut(), audio_resampler.as_mut(), audio_device.as_mut())
+                    {
+                        let slice = decode_audio_slice(
+                            &mut ictx,
+                            stream_index,
+                            audio_dec,
+                            resampler,
+                            &sample_converter,
+                            options.volume,
+                            start..start + step,
+                        )?;
+                        // Seule la tranche est jouée, puis le périphérique repasse en pause
+                        device.lock().load_slice(&slice, start);
+                        device.resume();
+                        step_pause_at = Some(Instant::now() + options.audio_step);
+                        audio_position = start;
+                        println!("Pas audio: {:.2}s - {:.2}s", start, start + step);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Commandes du bureau (touches média, widgets), traitées comme les touches équivalentes
+        for command in mpris.as_ref().map(Mpris::commands).unwrap_or_default() {
+            let target = match command {
+                MediaCommand::Play => {
+                    requested_pause = Some(false);
+                    None
+                }
+                MediaCommand::Pause => {
+                    requested_pause = Some(true);
+                    None
+                }
+                MediaCommand::PlayPause => {
+                    requested_pause = Some(!requested_pause.unwrap_or(paused));
+                    None
+                }
+                MediaCommand::Next => player::chapter_start(&chapters, current_time, true),
+                MediaCommand::Previous => player::chapter_start(&chapters, current_time, false),
+                MediaCommand::Seek(offset) => Some(current_time + offset),
+                MediaCommand::SetPosition(position) => Some(position),
+                MediaCommand::Quit => break 'running,
+            };
+            if let Some(target) = target.filter(|_| seekable) {
+                let target = duration.map_or(target, |duration| target.min(duration)).max(0.0);
+                seek_to(&mut ictx, decoder.as_mut(), &mut pip, &mut audio_decoder, &mut audio_device, mpris.as_ref(), target)?;
+                current_time = target;
+            }
+        }
+
+        if let Some(pause) = requested_pause.filter(|&pause| pause != paused) {
+            paused = pause;
+            step_pause_at = None;
+            if let Some((ref mut device, ref audio_state)) = audio_device {
+                if paused {
+                    device.pause();
+                    audio_position = audio_state.lock().map(|state| state.current_time()).unwrap_or(0.0);
+                } else {
+                    resume_when_primed(device);
+                }
+            }
+            if !paused {
+                if let Some(ref mut decoder) = decoder {
+                    decoder.reset_clock();
+                }
+            }
+            println!("{}", if paused { "Pause" } else { "Lecture" });
+        }
+
+        if decoder.is_none() {
+            // Sans vidéo, la position affichée suit l'horloge audio, une fois celle-ci réamorcée
+            // après un seek
+            if let Some((_, ref audio_state)) = audio_device {
+                if let Ok(state) = audio_state.lock() {
+                    if state.base_time.is_some() {
+                        current_time = state.current_time();
+                    }
+                }
+            }
+        }
+
+        // Boucle A-B sur l'horloge maîtresse: la dernière frame affichée, ou le son sans vidéo
+        if let Some(range) = loop_range.as_mut().filter(|range| current_time >= range.end) {
+            if range.complete_pass() {
+                // Le seek en arrière vers A s'arrête sur l'image clé: le décodage repart
+                // sans attente, et la vidéo comme le son ne reprennent qu'au point A
+                let start = range.start;
+                seek_to(&mut ictx, decoder.as_mut(), &mut pip, &mut audio_decoder, &mut audio_device, mpris.as_ref(), start)?;
+                if range.keyframe.is_some_and(|keyframe| keyframe < start) {
+                    if let Some(ref mut decoder) = decoder {
+                        decoder.seek_target = Some(start);
+                    }
+                    if let Some((ref mut device, _)) = audio_device {
+                        device.lock().skip_until(start);
+                    }
+                }
+                current_time = start;
+            } else {
+                println!("Boucle A-B terminée après {} passage(s)", range.passes);
+                loop_range = None;
+            }
+            title_dirty = true;
+        }
+
+        let chapter = player::chapter_at(&chapters, current_time);
+        if chapter != current_chapter {
+            current_chapter = chapter;
+            if let Some(index) = chapter {
+                println!("{}", chapters[index].describe(index));
+            }
+            title_dirty = true;
+        }
+
+        if let Some(ref mpris) = mpris {
+            mpris.update(paused, current_time);
+        }
+
+        if title_dirty {
+            update_window_title(
+                &mut canvas,
+                loop_range.as_ref(),
+                current_chapter.map(|index| (index, &chapters[index])),
+                decoder.as_ref(),
+                options.speed,
+                audio_device.as_ref().map(|(device, _)| (muted_channels, device.spec().channels)),
+                subtitles.is_active().then(|| subtitles.describe()),
+            );
+            title_dirty = false;
+        }
+
+        if options.progress && last_progress.elapsed() >= PROGRESS_INTERVAL {
+            print_progress(current_time, duration, progress_frames as f64 / last_progress.elapsed().as_secs_f64(), dropped_frames);
+            last_progress = Instant::now();
+            progress_frames = 0;
+        }
+
+        // Sans vidéo, la fenêtre est redessinée pour la visualisation et pour le compteur de la
+        // boucle A-B, une dernière fois quand celle-ci se termine pour l'effacer
+        let loop_remaining = loop_range.as_ref().map(LoopRange::remaining);
+        if decoder.is_none()
+            && (visualizer.mode != VisualizationMode::Off || loop_remaining.is_some() || loop_overlay_shown)
+            && last_visualization.elapsed() >= VISUALIZATION_INTERVAL
+        {
+            last_visualization = Instant::now();
+            visualizer.draw(&mut canvas)?;
+            if let Some(ref range) = loop_range {
+                loop_overlay::draw(&mut canvas, range.start, range.keyframe, range.end, range.remaining())?;
+            }
+            loop_overlay_shown = loop_remaining.is_some();
+            canvas.present();
+        }
+
+        if paused {
+            if step_pause_at.is_some_and(|deadline| Instant::now() >= deadline) {
+                if let Some((ref mut device, _)) = audio_device {
+                    device.pause();
+                }
+                step_pause_at = None;
+            }
+            // En pause, l'absence de frames n'est pas un blocage
+            watchdog.reset();
+            std::thread::sleep(Duration::from_millis(10));
+            continue;
+        }
+
+        if decoder.is_some() && last_drift_check.elapsed() >= DRIFT_CHECK_INTERVAL {
+            last_drift_check = Instant::now();
+            if let (Some((ref mut device, _)), Some(ref mut resampler)) = (&mut audio_device, &mut audio_resampler) {
+                let fill = device.lock().buffer.len();
+                if let Some(ppm) = drift_corrector.observe(fill) {
+                    resampler.set_compensation(ppm)?;
+                    println!("Correction de dérive audio: {} ppm", ppm);
+                }
+            }
+        }
+
+        // Sous-alimentations répétées: le buffer audio grandit pour absorber les irrégularités de lecture
+        if widen_due(buffer_stats.underruns(), handled_underruns) {
+            handled_underruns = buffer_stats.underruns();
+            if let Some((ref mut device, _)) = audio_device {
+                let rate = f64::from(device.spec().freq.max(1));
+                let widened = device.lock().widen();
+                if let Some(frames) = widened {
+                    println!(
+                        "Sous-alimentations audio répétées ({}): buffer agrandi à {} échantillons par canal ({:.0} ms)",
+                        handled_underruns,
+                        frames,
+                        frames as f64 / rate * 1000.0
+                    );
+                    // Le remplissage de référence de la correction de dérive change avec la taille
+                    drift_corrector = DriftCorrector::new();
+                    if let Some(ref mut resampler) = audio_resampler {
+                        resampler.set_compensation(0)?;
+                    }
+                }
+            }
+        }
+
+        if decoder.is_none() {
+            if let Some((ref mut device, _)) = audio_device {
+                if device.lock().has_enough_buffered() {
+                    watchdog.progress();
+                    std::thread::sleep(Duration::from_millis(5));
+                    continue;
+                }
+            }
+        }
+
+        // Plus de frame depuis --read-timeout: avertissement par-dessus la dernière image. Les lectures
+        // bloquées sont interrompues à chaque délai, ce qui garde la fenêtre réactive
+        if let Some(stalled_for) = watchdog.stalled_for() {
+            if watchdog.should_redraw() {
+                canvas.clear();
+                if let Some(ref decoder) = decoder {
+                    let nv12 = nv12_texture.as_ref().filter(|_| showing_nv12);
+                    copy_video_texture(&mut canvas, &texture, nv12, decoder.anamorphic_size, integer_scaling)?;
+                }
+                watchdog::draw(&mut canvas, stalled_for)?;
+                canvas.present();
+            }
+        }
+
+        // None: fin du fichier, les décodeurs doivent rendre les frames qu'ils retiennent encore
+        let packet = match player::read_packet(&mut ictx) {
+            Ok(Some(packet)) => {
+                read_errors = 0;
+                packets_since_loop += 1;
+                draining = false;
+                Some(packet)
+            }
+            // Boucle sans coupure: retour au début sans vider les décodeurs ni le buffer audio.
+            // Les frames encore dans les décodeurs sortent avant celles de la nouvelle passe, le son
+            // en file couvre le temps du seek, et la continuité du décodeur audio évite un clic
+            Ok(None) if options.loop_gapless && seekable && packets_since_loop > 0 => {
+                packets_since_loop = 0;
+                ictx.seek(0, ..).context("Impossible de revenir au début pour la boucle")?;
+                if let Some(ref mpris) = mpris {
+                    mpris.seeked(0.0);
+                }
+                if let Some((ref mut device, _)) = audio_device {
+                    device.lock().mark_loop();
+                }
+                continue;
+            }
+            Ok(None) if !draining => {
+                draining = true;
+                None
+            }
+            // Fin réelle du fichier, décodeurs vidés au tour précédent
+            Ok(None) => {
+                reached_end = true;
+                break;
+            }
+            // Lecture interrompue par le watchdog: on patiente encore, l'avertissement reste affiché
+            Err(ffmpeg::Error::Exit) if options.read_timeout.is_some() && !watchdog.gave_up() => continue,
+            Err(e) => {
+                let stalled = options.read_timeout.is_some() && watchdog.gave_up();
+                if !stalled {
+                    read_errors += 1;
+                    eprintln!("Erreur de lecture ({}/{}): {}", read_errors, MAX_READ_ERRORS, e);
+                    if read_errors < MAX_READ_ERRORS {
+                        std::thread::sleep(READ_RETRY_DELAY * read_errors);
+                        continue;
+                    }
+                }
+                if !player::is_network_input(&options.video_path) {
+                    return Err(e).context(if stalled { "Lecture bloquée, abandon" } else { "Lecture interrompue" });
+                }
+
+                // Entrée réseau: on rouvre la connexion et on reprend à la dernière position affichée
+                println!("Reconnexion à {}...", options.video_path);
+                let reopened = Player::open(&options).context("Reconnexion impossible")?;
+                let output_size = decoder
+                    .as_ref()
+                    .map(|decoder| (decoder.scaler.output().width, decoder.scaler.output().height));
+                let direct_nv12 = decoder.as_ref().is_some_and(|decoder| decoder.direct_nv12);
+                ictx = reopened.ictx;
+                audio_decoder = reopened.audio_decoder;
+                decoder = reopened.decoder;
+                if let (Some(ref mut pip), Some(pip_decoder)) = (&mut pip, reopened.pip_decoder) {
+                    let visible = pip.visible;
+                    *pip = PipOverlay::new(pip_decoder, options.pip_position, options.pip_scale, width)?;
+                    pip.visible = visible;
+                }
+                if let (Some(ref mut decoder), Some((width, height))) = (&mut decoder, output_size) {
+                    decoder.set_output_size(width, height)?;
+                    decoder.set_display_rate(display_rate);
+                    decoder.direct_nv12 = direct_nv12;
+                    if keyframes_only {
+                        decoder.set_keyframes_only(true);
+                    }
+                }
+                subtitles.reopen(&mut ictx)?;
+                if seekable {
+                    seek_to(&mut ictx, decoder.as_mut(), &mut pip, &mut audio_decoder, &mut audio_device, mpris.as_ref(), current_time)?;
+                }
+                read_errors = 0;
+                watchdog.reset();
+                continue;
+            }
+        };
+
+        let stream_index = packet.as_ref().map(ffmpeg::Packet::stream);
+        if let Some(decoder) = decoder.as_mut().filter(|decoder| stream_index.is_none_or(|index| index == decoder.stream_index)) {
+            // Le PTS du paquet ne sert que de secours pour la première frame reçue
+            let mut packet_pts = match packet {
+                Some(ref packet) => {
+                    decoder.send_packet(packet)?;
+                    packet.pts()
+                }
+                None => {
+                    decoder.decoder.send_eof()?;
+                    None
+                }
+            };
+
+            // Un paquet peut libérer plusieurs frames, et la fin du flux toutes celles en attente
+            while decoder.receive_frame_yuv(&mut frame)? {
+                watchdog.progress();
+                buffer_stats.record_video_queue(decoder.queued_packets);
+                // receive_frame_yuv a déjà reporté l'horodatage de la frame décodée dans son PTS
+                let pts = presentation_pts(None, frame.pts(), packet_pts.take()).unwrap_or(0);
+                let frame_time = pts as f64 * decoder.time_base;
+                decoder.check_discontinuity(frame_time);
+                let scheduled = decoder.next_frame_target;
+                // Avance du décodage sur l'heure d'affichage prévue pour cette frame
+                if let Some(target) = scheduled {
+                    let now = Instant::now();
+                    let lead = if target >= now {
+                        (target - now).as_secs_f64()
+                    } else {
+                        -(now - target).as_secs_f64()
+                    };
+                    buffer_stats.record_decode_lead(lead);
+                }
+                let decimated = decoder.decimated_frames;
+                // À une demi-frame près: un PTS arrondi ne doit pas masquer la frame du point visé
+                let half_frame = frame_offset(decoder.frame_rate, 1).as_secs_f64() / 2.0;
+                let before_target = decoder.seek_target.is_some_and(|target| frame_time < target - half_frame);
+                if !before_target {
+                    decoder.seek_target = None;
+                }
+                // En images clés seules, chaque image est montrée dès qu'elle est décodée
+                let displayed = !before_target && (keyframes_only || decoder.should_display_frame(pts));
+                if !displayed && !before_target && decoder.decimated_frames == decimated {
+                    dropped_frames += 1;
+                }
+                if displayed {
+                    progress_frames += 1;
+                    current_time = frame_time;
+                    // Rendu direct: la frame NV12 va dans sa propre texture, créée à sa taille
+                    let size = (frame.width(), frame.height());
+                    let query = texture.query();
+                    if frame.format() == Pixel::NV12 {
+                        if nv12_texture.as_ref().map(Nv12Texture::size) != Some(size) {
+                            println!("Rendu direct NV12: texture {}x{}", size.0, size.1);
+                            let mut created = Nv12Texture::new(&mut canvas, size.0, size.1)?;
+                            if integer_scaling {
+                                created.set_nearest(true)?;
+                            }
+                            nv12_texture = Some(created);
+                        }
+                    } else if size != (query.width, query.height) {
+                        // Changement de résolution non suivi par le scaler: la texture prend la taille de la frame
+                        println!(
+                            "Taille des frames: {}x{} -> {}x{}",
+                            query.width,
+                            query.height,
+                            frame.width(),
+                            frame.height()
+                        );
+                        texture = texture_creator
+                            .create_texture_streaming(PixelFormatEnum::IYUV, frame.width(), frame.height())
+                            .map_err(|e| anyhow::anyhow!(e))?;
+                        if integer_scaling {
+                            integer_scale::set_nearest(&mut texture, true)?;
+                        }
+                    }
+                }
+                // Une frame aux plans incohérents est ignorée, l'image précédente reste affichée
+                let uploaded = match nv12_texture {
+                    _ if !displayed => false,
+                    Some(ref mut nv12_texture) if frame.format() == Pixel::NV12 => nv12_texture.update(&frame)?,
+                    _ => yuv_upload::upload(&mut texture, &frame)?,
+                };
+                if uploaded {
+                    showing_nv12 = frame.format() == Pixel::NV12;
+                    canvas.clear();
+                    let nv12 = nv12_texture.as_ref().filter(|_| showing_nv12);
+                    copy_video_texture(&mut canvas, &texture, nv12, decoder.anamorphic_size, integer_scaling)?;
+                    // L'incrustation suit l'horloge de la vidéo principale
+                    if let (Some(ref mut pip), Some(ref mut pip_texture)) = (&mut pip, &mut pip_texture) {
+                        pip.update(pip_texture, current_time)?;
+                        pip.draw(&mut canvas, pip_texture)?;
+                    }
+                    subtitles.draw(&mut canvas, current_time)?;
+                    if show_fps_overlay {
+                        fps_overlay::draw(&mut canvas, &decoder.frame_intervals, decoder.frame_target(1))?;
+                    }
+                    if let Some(ref info) = decoder.last_frame_info.filter(|_| show_frame_info) {
+                        frame_info::draw(&mut canvas, info, decoder.time_base)?;
+                    }
+                    if show_buffer_stats {
+                        buffer_stats::draw(&mut canvas, &buffer_stats.snapshot())?;
+                    }
+                    if let Some(ref range) = loop_range {
+                        loop_overlay::draw(&mut canvas, range.start, range.keyframe, range.end, range.remaining())?;
+                    }
+                    canvas.present();
+                }
+
+                if let Some(ref trace) = frame_trace {
+                    trace.record(TraceRow {
+                        frame_index: traced_frames,
+                        pts,
+                        pts_seconds: frame_time,
+                        scheduled,
+                        displayed_at: displayed.then(Instant::now),
+                        audio_clock: audio_device
+                            .as_ref()
+                            .and_then(|(_, audio_state)| audio_state.lock().ok().map(|state| state.current_time())),
+                    });
+                    traced_frames += 1;
+                }
+            }
+        } else if let Some(ref packet) = packet {
+            let stream_index = packet.stream();
+            if let Some(pip) = pip.as_mut().filter(|pip| pip.decoder.stream_index == stream_index) {
+                pip.push_packet(packet)?;
+            } else if subtitles.stream_index() == Some(stream_index) {
+                subtitles.push_packet(packet)?;
+            } else if Some(stream_index) == audio_stream_index && !keyframes_only {
+                if let Some(ref mut audio_dec) = audio_decoder {
+                    audio_dec.send_packet(packet)?;
+
+                    while audio_dec.receive_frame(&mut audio_frame).is_ok() {
+                        if let (Some((ref mut device, _)), Some(ref mut resampler)) = (&mut audio_device, &mut audio_resampler) {
+                            resampler.process(&sample_converter, &audio_frame, options.volume, &mut audio_samples)?;
+                            // Le décodeur peut retarder ses frames: un paquet n'est pas forcément la frame rendue
+                            let pts = presentation_pts(audio_frame.timestamp(), audio_frame.pts(), packet.pts()).unwrap_or(0);
+                            last_audio_pts = pts;
+                            device.lock().add_samples(&audio_samples, pts);
+                            watchdog.progress();
+                            if !paused {
+                                resume_when_primed(device);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Fin du fichier: dernières frames du décodeur audio, puis ce que retiennent encore les filtres
+        // et le rééchantillonneur. Le tour suivant relit la fin du fichier et termine la lecture
+        if packet.is_none() && !keyframes_only {
+            if let Some(ref mut audio_dec) = audio_decoder {
+                audio_dec.send_eof()?;
+                while audio_dec.receive_frame(&mut audio_frame).is_ok() {
+                    if let (Some((ref mut device, _)), Some(ref mut resampler)) = (&mut audio_device, &mut audio_resampler) {
+                        resampler.process(&sample_converter, &audio_frame, options.volume, &mut audio_samples)?;
+                        last_audio_pts = presentation_pts(audio_frame.timestamp(), audio_frame.pts(), None).unwrap_or(last_audio_pts);
+                        device.lock().add_samples(&audio_samples, last_audio_pts);
+                    }
+                }
+                if let (Some((ref mut device, _)), Some(ref mut resampler)) = (&mut audio_device, &mut audio_resampler) {
+                    resampler.drain(&sample_converter, options.volume, &mut audio_samples)?;
+                    device.lock().add_samples(&audio_samples, last_audio_pts);
+                }
+            }
+        }
+    }
+
+    // Un fichier lu jusqu'au bout reprendra au début
+    if let (Some(ref mut store), Some(key)) = (&mut resume_store, resume_key) {
+        let position = if reached_end { 0.0 } else { current_time };
+        if let Err(e) = store.save(key, position, duration) {
+            eprintln!("Impossible de mémoriser la position: {:#}", e);
+        }
+    }
+
+    // Arrêt propre: en fin de fichier on laisse jouer ce qui reste dans le buffer, puis le périphérique
+    // est mis en pause et vidé avant d'être fermé, pour éviter un dernier paquet de son ou un clic
+    if let Some((ref mut device, _)) = audio_device {
+        if reached_end {
+            // Un fichier plus court que l'amorçage n'a peut-être jamais démarré le périphérique
+            device.resume();
+            let deadline = Instant::now() + AUDIO_DRAIN_TIMEOUT;
+            while !device.lock().buffer.is_empty() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        device.pause();
+        device.lock().clear();
+    }
+    drop(audio_device);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loop_range_counts_down_remaining_passes() {
+        let mut range = LoopRange::new(10.0, 20.0, 3, None);
+        assert_eq!(range.remaining(), Some(3));
+        assert!(range.complete_pass());
+        assert!(range.complete_pass());
+        assert_eq!(range.remaining(), Some(1));
+        // Le troisième passage est le dernier: la lecture continue après B
+        assert!(!range.complete_pass());
+        assert_eq!(range.remaining(), Some(0));
+    }
+
+    #[test]
+    fn loop_range_without_count_is_infinite() {
+        let mut range = LoopRange::new(10.0, 20.0, 0, None);
+        for _ in 0..1000 {
+            assert!(range.complete_pass());
+        }
+        assert_eq!(range.remaining(), None);
+    }
+
+    // Plan Y 64x36 d'un dégradé horizontal décalé de shift, avec 16 octets de padding par ligne
+    fn gradient_plane(shift: usize, inverted: bool) -> Vec<u8> {
+        let mut plane = Vec::new();
+        for _ in 0..36 {
+            for x in 0..64 {
+                let value = ((x + shift) * 3 % 256) as u8;
+                plane.push(if inverted { 255 - value } else { value });
+            }
+            plane.extend_from_slice(&[0xAA; 16]);
+        }
+        plane
+    }
+
+    #[test]
+    fn scene_detector_reports_only_the_hard_cut() {
+        // Clip de 30 frames: panoramique lent, plan inversé coupé net à la frame 20
+        let mut detector = SceneDetector::new(0.3);
+        let mut cuts = Vec::new();
+        for index in 0..30 {
+            let plane = gradient_plane(index, index >= 20);
+            let luma = copy_luma(&plane, 80, 64, 36);
+            assert_eq!(luma.len(), 64 * 36);
+            if let Some(score) = detector.push(luma) {
+                cuts.push((index, score));
+            }
+        }
+        assert_eq!(cuts.len(), 1, "{:?}", cuts);
+        assert_eq!(cuts[0].0, 20);
+        assert!(cuts[0].1 > 0.3 && cuts[0].1 <= 1.0);
+    }
+
+    #[test]
+    fn luma_difference_is_normalized() {
+        assert_eq!(luma_difference(&[10, 20], &[10, 20]), 0.0);
+        assert_eq!(luma_difference(&[0, 0], &[255, 255]), 1.0);
+        assert_eq!(luma_difference(&[0, 255], &[255, 255]), 0.5);
+        // Tailles différentes (changement de résolution): toujours un changement de plan
+        assert_eq!(luma_difference(&[0; 4], &[0; 6]), 1.0);
+    }
+
+    // Lecture simulée d'une heure: le décodeur produit l'audio au rythme de l'horloge système, la
+    // carte son le consomme à card_ppm près. Renvoie l'écart maximal entre l'horloge audio et la
+    // position réellement jouée, le remplissage minimal et ceux de la dernière minute (s)
+    fn simulate_drift(card_ppm: f64, corrected: bool) -> (f64, f64, Vec<f64>) {
+        const RATE: f64 = 48000.0;
+        const LATENCY: f64 = 0.1;
+        let chunk = 1024.0 / RATE;
+        let mut clock = SimulatedAudioClock { anchors: Vec::new(), queued: 0.0, speed: 1.0 };
+        let mut corrector = DriftCorrector::new();
+        let mut ppm = 0;
+        // Début de chaque bloc sur la carte (s joués), et sa durée de lecture
+        let mut chunks: Vec<(f64, f64)> = Vec::new();
+        let mut last_check = 0.0;
+        let mut max_error: f64 = 0.0;
+        let mut min_fill = f64::INFINITY;
+        let mut last_minute = Vec::new();
+
+        for index in 0..(3600.0 / chunk) as usize {
+            let now = index as f64 * chunk;
+            // La compensation ajoute ou retire des échantillons, la carte les lit à son propre débit
+            let duration = chunk * (1.0 + ppm as f64 * 1e-6) / (1.0 + card_ppm * 1e-6);
+            chunks.push((clock.queued, duration));
+            clock.push(index as f64 * chunk, duration);
+
+            let played = (now - LATENCY).max(0.0);
+            let fill = clock.queued - played;
+            if now >= LATENCY {
+                min_fill = min_fill.min(fill);
+            }
+            if now >= LATENCY && now - last_check >= DRIFT_CHECK_INTERVAL.as_secs_f64() {
+                last_check = now;
+                if let Some(update) = corrector.observe((fill * RATE) as usize).filter(|_| corrected) {
+                    assert!(update.abs() <= 50);
+                    ppm = update;
+                }
+                // Position réellement jouée: le bloc en cours de lecture et la fraction déjà lue
+                let current = chunks.partition_point(|&(start, _)| start <= played) - 1;
+                let (start, length) = chunks[current];
+                let actual = (current as f64 + (played - start) / length) * chunk;
+                max_error = max_error.max((clock.at(played).unwrap() - actual).abs());
+                if now >= 3540.0 {
+                    last_minute.push(fill);
+                }
+            }
+        }
+        // Blocs contigus: la compensation ne doit jamais passer pour une discontinuité
+        assert_eq!(clock.anchors.len(), 1);
+        (max_error, min_fill, last_minute)
+    }
+
+    #[test]
+    fn drift_corrector_bounds_simulated_clock_error() {
+        for card_ppm in [40.0, -40.0] {
+            // Sans correction, 40 ppm sur une heure font 144 ms d'écart: plus que tout le buffer
+            let (uncorrected, _, _) = simulate_drift(card_ppm, false);
+            assert!(uncorrected > 0.1, "{} ppm: {:.4}s", card_ppm, uncorrected);
+
+            let (error, min_fill, last_minute) = simulate_drift(card_ppm, true);
+            assert!(error < 0.025, "{} ppm: {:.4}s", card_ppm, error);
+            assert!(min_fill > 0.05, "{} ppm: sous-alimentation ({:.4}s)", card_ppm, min_fill);
+            // Une fois la compensation établie, le remplissage ne bouge presque plus
+            let min = last_minute.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = last_minute.iter().cloned().fold(0.0, f64::max);
+            assert!(max - min < 0.001, "{} ppm: {:.4}s de gigue", card_ppm, max - min);
+        }
+    }
+
+    #[test]
+    fn loudness_of_reference_tone() {
+        // EBU Tech 3341, cas 1: sinus stéréo de 1 kHz à -23 dBFS pendant 20 s, soit -23 LUFS
+        let rate = 48000;
+        let amplitude = 10f64.powf(-23.0 / 20.0);
+        let mut samples = Vec::new();
+        for index in 0..20 * rate {
+            let value = (amplitude * (2.0 * std::f64::consts::PI * 1000.0 * f64::from(index) / f64::from(rate)).sin()) as f32;
+            samples.extend_from_slice(&[value, value]);
+        }
+        let mut meter = loudness_meter(2, rate).unwrap();
+        meter.add_frames_f32(&samples).unwrap();
+
+        let loudness = Loudness::measure(&meter, 2).unwrap();
+        assert!((loudness.integrated + 23.0).abs() < 0.1, "{} LUFS", loudness.integrated);
+        assert!(loudness.range.abs() < 0.1, "{} LU", loudness.range);
+        assert!((loudness.true_peak + 23.0).abs() < 0.5, "{} dBTP", loudness.true_peak);
+    }
+
+    #[test]
+    fn audio_clock_reanchors_on_pts_reset() {
+        let mut clock = SimulatedAudioClock { anchors: Vec::new(), queued: 0.0, speed: 1.0 };
+        for index in 0..50 {
+            clock.push(100.0 + f64::from(index) * 0.02, 0.02);
+        }
+        assert_eq!(clock.anchors.len(), 1);
+        // Remise à zéro après une seconde d'audio: la suite est réancrée, sans saut de lecture
+        for index in 0..50 {
+            clock.push(f64::from(index) * 0.02, 0.02);
+        }
+        assert_eq!(clock.anchors.len(), 2);
+        let (wall, media) = clock.anchors[1];
+        assert!((wall - 1.0).abs() < 1e-9 && media == 0.0);
+        assert!((clock.at(0.5).unwrap() - 100.5).abs() < 1e-9);
+        assert!((clock.at(1.5).unwrap() - 0.5).abs() < 1e-9);
+        assert_eq!(clock.at(-0.1), None);
+    }
+
+    // Nécessite les bibliothèques ffmpeg. Décodage instantané face à un son continu depuis 0: chaque
+    // frame attend son échéance puis s'affiche, avec l'avance constante du premier PTS (40 ms)
+    #[test]
+    fn verify_sync_replays_the_display_decision() {
+        ffmpeg::init().unwrap();
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/b_frames.flv");
+        let options = Options { video_path: path.to_string(), ..Options::default() };
+        let mut player = Player::open(&options).unwrap();
+        let mut decoder = player.decoder.take().unwrap();
+        decoder.log_stats = false;
+        let mut audio_clock = SimulatedAudioClock { anchors: Vec::new(), queued: 0.0, speed: 1.0 };
+        audio_clock.push(0.0, 1.0);
+
+        let (displayed, dropped) = simulate_display(&mut decoder, &[40, 80, 120, 160, 200, 240, 280]);
+
+        assert_eq!(dropped, 0);
+        assert_eq!(displayed.len(), 7);
+        for (index, &(wall, pts_time)) in displayed.iter().enumerate() {
+            assert!((wall - index as f64 * 0.04).abs() < 1e-6);
+            assert!((pts_time - audio_clock.at(wall).unwrap() - 0.04).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn anamorphic_pal_frame_is_shown_at_display_aspect() {
+        // DVD PAL 16:9: 720x576 codé, pixels de 64:45, soit 1024x576 à l'affichage
+        let display = player::sar_display_size(720, 576, ffmpeg::Rational::new(64, 45));
+        assert_eq!(display, (1024, 576));
+
+        let rect = video_destination((1280, 720), (720, 576), Some(display), false).unwrap();
+        assert_eq!(rect, Rect::new(0, 0, 1280, 720));
+        // Fenêtre 4:3: bandes noires en haut et en bas
+        let rect = video_destination((720, 576), (720, 576), Some(display), false).unwrap();
+        assert_eq!(rect, Rect::new(0, 85, 720, 405));
+        // Avec --integer-scale, multiple entier de la taille d'affichage
+        let rect = video_destination((2100, 1200), (720, 576), Some(display), true).unwrap();
+        assert_eq!(rect, Rect::new(26, 24, 2048, 1152));
+    }
+
+    #[test]
+    fn square_pixels_keep_the_previous_destination() {
+        assert_eq!(player::sar_display_size(720, 576, ffmpeg::Rational::new(1, 1)), (720, 576));
+        // SAR inconnu (0/1): taille codée
+        assert_eq!(player::sar_display_size(720, 576, ffmpeg::Rational::new(0, 1)), (720, 576));
+        assert_eq!(video_destination((1280, 720), (720, 576), None, false), None);
+        assert_eq!(video_destination((1440, 1152), (720, 576), None, true), Some(Rect::new(0, 0, 1440, 1152)));
+    }
+
+    // Décodeur simulé: les résultats successifs de receive_frame
+    fn receiver(results: Vec<Result<(), ffmpeg::Error>>) -> impl FnMut() -> Result<(), ffmpeg::Error> {
+        let mut results = results.into_iter();
+        move || results.next().unwrap_or(Err(ffmpeg::Error::Other { errno: ffmpeg::error::EAGAIN }))
+    }
+
+    #[test]
+    fn null_output_counts_frames_until_eagain_or_eof() {
+        let mut errors = DecodeErrors { count: 0, first: None };
+        let frames = errors.decode("de décodage vidéo", Some(1.0), Ok(()), receiver(vec![Ok(()), Ok(())]));
+        assert_eq!(frames, 2);
+        let frames = errors.decode("de décodage vidéo", None, Ok(()), receiver(vec![Ok(()), Err(ffmpeg::Error::Eof), Ok(())]));
+        assert_eq!(frames, 1);
+        assert_eq!(errors.count, 0);
+        assert!(errors.finish(3, 0).is_ok());
+    }
+
+    #[test]
+    fn null_output_records_every_error_and_the_first_time() {
+        let mut errors = DecodeErrors { count: 0, first: None };
+        // Paquet refusé, puis frame corrompue: deux erreurs, le décodage continue
+        let frames = errors.decode(
+            "de décodage vidéo",
+            Some(2.5),
+            Err(ffmpeg::Error::InvalidData),
+            receiver(vec![Ok(()), Err(ffmpeg::Error::InvalidData), Ok(())]),
+        );
+        assert_eq!(frames, 1);
+        let frames = errors.decode("de décodage audio", Some(7.0), Ok(()), receiver(vec![Err(ffmpeg::Error::Bug)]));
+        assert_eq!(frames, 0);
+        assert_eq!(errors.count, 3);
+        assert_eq!(errors.first, Some(2.5));
+
+        let result = errors.finish(1, 0);
+        assert_eq!(result.unwrap_err().to_string(), "3 erreur(s) de décodage");
+    }
+
+    #[test]
+    fn buffer_stats_follow_simulated_audio_activity() {
+        let stats = Arc::new(BufferStats::new());
+        let mut player = AudioPlayer::new(2, 1.0 / 48000.0, 48000, 1024, 1.0, visualizer::new_history(), stats.clone());
+        let mut out = vec![0.0; 2048];
+        let chunk = vec![0.5; 2048];
+        let mut pts = 0;
+        let mut feed = |player: &mut AudioPlayer, chunks: usize| {
+            for _ in 0..chunks {
+                player.add_samples(&chunk, pts);
+                pts += 1024;
+            }
+        };
+
+        // Buffer vide avant les premiers échantillons: pas une sous-alimentation
+        player.callback(&mut out);
+        assert_eq!(stats.underruns(), 0);
+
+        feed(&mut player, 8);
+        player.callback(&mut out);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.audio_fill_percent, Some(7.0 * 2048.0 / 32768.0 * 100.0));
+        assert_eq!(snapshot.audio_buffered, Some(7.0 * 2048.0 / 96000.0));
+
+        // Décodage bloqué à chaque passe: le buffer se vide, une seule sous-alimentation par blocage
+        // quel que soit le nombre de callbacks à vide. La boucle principale agrandit le buffer toutes
+        // les UNDERRUNS_BEFORE_WIDEN sous-alimentations, jusqu'au maximum
+        let mut handled_underruns = 0;
+        let mut capacities = Vec::new();
+        for stall in 1..=10 {
+            for _ in 0..12 {
+                player.callback(&mut out);
+            }
+            assert_eq!(stats.underruns(), stall);
+            assert_eq!(stats.snapshot().audio_fill_percent, Some(0.0));
+            if widen_due(stats.underruns(), handled_underruns) {
+                handled_underruns = stats.underruns();
+                capacities.push(player.widen());
+            }
+            feed(&mut player, 8);
+        }
+        assert_eq!(capacities, [Some(32768), Some(AUDIO_BUFFER_MAX_SIZE), None]);
+        assert_eq!(player.primed_frames, AUDIO_BUFFER_MAX_SIZE / 2);
+
+        // Vidage volontaire (seek): pas compté
+        player.clear();
+        player.callback(&mut out);
+        assert_eq!(stats.underruns(), 10);
+    }
 }
@@ -0,0 +1,381 @@
+use anyhow::Result;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+
+// Commande reçue du bureau (touches média, widgets), appliquée par la boucle principale
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaCommand {
+    Play,
+    Pause,
+    PlayPause,
+    Next,
+    Previous,
+    // Décalage relatif, en secondes
+    Seek(f64),
+    // Position absolue, en secondes
+    SetPosition(f64),
+    Quit,
+}
+
+// État exposé sur le bus, mis à jour à chaque tour de boucle
+#[derive(Clone, Debug)]
+pub struct Status {
+    pub title: String,
+    pub duration: Option<f64>,
+    pub position: f64,
+    pub paused: bool,
+    pub rate: f64,
+    pub seekable: bool,
+    pub has_chapters: bool,
+}
+
+// Service MPRIS MediaPlayer2 (--mpris): le thread de zbus ne fait que lire l'état partagé
+// et poster des commandes, la lecture reste pilotée par la boucle principale
+pub struct Mpris {
+    #[cfg(target_os = "linux")]
+    connection: zbus::blocking::Connection,
+    commands: Receiver<MediaCommand>,
+    status: Arc<Mutex<Status>>,
+}
+
+impl Mpris {
+    // Le nom du bus est unique: si une autre instance du lecteur l'a déjà pris, celle-ci s'enregistre
+    // sous le nom suffixé .instance<pid> prévu par la spécification MPRIS
+    #[cfg(target_os = "linux")]
+    pub fn start(status: Status) -> Result<Self> {
+        use anyhow::Context;
+
+        let (sender, commands) = std::sync::mpsc::channel();
+        let status = Arc::new(Mutex::new(status));
+        let instance_name = dbus::instance_name(std::process::id());
+        let (connection, name) = match dbus::connect(dbus::BUS_NAME, &sender, &status) {
+            Ok(connection) => (connection, dbus::BUS_NAME),
+            Err(_) => {
+                let connection = dbus::connect(&instance_name, &sender, &status)
+                    .context("Impossible de s'enregistrer sur le bus de session D-Bus")?;
+                (connection, instance_name.as_str())
+            }
+        };
+        println!("Service MPRIS enregistré: {}", name);
+
+        Ok(Self {
+            connection,
+            commands,
+            status,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn start(_status: Status) -> Result<Self> {
+        anyhow::bail!("--mpris n'est disponible que sous Linux")
+    }
+
+    // Commandes reçues depuis le dernier appel, sans attendre
+    pub fn commands(&self) -> Vec<MediaCommand> {
+        self.commands.try_iter().collect()
+    }
+
+    pub fn update(&self, paused: bool, position: f64) {
+        let changed = match self.status.lock() {
+            Ok(mut status) => {
+                status.position = position;
+                std::mem::replace(&mut status.paused, paused) != paused
+            }
+            Err(_) => return,
+        };
+        // La position est lue à la demande, seul le changement d'état doit être signalé aux widgets
+        if changed {
+            self.notify_playback_status();
+        }
+    }
+
+    // Position qui saute (seek, chapitre, boucle): les widgets recalent leur barre de progression,
+    // qu'ils font sinon avancer d'eux-mêmes au rythme de Rate
+    pub fn seeked(&self, position: f64) {
+        if let Ok(mut status) = self.status.lock() {
+            status.position = position;
+        }
+        self.notify_seeked(position);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn notify_playback_status(&self) {
+        let result = self
+            .connection
+            .object_server()
+            .interface::<_, dbus::PlayerInterface>(dbus::OBJECT_PATH)
+            .and_then(|iface| zbus::block_on(iface.get().playback_status_changed(iface.signal_context())));
+        if let Err(e) = result {
+            eprintln!("MPRIS: impossible de signaler le changement d'état: {}", e);
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn notify_playback_status(&self) {}
+
+    #[cfg(target_os = "linux")]
+    fn notify_seeked(&self, position: f64) {
+        let result = self
+            .connection
+            .object_server()
+            .interface::<_, dbus::PlayerInterface>(dbus::OBJECT_PATH)
+            .and_then(|iface| {
+                zbus::block_on(dbus::PlayerInterface::seeked(iface.signal_context(), dbus::microseconds(position)))
+            });
+        if let Err(e) = result {
+            eprintln!("MPRIS: impossible de signaler le déplacement: {}", e);
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn notify_seeked(&self, _position: f64) {}
+}
+
+// MinimumRate et MaximumRate doivent encadrer 1.0 (la spécification l'impose) ainsi que la vitesse
+// de lecture, fixée au lancement par --speed
+fn rate_bounds(rate: f64) -> (f64, f64) {
+    (rate.min(1.0), rate.max(1.0))
+}
+
+#[cfg(target_os = "linux")]
+mod dbus {
+    use super::{rate_bounds, MediaCommand, Status};
+    use std::collections::HashMap;
+    use std::sync::mpsc::Sender;
+    use std::sync::{Arc, Mutex};
+    use zbus::blocking::connection::Builder;
+    use zbus::blocking::Connection;
+    use zbus::zvariant::{ObjectPath, Value};
+    use zbus::{interface, SignalContext};
+
+    pub const BUS_NAME: &str = "org.mpris.MediaPlayer2.rust_media_player";
+    pub const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+    // Un seul fichier lu: identifiant de piste fixe
+    const TRACK_ID: &str = "/org/mpris/MediaPlayer2/rust_media_player/track/0";
+    // MPRIS compte les temps en microsecondes
+    const MICROSECONDS: f64 = 1_000_000.0;
+
+    pub fn instance_name(pid: u32) -> String {
+        format!("{}.instance{}", BUS_NAME, pid)
+    }
+
+    pub fn microseconds(seconds: f64) -> i64 {
+        (seconds * MICROSECONDS) as i64
+    }
+
+    pub fn connect(name: &str, commands: &Sender<MediaCommand>, status: &Arc<Mutex<Status>>) -> zbus::Result<Connection> {
+        Builder::session()
+            .and_then(|builder| builder.name(name))
+            .and_then(|builder| builder.serve_at(OBJECT_PATH, Root { commands: commands.clone() }))
+            .and_then(|builder| {
+                builder.serve_at(
+                    OBJECT_PATH,
+                    PlayerInterface {
+                        commands: commands.clone(),
+                        status: status.clone(),
+                    },
+                )
+            })
+            .and_then(|builder| builder.build())
+    }
+
+    pub struct Root {
+        pub commands: Sender<MediaCommand>,
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2")]
+    impl Root {
+        fn raise(&self) {}
+
+        fn quit(&self) {
+            let _ = self.commands.send(MediaCommand::Quit);
+        }
+
+        #[zbus(property)]
+        fn can_quit(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn can_raise(&self) -> bool {
+            false
+        }
+
+        #[zbus(property)]
+        fn has_track_list(&self) -> bool {
+            false
+        }
+
+        #[zbus(property)]
+        fn identity(&self) -> String {
+            crate::WINDOW_TITLE.to_string()
+        }
+
+        #[zbus(property)]
+        fn supported_uri_schemes(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        #[zbus(property)]
+        fn supported_mime_types(&self) -> Vec<String> {
+            Vec::new()
+        }
+    }
+
+    pub struct PlayerInterface {
+        pub commands: Sender<MediaCommand>,
+        pub status: Arc<Mutex<Status>>,
+    }
+
+    impl PlayerInterface {
+        fn send(&self, command: MediaCommand) {
+            let _ = self.commands.send(command);
+        }
+
+        fn status(&self) -> Option<Status> {
+            self.status.lock().ok().map(|status| status.clone())
+        }
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl PlayerInterface {
+        fn play(&self) {
+            self.send(MediaCommand::Play);
+        }
+
+        fn pause(&self) {
+            self.send(MediaCommand::Pause);
+        }
+
+        fn play_pause(&self) {
+            self.send(MediaCommand::PlayPause);
+        }
+
+        // Pas d'état arrêté distinct: Stop met en pause
+        fn stop(&self) {
+            self.send(MediaCommand::Pause);
+        }
+
+        fn next(&self) {
+            self.send(MediaCommand::Next);
+        }
+
+        fn previous(&self) {
+            self.send(MediaCommand::Previous);
+        }
+
+        fn seek(&self, offset: i64) {
+            self.send(MediaCommand::Seek(offset as f64 / MICROSECONDS));
+        }
+
+        fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+            // La spécification demande d'ignorer une position visant une autre piste
+            if track_id.as_str() == TRACK_ID && position >= 0 {
+                self.send(MediaCommand::SetPosition(position as f64 / MICROSECONDS));
+            }
+        }
+
+        fn open_uri(&self, _uri: String) {}
+
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            let paused = self.status().is_some_and(|status| status.paused);
+            if paused { "Paused" } else { "Playing" }.to_string()
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, Value<'static>> {
+            let mut metadata = HashMap::new();
+            metadata.insert(
+                "mpris:trackid".to_string(),
+                Value::from(ObjectPath::from_static_str_unchecked(TRACK_ID)),
+            );
+            if let Some(status) = self.status() {
+                metadata.insert("xesam:title".to_string(), Value::from(status.title));
+                if let Some(duration) = status.duration {
+                    metadata.insert("mpris:length".to_string(), Value::from(microseconds(duration)));
+                }
+            }
+            metadata
+        }
+
+        #[zbus(property)]
+        fn position(&self) -> i64 {
+            self.status().map_or(0, |status| microseconds(status.position))
+        }
+
+        // La vitesse est fixée au lancement par --speed, elle n'est pas modifiable depuis le bus
+        #[zbus(property)]
+        fn rate(&self) -> f64 {
+            self.status().map_or(1.0, |status| status.rate)
+        }
+
+        #[zbus(property)]
+        fn minimum_rate(&self) -> f64 {
+            rate_bounds(self.rate()).0
+        }
+
+        #[zbus(property)]
+        fn maximum_rate(&self) -> f64 {
+            rate_bounds(self.rate()).1
+        }
+
+        // Position en microsecondes après un saut
+        #[zbus(signal)]
+        pub async fn seeked(ctxt: &SignalContext<'_>, position: i64) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn volume(&self) -> f64 {
+            1.0
+        }
+
+        #[zbus(property)]
+        fn can_go_next(&self) -> bool {
+            self.status().is_some_and(|status| status.seekable && status.has_chapters)
+        }
+
+        #[zbus(property)]
+        fn can_go_previous(&self) -> bool {
+            self.can_go_next()
+        }
+
+        #[zbus(property)]
+        fn can_play(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn can_pause(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn can_seek(&self) -> bool {
+            self.status().is_some_and(|status| status.seekable)
+        }
+
+        #[zbus(property)]
+        fn can_control(&self) -> bool {
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_bounds_include_normal_speed() {
+        assert_eq!(rate_bounds(1.0), (1.0, 1.0));
+        assert_eq!(rate_bounds(0.5), (0.5, 1.0));
+        assert_eq!(rate_bounds(2.0), (1.0, 2.0));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn second_instance_gets_a_pid_suffix() {
+        assert_eq!(dbus::instance_name(4242), "org.mpris.MediaPlayer2.rust_media_player.instance4242");
+        assert_eq!(dbus::microseconds(1.5), 1_500_000);
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+use ffmpeg::format::Pixel;
+use ffmpeg::util::frame::video::Video;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::sys;
+use sdl2::video::Window;
+use std::mem::MaybeUninit;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+use crate::integer_scale;
+
+// Texture NV12 pour le rendu direct des frames du décodage matériel (--direct-nv12): le plan Y et
+// le plan UV entrelacé sont envoyés tels quels, sans conversion par le scaler, et c'est le GPU qui
+// les convertit à l'affichage. sdl2 0.35 ne connaît pas le format NV12 (PixelFormatEnum,
+// Texture::query): la texture est gérée directement par l'API C de SDL
+pub struct Nv12Texture {
+    raw: *mut sys::SDL_Texture,
+    width: u32,
+    height: u32,
+    // Plans réunis pour SDL, réutilisé d'une frame à l'autre
+    buffer: Vec<u8>,
+}
+
+// Le renderer accepte-t-il des textures NV12 natives? Sans cela, SDL les convertirait lui-même
+// sur le CPU et le rendu direct n'apporterait rien
+pub fn is_supported(canvas: &Canvas<Window>) -> bool {
+    let mut info = MaybeUninit::<sys::SDL_RendererInfo>::uninit();
+    unsafe {
+        if sys::SDL_GetRendererInfo(canvas.raw(), info.as_mut_ptr()) != 0 {
+            return false;
+        }
+        let info = info.assume_init();
+        let count = (info.num_texture_formats as usize).min(info.texture_formats.len());
+        info.texture_formats[..count].contains(&(sys::SDL_PixelFormatEnum::SDL_PIXELFORMAT_NV12 as u32))
+    }
+}
+
+impl Nv12Texture {
+    pub fn new(canvas: &mut Canvas<Window>, width: u32, height: u32) -> Result<Self> {
+        let raw = unsafe {
+            sys::SDL_CreateTexture(
+                canvas.raw(),
+                sys::SDL_PixelFormatEnum::SDL_PIXELFORMAT_NV12 as u32,
+                sys::SDL_TextureAccess::SDL_TEXTUREACCESS_STREAMING as c_int,
+                width as c_int,
+                height as c_int,
+            )
+        };
+        if raw.is_null() {
+            anyhow::bail!("Impossible de créer la texture NV12 {}x{}: {}", width, height, sdl2::get_error());
+        }
+        Ok(Self { raw, width, height, buffer: Vec::new() })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn set_nearest(&mut self, nearest: bool) -> Result<()> {
+        integer_scale::set_nearest_raw(self.raw, nearest)
+    }
+
+    // Mêmes vérifications que yuv_upload: une frame incohérente est ignorée avec un message.
+    // SDL attend les deux plans l'un après l'autre avec le même pas, alors que ffmpeg les alloue
+    // séparément avec des pas qui peuvent différer: les lignes sont recopiées, ce qui reste bien
+    // moins coûteux que la conversion par le scaler. Renvoie false si la frame n'a pas été envoyée
+    pub fn update(&mut self, frame: &Video) -> Result<bool> {
+        if frame.format() != Pixel::NV12 {
+            eprintln!("Frame ignorée: format {:?} au lieu de NV12", frame.format());
+            return Ok(false);
+        }
+        if frame.width() != self.width || frame.height() != self.height {
+            eprintln!(
+                "Frame ignorée: {}x{} pour une texture {}x{}",
+                frame.width(),
+                frame.height(),
+                self.width,
+                self.height
+            );
+            return Ok(false);
+        }
+
+        let planes = [(frame.data(0), frame.stride(0)), (frame.data(1), frame.stride(1))];
+        let pitch = match pack_planes(self.width as usize, self.height as usize, planes, &mut self.buffer) {
+            Ok(pitch) => pitch,
+            Err(reason) => {
+                eprintln!("Frame ignorée: {}", reason);
+                return Ok(false);
+            }
+        };
+
+        let ret = unsafe {
+            sys::SDL_UpdateTexture(self.raw, ptr::null(), self.buffer.as_ptr() as *const c_void, pitch as c_int)
+        };
+        if ret != 0 {
+            anyhow::bail!("Impossible de mettre à jour la texture NV12: {}", sdl2::get_error());
+        }
+        Ok(true)
+    }
+
+    pub fn copy(&self, canvas: &mut Canvas<Window>, destination: Option<Rect>) -> Result<()> {
+        let destination = destination.as_ref().map_or(ptr::null(), |rect| rect.raw());
+        if unsafe { sys::SDL_RenderCopy(canvas.raw(), self.raw, ptr::null(), destination) } != 0 {
+            anyhow::bail!("Impossible d'afficher la texture NV12: {}", sdl2::get_error());
+        }
+        Ok(())
+    }
+}
+
+// Recopie le plan Y puis le plan UV dans buffer avec un même pas, comme SDL les attend. Renvoie ce
+// pas, ou la raison du refus si un plan est trop court ou son pas plus petit qu'une ligne
+fn pack_planes(
+    width: usize,
+    height: usize,
+    planes: [(&[u8], usize); 2],
+    buffer: &mut Vec<u8>,
+) -> Result<usize, String> {
+    // Pas pair: une ligne UV couvre width.div_ceil(2) paires d'octets U et V
+    let pitch = width.div_ceil(2) * 2;
+    let chroma_height = height.div_ceil(2);
+    buffer.clear();
+    let dimensions = [(height, width), (chroma_height, pitch)];
+    for (index, ((plane, stride), (rows, row_bytes))) in planes.into_iter().zip(dimensions).enumerate() {
+        if stride < row_bytes {
+            return Err(format!("pas de {} octets pour une ligne de {} sur le plan {}", stride, row_bytes, index));
+        }
+        let needed = stride * (rows - 1) + row_bytes;
+        if plane.len() < needed {
+            return Err(format!("plan {} de {} octets, {} attendus", index, plane.len(), needed));
+        }
+        for row in plane.chunks(stride).take(rows) {
+            buffer.extend_from_slice(&row[..row_bytes]);
+            buffer.resize(buffer.len() + pitch - row_bytes, 0);
+        }
+    }
+    Ok(pitch)
+}
+
+impl Drop for Nv12Texture {
+    fn drop(&mut self) {
+        unsafe { sys::SDL_DestroyTexture(self.raw) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planes_share_one_pitch() {
+        // 4x2: pas de 8 octets pour Y, 6 pour UV, réunis avec le pas de la texture (4)
+        let luma = [1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8];
+        let chroma = [10, 11, 12, 13, 0, 0];
+        let mut buffer = vec![99; 3];
+        let pitch = pack_planes(4, 2, [(&luma, 8), (&chroma, 6)], &mut buffer).unwrap();
+        assert_eq!(pitch, 4);
+        assert_eq!(buffer, [1, 2, 3, 4, 5, 6, 7, 8, 10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn odd_dimensions_keep_the_last_chroma_pair_and_row() {
+        // 3x3: deux paires UV par ligne sur deux lignes, lignes Y complétées jusqu'au pas pair
+        let luma = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let chroma = [10, 11, 12, 13, 14, 15, 16, 17];
+        let mut buffer = Vec::new();
+        let pitch = pack_planes(3, 3, [(&luma, 3), (&chroma, 4)], &mut buffer).unwrap();
+        assert_eq!(pitch, 4);
+        assert_eq!(buffer, [1, 2, 3, 0, 4, 5, 6, 0, 7, 8, 9, 0, 10, 11, 12, 13, 14, 15, 16, 17]);
+    }
+
+    #[test]
+    fn last_row_may_stop_at_its_pixels() {
+        let luma = vec![0; 32 * 3 + 4];
+        let chroma = vec![0; 32 + 4];
+        let mut buffer = Vec::new();
+        assert_eq!(pack_planes(4, 4, [(&luma, 32), (&chroma, 32)], &mut buffer), Ok(4));
+        assert!(pack_planes(4, 4, [(&luma[1..], 32), (&chroma, 32)], &mut buffer).is_err());
+        assert!(pack_planes(4, 4, [(&luma, 32), (&chroma[1..], 32)], &mut buffer).is_err());
+    }
+
+    #[test]
+    fn rejects_a_stride_smaller_than_a_row() {
+        let plane = vec![0; 64];
+        let mut buffer = Vec::new();
+        assert_eq!(
+            pack_planes(4, 2, [(&plane, 3), (&plane, 4)], &mut buffer),
+            Err("pas de 3 octets pour une ligne de 4 sur le plan 0".to_string())
+        );
+        // UV d'une largeur impaire: la dernière paire compte
+        assert_eq!(
+            pack_planes(5, 2, [(&plane, 8), (&plane, 5)], &mut buffer),
+            Err("pas de 5 octets pour une ligne de 6 sur le plan 1".to_string())
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use ffmpeg_next as ffmpeg;
+use crate::hwaccel::HwAccel;
+use crate::interpolate::InterpolationMode;
+use crate::pip::PipPosition;
+use std::time::Duration;
+
+pub struct Options {
+    pub video_path: String,
+    pub scale_to_window: bool,
+    // Agrandissement par multiples entiers, sans filtrage (pixel art)
+    pub integer_scale: bool,
+    // Taille fixe des miniatures, et bandes noires plutôt que déformation
+    pub output_size: Option<(u32, u32)>,
+    pub keep_aspect: bool,
+    pub loop_range: Option<(f64, f64)>,
+    pub ab_repeat_count: u32,
+    pub volume: f32,
+    pub probe_size: Option<u64>,
+    pub analyze_duration: Option<u64>,
+    pub audio_step: Duration,
+    pub scene_detect: Option<f64>,
+    // Cadence imposée, indispensable pour les séquences d'images qui n'en ont pas
+    pub frame_rate: Option<ffmpeg::Rational>,
+    // Vitesse de lecture initiale (--speed)
+    pub speed: f64,
+    // Index explicites des flux à lire, à la place du choix de ffmpeg
+    pub video_stream: Option<usize>,
+    pub audio_stream: Option<usize>,
+    // Exige des index explicites, sans jamais laisser ffmpeg choisir
+    pub strict_streams: bool,
+    // Incrustation d'un second flux vidéo
+    pub pip: bool,
+    pub pip_position: PipPosition,
+    // Largeur de l'incrustation en fraction de la fenêtre
+    pub pip_scale: f64,
+    // Ligne de progression structurée à la place des statistiques détaillées
+    pub progress: bool,
+    // Graphe des intervalles entre frames et FPS, sans police TTF
+    pub fps_overlay: bool,
+    // Remplissage des tampons audio et vidéo affiché dès le démarrage (touche B)
+    pub buffer_stats: bool,
+    // Afficher d'office la piste de sous-titres forcés
+    pub forced_subs: bool,
+    // Fichier de sous-titres externe, WebVTT ou SRT
+    pub subtitle_file: Option<String>,
+    // Service MPRIS sur le bus de session (Linux)
+    pub mpris: bool,
+    // Segment d'initialisation fMP4 à lire avant le segment média
+    pub init_segment: Option<String>,
+    // Reprendre au début en fin de fichier, sans coupure
+    pub loop_gapless: bool,
+    // Reprendre à la position mémorisée lors de la dernière lecture du fichier
+    pub resume: bool,
+    // Chaîne de filtres ffmpeg appliquée à la vidéo avant le scaler (ex: "hflip,eq=contrast=1.2")
+    pub video_filter: Option<String>,
+    // Chaîne de filtres ffmpeg appliquée au son avant le rééchantillonnage (ex: "volume=2,highpass=f=200")
+    pub audio_filter: Option<String>,
+    // Lister les filtres vidéo disponibles puis quitter
+    pub list_filters: bool,
+    // Images intermédiaires en ralenti (--speed < 1), None sans interpolation
+    pub interpolate: Option<InterpolationMode>,
+    // Plafond de la cadence d'affichage, en plus de la fréquence de l'écran
+    pub max_fps: Option<f64>,
+    // Ne décoder que les images clés, affichées dès qu'elles sont prêtes, sans le son
+    pub keyframes_only: bool,
+    // Fichier de raccourcis clavier (touche=action), None pour les touches par défaut
+    pub keymap: Option<String>,
+    // Délai sans nouvelle frame au-delà duquel la lecture est considérée bloquée, None sans surveillance
+    pub read_timeout: Option<Duration>,
+    // Pilote de rendu SDL, None = choix de SDL
+    pub render_driver: Option<String>,
+    // Canal écouté seul (--solo), à partir de 0
+    pub solo_channel: Option<u8>,
+    pub hwaccel: HwAccel,
+    // Frames NV12 du décodage matériel envoyées telles quelles dans une texture NV12, sans le scaler
+    pub direct_nv12: bool,
+    pub dump_audio: Option<String>,
+    // Frames en RGBA brut, sans affichage
+    pub dump_frames: Option<String>,
+    // Miniature en PPM, sans affichage
+    pub thumbnail: Option<String>,
+    pub loudness: bool,
+    // Statistiques de synchronisation A/V simulée, sans affichage
+    pub verify_sync: bool,
+    // Sans affichage: tout décoder sans rien rendre ni jouer, code de sortie non nul en cas d'erreur
+    pub null_output: bool,
+    pub wav_rate: Option<u32>,
+    pub wav_channels: Option<u16>,
+    // Options ffmpeg libres (--opt clé=valeur), pour le démuxeur et les décodeurs
+    pub ffmpeg_options: Vec<(String, String)>,
+    pub trace_csv: Option<String>,
+    // WAV de ce que joue réellement le périphérique audio
+    pub record_output: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            video_path: String::new(),
+            scale_to_window: false,
+            integer_scale: false,
+            output_size: None,
+            keep_aspect: false,
+            loop_range: None,
+            ab_repeat_count: 0,
+            volume: 1.0,
+            probe_size: None,
+            analyze_duration: None,
+            audio_step: Duration::from_secs(1),
+            scene_detect: None,
+            frame_rate: None,
+            speed: 1.0,
+            video_stream: None,
+            audio_stream: None,
+            strict_streams: false,
+            pip: false,
+            pip_position: PipPosition::BottomRight,
+            pip_scale: 0.25,
+            progress: false,
+            fps_overlay: false,
+            buffer_stats: false,
+            forced_subs: false,
+            subtitle_file: None,
+            mpris: false,
+            init_segment: None,
+            loop_gapless: false,
+            resume: false,
+            video_filter: None,
+            audio_filter: None,
+            list_filters: false,
+            interpolate: None,
+            max_fps: None,
+            read_timeout: None,
+            keymap: None,
+            keyframes_only: false,
+            render_driver: None,
+            solo_channel: None,
+            hwaccel: HwAccel::None,
+            direct_nv12: false,
+            dump_audio: None,
+            dump_frames: None,
+            thumbnail: None,
+            loudness: false,
+            verify_sync: false,
+            null_output: false,
+            wav_rate: None,
+            wav_channels: None,
+            ffmpeg_options: Vec::new(),
+            trace_csv: None,
+            record_output: None,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+use ffmpeg::util::frame::video::Video;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+use std::collections::VecDeque;
+use crate::decoder::Decoder;
+use crate::yuv_upload;
+
+// Frames secondaires gardées en attente de l'horloge principale
+const MAX_PENDING_FRAMES: usize = 8;
+// Marge entre l'incrustation et le bord de la fenêtre, en pixels
+const MARGIN: i32 = 16;
+
+// Coin de la fenêtre où s'affiche l'incrustation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl PipPosition {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "top-left" => Ok(PipPosition::TopLeft),
+            "top-right" => Ok(PipPosition::TopRight),
+            "bottom-left" => Ok(PipPosition::BottomLeft),
+            "bottom-right" => Ok(PipPosition::BottomRight),
+            _ => anyhow::bail!(
+                "Position d'incrustation inconnue: {} (top-left, top-right, bottom-left ou bottom-right)",
+                value
+            ),
+        }
+    }
+}
+
+// Second flux vidéo (interprète en langue des signes, autre angle...) incrusté dans un coin
+pub struct PipOverlay {
+    pub decoder: Decoder,
+    position: PipPosition,
+    // Largeur de l'incrustation en fraction de la fenêtre
+    scale: f64,
+    pub visible: bool,
+    pending: VecDeque<Video>,
+    has_frame: bool,
+}
+
+impl PipOverlay {
+    // Le flux secondaire est décodé directement à taille réduite, par rapport à la vidéo principale
+    pub fn new(mut decoder: Decoder, position: PipPosition, scale: f64, main_width: u32) -> Result<Self> {
+        let (width, height) = (decoder.decoder.width(), decoder.decoder.height());
+        let overlay_width = even((f64::from(main_width) * scale) as u32).min(width);
+        let overlay_height = even((u64::from(overlay_width) * u64::from(height) / u64::from(width)) as u32);
+        decoder.set_output_size(overlay_width, overlay_height)?;
+        println!("Incrustation du flux {} en {}x{}", decoder.stream_index, overlay_width, overlay_height);
+
+        Ok(Self {
+            decoder,
+            position,
+            scale,
+            visible: true,
+            pending: VecDeque::with_capacity(MAX_PENDING_FRAMES),
+            has_frame: false,
+        })
+    }
+
+    // Taille des frames décodées, pour la texture
+    pub fn size(&self) -> (u32, u32) {
+        let output = self.decoder.scaler.output();
+        (output.width, output.height)
+    }
+
+    // Décode un paquet du flux secondaire, ses frames attendent leur heure dans la file
+    pub fn push_packet(&mut self, packet: &ffmpeg::Packet) -> Result<()> {
+        self.decoder.decoder.send_packet(packet)?;
+        let mut frame = Video::empty();
+        while self.decoder.receive_frame_yuv(&mut frame)? {
+            if self.pending.len() == MAX_PENDING_FRAMES {
+                self.pending.pop_front();
+            }
+            self.pending.push_back(std::mem::replace(&mut frame, Video::empty()));
+        }
+        Ok(())
+    }
+
+    // Charge dans la texture la dernière frame secondaire atteinte par l'horloge principale
+    pub fn update(&mut self, texture: &mut Texture, clock: f64) -> Result<()> {
+        let time_base = self.decoder.time_base;
+        let mut latest = None;
+        while self
+            .pending
+            .front()
+            .is_some_and(|frame| frame.pts().unwrap_or(0) as f64 * time_base <= clock)
+        {
+            latest = self.pending.pop_front();
+        }
+
+        if let Some(frame) = latest {
+            if yuv_upload::upload(texture, &frame)? {
+                self.has_frame = true;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, texture: &Texture) -> Result<()> {
+        if !self.visible || !self.has_frame {
+            return Ok(());
+        }
+        let (canvas_width, canvas_height) = canvas.output_size().map_err(|e| anyhow::anyhow!(e))?;
+        let (width, height) = self.size();
+        let rect_width = (f64::from(canvas_width) * self.scale) as u32;
+        let rect_height = (u64::from(rect_width) * u64::from(height) / u64::from(width)) as u32;
+
+        let right = canvas_width as i32 - rect_width as i32 - MARGIN;
+        let bottom = canvas_height as i32 - rect_height as i32 - MARGIN;
+        let (x, y) = match self.position {
+            PipPosition::TopLeft => (MARGIN, MARGIN),
+            PipPosition::TopRight => (right, MARGIN),
+            PipPosition::BottomLeft => (MARGIN, bottom),
+            PipPosition::BottomRight => (right, bottom),
+        };
+        canvas
+            .copy(texture, None, Some(Rect::new(x, y, rect_width, rect_height)))
+            .map_err(|e| anyhow::anyhow!(e))
+    }
+
+    // Après un seek: les frames en attente et les références du décodeur sont périmées
+    pub fn flush(&mut self) {
+        self.decoder.decoder.flush();
+        self.pending.clear();
+    }
+}
+
+// Le YUV 4:2:0 demande des dimensions paires
+fn even(value: u32) -> u32 {
+    (value & !1).max(2)
+}
@@ -0,0 +1,1335 @@
+use anyhow::{Context, Result};
+use ffmpeg_next as ffmpeg;
+use ffmpeg::ffi;
+use ffmpeg::format::context::Input;
+use ffmpeg::format::Pixel;
+use ffmpeg::software::scaling::{context::Context as ScalingContext, flag::Flags};
+use ffmpeg::util::frame::video::Video;
+use crate::avio::ReaderIo;
+use crate::buffer_stats::BufferStats;
+use crate::error::PlayerError;
+use crate::hwaccel::{self, HwAccel};
+use crate::subtitle::SubtitleTrack;
+use crate::watchdog;
+use crate::decoder::Decoder;
+use crate::options::Options;
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::sync::Arc;
+
+// Piste audio ou de sous-titres proposée par le fichier
+#[derive(Clone, Debug)]
+pub struct TrackInfo {
+    pub index: usize,
+    pub medium: ffmpeg::media::Type,
+    pub codec: ffmpeg::codec::Id,
+    pub language: Option<String>,
+}
+
+// Options du démuxeur, les valeurs par défaut de ffmpeg sont conservées si rien n'est fourni
+fn input_options(options: &Options) -> ffmpeg::Dictionary<'static> {
+    let mut dictionary = ffmpeg::Dictionary::new();
+    if let Some(probe_size) = options.probe_size {
+        dictionary.set("probesize", &probe_size.to_string());
+    }
+    if let Some(analyze_duration) = options.analyze_duration {
+        dictionary.set("analyzeduration", &analyze_duration.to_string());
+    }
+    if let Some(frame_rate) = options.frame_rate {
+        // Option du démuxeur image2, ignorée par les autres formats
+        dictionary.set("framerate", &frame_rate.to_string());
+    }
+    for (key, value) in &options.ffmpeg_options {
+        dictionary.set(key, value);
+    }
+    dictionary
+}
+
+// Options libres de --opt, proposées aussi aux décodeurs
+fn decoder_options(options: &Options) -> ffmpeg::Dictionary<'static> {
+    let mut dictionary = ffmpeg::Dictionary::new();
+    for (key, value) in &options.ffmpeg_options {
+        dictionary.set(key, value);
+    }
+    dictionary
+}
+
+fn dictionary_keys(dictionary: &ffmpeg::Dictionary) -> Vec<String> {
+    dictionary.iter().map(|(key, _)| key.to_string()).collect()
+}
+
+// Comme format::input_with_dictionary, en rendant les options que ffmpeg n'a pas consommées
+fn open_input(path: &str, dictionary: ffmpeg::Dictionary) -> Result<(Input, Vec<String>)> {
+    let path = CString::new(path)?;
+    unsafe { open_input_context(ptr::null_mut(), path.as_ptr(), dictionary) }
+}
+
+// Même chose sans chemin: le démuxeur lit par les callbacks du contexte AVIO
+fn open_input_io(io: &mut ReaderIo, dictionary: ffmpeg::Dictionary) -> Result<(Input, Vec<String>)> {
+    unsafe {
+        let context = ffi::avformat_alloc_context();
+        if context.is_null() {
+            anyhow::bail!("Impossible d'allouer le contexte de format");
+        }
+        (*context).pb = io.as_mut_ptr();
+        open_input_context(context, ptr::null(), dictionary)
+    }
+}
+
+// context est nul ou préparé avec un pb; ffmpeg le libère en cas d'échec
+unsafe fn open_input_context(
+    mut context: *mut ffi::AVFormatContext,
+    url: *const c_char,
+    dictionary: ffmpeg::Dictionary,
+) -> Result<(Input, Vec<String>)> {
+    if context.is_null() {
+        context = ffi::avformat_alloc_context();
+        if context.is_null() {
+            anyhow::bail!("Impossible d'allouer le contexte de format");
+        }
+    }
+    // Le callback doit être en place avant l'ouverture: les protocoles en gardent une copie
+    (*context).interrupt_callback = watchdog::interrupt_callback();
+
+    let mut raw_options = dictionary.disown();
+    let ret = watchdog::guard(|| ffi::avformat_open_input(&mut context, url, ptr::null(), &mut raw_options));
+    let unused = dictionary_keys(&ffmpeg::Dictionary::own(raw_options));
+    if ret < 0 {
+        return Err(ffmpeg::Error::from(ret).into());
+    }
+
+    let ret = watchdog::guard(|| ffi::avformat_find_stream_info(context, ptr::null_mut()));
+    if ret < 0 {
+        ffi::avformat_close_input(&mut context);
+        return Err(ffmpeg::Error::from(ret).into());
+    }
+    Ok((Input::wrap(context), unused))
+}
+
+// Entrée à ouvrir: avec --init-segment, le segment d'initialisation fMP4 (moov) est placé devant
+// le segment média (moof/mdat) par le protocole concat de ffmpeg, qui les lit comme un seul fichier
+fn input_url(options: &Options) -> Result<String> {
+    let Some(ref init_segment) = options.init_segment else {
+        return Ok(options.video_path.clone());
+    };
+    for path in [init_segment, &options.video_path] {
+        // '|' sépare les entrées du protocole concat
+        if path.contains('|') {
+            anyhow::bail!("--init-segment: le chemin {} ne doit pas contenir '|'", path);
+        }
+        if !is_network_input(path) && !std::path::Path::new(path).exists() {
+            anyhow::bail!("--init-segment: {} introuvable", path);
+        }
+    }
+    Ok(format!("concat:{}|{}", init_segment, options.video_path))
+}
+
+// Un segment média fMP4 (DASH, HLS) commence par une boîte styp, sidx ou moof: sans la boîte moov
+// du segment d'initialisation, le démuxeur mp4 ne sait pas décrire les flux
+fn is_media_segment(path: &str) -> bool {
+    let mut header = [0u8; 8];
+    let read = File::open(path).and_then(|mut file| file.read_exact(&mut header));
+    read.is_ok() && matches!(&header[4..8], b"styp" | b"sidx" | b"moof")
+}
+
+// Comme Decoder::open_as_with, en rendant les options que le décodeur n'a pas consommées
+fn open_codec(
+    context: ffmpeg::codec::Context,
+    dictionary: ffmpeg::Dictionary,
+) -> Result<(ffmpeg::codec::decoder::Opened, Vec<String>)> {
+    let mut decoder = context.decoder();
+    let codec = ffmpeg::codec::decoder::find(decoder.id())
+        .ok_or(ffmpeg::Error::DecoderNotFound)
+        .context("Aucun décodeur disponible pour ce codec")?;
+    unsafe {
+        let mut raw_options = dictionary.disown();
+        let ret = ffi::avcodec_open2(decoder.as_mut_ptr(), codec.as_ptr(), &mut raw_options);
+        let unused = dictionary_keys(&ffmpeg::Dictionary::own(raw_options));
+        if ret < 0 {
+            return Err(ffmpeg::Error::from(ret).into());
+        }
+        Ok((ffmpeg::codec::decoder::Opened(decoder), unused))
+    }
+}
+
+// Les flux non lus (données, pièces jointes, pistes secondaires) sont écartés dès le démuxeur,
+// av_read_frame ne renvoie alors plus leurs paquets. Les polices des pièces jointes ne sont pas
+// extraites: les sous-titres sont dessinés avec la police intégrée, qui ne lit pas les TTF/OTF
+fn discard_unused_streams(ictx: &mut Input, used: &[Option<usize>]) {
+    for index in 0..ictx.nb_streams() as usize {
+        if !used.contains(&Some(index)) {
+            set_stream_enabled(ictx, index, false);
+        }
+    }
+}
+
+// Réactive ou écarte un flux pendant la lecture, par exemple au changement de piste de sous-titres
+pub fn set_stream_enabled(ictx: &mut Input, index: usize, enabled: bool) {
+    if let Some(mut stream) = ictx.stream_mut(index) {
+        let discard = if enabled { ffi::AVDiscard::AVDISCARD_DEFAULT } else { ffi::AVDiscard::AVDISCARD_ALL };
+        unsafe {
+            (*stream.as_mut_ptr()).discard = discard;
+        }
+    }
+}
+
+// Instant de l'image clé qui précède `seconds` sur un flux, d'après l'index du démuxeur (MP4, MKV...).
+// None sans index, par exemple pour un flux réseau ou un fichier TS
+pub fn keyframe_before(ictx: &mut Input, index: usize, seconds: f64) -> Option<f64> {
+    let mut stream = ictx.stream_mut(index)?;
+    let time_base = f64::from(stream.time_base());
+    let timestamp = (seconds / time_base).round() as i64;
+    unsafe {
+        let entry = ffi::avformat_index_get_entry_from_timestamp(
+            stream.as_mut_ptr(),
+            timestamp,
+            ffi::AVSEEK_FLAG_BACKWARD as c_int,
+        );
+        (!entry.is_null()).then(|| (*entry).timestamp as f64 * time_base)
+    }
+}
+
+fn open_video_decoder(
+    video_stream: &ffmpeg::Stream,
+    options: &Options,
+    accel: HwAccel,
+) -> Result<(Decoder, Vec<String>)> {
+    println!("Information flux vidéo:");
+    println!("  Time base: {}", video_stream.time_base());
+    println!("  Frame rate: {}", video_stream.rate());
+    println!("  Duration: {} secondes", video_stream.duration() as f64 * f64::from(video_stream.time_base()));
+    println!("  Codec: {:?}", video_stream.parameters().id());
+
+    open_with_fallback(accel, |accel| open_video_decoder_with(video_stream, options, accel))
+}
+
+// Un périphérique créé ne garantit pas que le décodeur s'ouvre (pilote incomplet, GPU occupé...):
+// toute erreur côté matériel se replie sur le décodage logiciel au lieu d'arrêter la lecture.
+// L'API retenue est gardée dans decoder.hw_accel. open ouvre le décodeur pour l'API donnée
+fn open_with_fallback<T>(accel: HwAccel, mut open: impl FnMut(HwAccel) -> Result<T>) -> Result<T> {
+    if accel == HwAccel::None {
+        println!("Décodage logiciel");
+        return open(HwAccel::None);
+    }
+    match open(accel) {
+        Ok(opened) => {
+            println!("Décodage matériel: {}", accel.name());
+            Ok(opened)
+        }
+        Err(e) => {
+            println!(
+                "Accélération {} indisponible ({:#}), repli sur le décodage logiciel",
+                accel.name(),
+                e
+            );
+            open(HwAccel::None)
+        }
+    }
+}
+
+fn open_video_decoder_with(
+    video_stream: &ffmpeg::Stream,
+    options: &Options,
+    accel: HwAccel,
+) -> Result<(Decoder, Vec<String>)> {
+    let mut context = ffmpeg::codec::Context::from_parameters(video_stream.parameters())?;
+    if accel != HwAccel::None {
+        hwaccel::attach_device(&mut context, accel)?;
+    }
+
+    let (opened, unused) = open_codec(context, decoder_options(options))?;
+    let mut decoder = Decoder::new(opened.video()?, video_stream, options.frame_rate)?;
+    decoder.hw_accel = accel;
+    decoder.speed = options.speed;
+    decoder.log_stats = !options.progress;
+    Ok((decoder, unused))
+}
+
+// Rouvre le décodeur vidéo d'un flux déjà lu, par exemple pour passer du matériel au logiciel
+pub fn reopen_video_decoder(ictx: &Input, stream_index: usize, options: &Options, accel: HwAccel) -> Result<Decoder> {
+    let stream = ictx.stream(stream_index).context("Flux vidéo introuvable")?;
+    let (mut decoder, _) = open_video_decoder(&stream, options, accel)?;
+    configure_filters(&mut decoder, &stream, options)?;
+    Ok(decoder)
+}
+
+// --vf et --interpolate ne s'appliquent qu'au flux principal, l'interpolation seulement en ralenti
+fn configure_filters(decoder: &mut Decoder, stream: &ffmpeg::Stream, options: &Options) -> Result<()> {
+    if let Some(ref spec) = options.video_filter {
+        decoder.add_filter(spec, stream.time_base())?;
+    }
+    match options.interpolate {
+        Some(mode) if options.speed < 1.0 => decoder.enable_interpolation(mode, stream.time_base())?,
+        Some(_) => println!("--interpolate: sans effet à vitesse x{:.2}, seul le ralenti est interpolé", options.speed),
+        None => {}
+    }
+    Ok(())
+}
+
+// Chapitre du fichier, bornes en secondes
+#[derive(Clone, Debug)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
+}
+
+impl Chapter {
+    pub fn describe(&self, index: usize) -> String {
+        match self.title {
+            Some(ref title) => format!("Chapitre {}: {}", index + 1, title),
+            None => format!("Chapitre {}", index + 1),
+        }
+    }
+}
+
+// Chapitre contenant la position donnée
+pub fn chapter_at(chapters: &[Chapter], position: f64) -> Option<usize> {
+    chapters.iter().rposition(|chapter| chapter.start <= position)
+}
+
+// Début du chapitre suivant ou précédent. Comme la plupart des lecteurs, reculer revient au début
+// du chapitre courant sauf dans la première seconde, où on passe au précédent
+pub fn chapter_start(chapters: &[Chapter], position: f64, forward: bool) -> Option<f64> {
+    let chapter = if forward {
+        chapters.iter().find(|chapter| chapter.start > position + 0.01)
+    } else {
+        chapters.iter().rev().find(|chapter| chapter.start < position - 1.0)
+    };
+    chapter.map(|chapter| chapter.start)
+}
+
+// Flux vidéo à lire: les images de couverture (pochettes de MP3...) ne sont pas une vidéo,
+// on prend alors un autre flux vidéo s'il y en a un
+fn main_video_stream(ictx: &Input) -> Option<ffmpeg::Stream<'_>> {
+    let is_picture = |stream: &ffmpeg::Stream| {
+        stream.disposition().contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC)
+    };
+    match ictx.streams().best(ffmpeg::media::Type::Video) {
+        Some(stream) if !is_picture(&stream) => Some(stream),
+        Some(stream) => {
+            println!("Flux {} ignoré: image de couverture", stream.index());
+            ictx.streams()
+                .find(|other| other.parameters().medium() == ffmpeg::media::Type::Video && !is_picture(other))
+        }
+        None => None,
+    }
+}
+
+// Liste de tous les flux avec leur index et leur type, pour aider à choisir --video-stream/--audio-stream
+fn describe_streams(ictx: &Input) -> String {
+    let mut description = "Flux disponibles:".to_string();
+    for stream in ictx.streams() {
+        let parameters = stream.parameters();
+        description.push_str(&format!("\n  {}: {:?} {:?}", stream.index(), parameters.medium(), parameters.id()));
+        if stream.disposition().contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC) {
+            description.push_str(" (image de couverture)");
+        }
+    }
+    description
+}
+
+// Flux demandé par son index, qui doit exister et être du bon type
+fn select_stream(ictx: &Input, index: usize, medium: ffmpeg::media::Type) -> Result<ffmpeg::Stream<'_>> {
+    let stream = ictx
+        .stream(index)
+        .with_context(|| format!("Flux {} inexistant\n{}", index, describe_streams(ictx)))?;
+    let actual = stream.parameters().medium();
+    if actual != medium {
+        anyhow::bail!(
+            "Le flux {} est de type {:?} et non {:?}\n{}",
+            index,
+            actual,
+            medium,
+            describe_streams(ictx)
+        );
+    }
+    Ok(stream)
+}
+
+// Flux vidéo secondaire pour l'incrustation: le premier autre flux vidéo qui n'est pas une image
+fn secondary_video_stream(ictx: &Input, main_index: usize) -> Option<ffmpeg::Stream<'_>> {
+    ictx.streams().find(|stream| {
+        stream.index() != main_index
+            && stream.parameters().medium() == ffmpeg::media::Type::Video
+            && !stream.disposition().contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC)
+    })
+}
+
+// Pistes de sous-titres dans l'ordre des flux
+fn subtitle_tracks(ictx: &Input) -> Vec<SubtitleTrack> {
+    ictx.streams()
+        .filter(|stream| stream.parameters().medium() == ffmpeg::media::Type::Subtitle)
+        .map(|stream| SubtitleTrack {
+            index: stream.index(),
+            codec: stream.parameters().id(),
+            language: stream.metadata().get("language").map(str::to_string),
+            forced: stream.disposition().contains(ffmpeg::format::stream::Disposition::FORCED),
+        })
+        .collect()
+}
+
+// Piste forcée à afficher d'office: de préférence dans la langue de l'audio, sinon la première
+fn forced_subtitle_track(tracks: &[SubtitleTrack], audio_language: Option<&str>) -> Option<usize> {
+    let forced = || tracks.iter().enumerate().filter(|(_, track)| track.forced);
+    forced()
+        .find(|(_, track)| audio_language.is_some() && track.language.as_deref() == audio_language)
+        .or_else(|| forced().next())
+        .map(|(position, _)| position)
+}
+
+// Contrairement à packets(), qui réessaie en silence, distingue la fin du fichier (Ok(None))
+// d'une vraie erreur de lecture
+pub fn read_packet(ictx: &mut Input) -> Result<Option<ffmpeg::Packet>, ffmpeg::Error> {
+    let mut packet = ffmpeg::Packet::empty();
+    // Une lecture bloquée au-delà de --read-timeout est interrompue par le watchdog (Error::Exit)
+    let read = watchdog::guard(|| packet.read(ictx));
+    Ok(packet_read(read)?.then_some(packet))
+}
+
+// true si un paquet a été lu, false en fin de fichier. Toute autre erreur, y compris une lecture
+// interrompue, reste une erreur: elle ne doit pas passer pour la fin et vider les décodeurs
+fn packet_read(read: Result<(), ffmpeg::Error>) -> Result<bool, ffmpeg::Error> {
+    match read {
+        Ok(()) => Ok(true),
+        Err(ffmpeg::Error::Eof) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+// Dimensions d'affichage d'après le rapport d'aspect des pixels (SAR): seule la largeur change,
+// comme ffmpeg pour un anamorphique. SAR inconnu (0/1) ou carré: taille codée inchangée
+fn display_size(frame: &Video) -> (u32, u32) {
+    sar_display_size(frame.width(), frame.height(), frame.aspect_ratio())
+}
+
+// Même calcul à partir de la taille et du SAR, pour le rendu à l'écran
+pub fn sar_display_size(width: u32, height: u32, sar: ffmpeg::Rational) -> (u32, u32) {
+    if sar.numerator() <= 0 || sar.denominator() <= 0 || sar.numerator() == sar.denominator() {
+        return (width, height);
+    }
+    let display_width = (f64::from(width) * f64::from(sar)).round() as u32;
+    (display_width.max(2), height)
+}
+
+// Plus grande taille aux proportions de la source qui tient dans le cadre, au moins 2x2 pour le scaler
+fn fit_within(source_width: u32, source_height: u32, width: u32, height: u32) -> (u32, u32) {
+    let scale = (f64::from(width) / f64::from(source_width.max(1))).min(f64::from(height) / f64::from(source_height.max(1)));
+    let fitted_width = (f64::from(source_width) * scale).round() as u32;
+    let fitted_height = (f64::from(source_height) * scale).round() as u32;
+    (fitted_width.clamp(2, width), fitted_height.clamp(2, height))
+}
+
+pub fn is_network_input(path: &str) -> bool {
+    path.contains("://") && !path.starts_with("file:")
+}
+
+// Position par défaut de la miniature, en fraction de la durée
+const POSTER_FRACTION: f64 = 0.1;
+// Sans durée connue, nombre de frames examinées pour trouver une image non noire
+const POSTER_MAX_FRAMES: usize = 120;
+// Luminance moyenne (0-255) en dessous de laquelle une frame est considérée comme noire
+const POSTER_BLACK_LUMA: f64 = 16.0;
+
+// Placement d'une image de taille d'affichage donnée dans une miniature: taille de la miniature,
+// taille de l'image mise à l'échelle et son décalage, nul sans bandes noires
+#[derive(Debug, PartialEq, Eq)]
+struct PosterLayout {
+    size: (u32, u32),
+    scaled: (u32, u32),
+    offset: (u32, u32),
+}
+
+impl PosterLayout {
+    fn new(display: (u32, u32), output_size: Option<(u32, u32)>, keep_aspect: bool) -> Self {
+        let (width, height) = output_size.unwrap_or(display);
+        let scaled = if keep_aspect { fit_within(display.0, display.1, width, height) } else { (width, height) };
+        let offset = ((width - scaled.0) / 2, (height - scaled.1) / 2);
+        Self { size: (width, height), scaled, offset }
+    }
+}
+
+// Image décodée en RGB 8 bits, lignes contiguës sans remplissage
+pub struct DecodedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl DecodedFrame {
+    fn average_luma(&self) -> f64 {
+        let pixels = self.data.len() / 3;
+        if pixels == 0 {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .data
+            .chunks_exact(3)
+            .map(|rgb| 0.299 * f64::from(rgb[0]) + 0.587 * f64::from(rgb[1]) + 0.114 * f64::from(rgb[2]))
+            .sum();
+        sum / pixels as f64
+    }
+
+    // PPM binaire (P6): lisible par la plupart des visionneuses et convertisseurs, sans dépendance
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.data)
+    }
+}
+
+// Écrit une image RGBA mise à l'échelle (lignes de source_stride octets) à sa place dans buf, une
+// ligne tous les stride octets, entourée de bandes noires opaques si elle ne remplit pas le cadre
+fn place_rgba(layout: &PosterLayout, source: &[u8], source_stride: usize, buf: &mut [u8], stride: usize) {
+    let row_size = layout.size.0 as usize * 4;
+    if layout.scaled != layout.size {
+        for row in 0..layout.size.1 as usize {
+            for pixel in buf[row * stride..row * stride + row_size].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+    }
+    let scaled_row_size = layout.scaled.0 as usize * 4;
+    let left = layout.offset.0 as usize * 4;
+    let top = layout.offset.1 as usize;
+    for row in 0..layout.scaled.1 as usize {
+        let start = (top + row) * stride + left;
+        let line = &source[row * source_stride..row * source_stride + scaled_row_size];
+        buf[start..start + scaled_row_size].copy_from_slice(line);
+    }
+}
+
+// Première frame qui n'est pas noire, pour éviter un fondu d'ouverture, sinon la dernière examinée
+fn choose_poster(frames: impl Iterator<Item = Result<DecodedFrame>>) -> Result<Option<DecodedFrame>> {
+    let mut poster = None;
+    for frame in frames.take(POSTER_MAX_FRAMES) {
+        let frame = frame?;
+        let is_black = frame.average_luma() < POSTER_BLACK_LUMA;
+        poster = Some(frame);
+        if !is_black {
+            break;
+        }
+    }
+    Ok(poster)
+}
+
+// La dernière ligne n'a pas besoin du remplissage jusqu'au pas suivant
+fn check_rgba_buffer(width: u32, height: u32, len: usize, stride: usize) -> Result<()> {
+    let row_size = width as usize * 4;
+    let required = stride * (height as usize).saturating_sub(1) + row_size;
+    if stride < row_size || len < required {
+        anyhow::bail!(
+            "Buffer trop petit pour une frame {}x{}: {} octets avec un pas de {} (minimum {} octets, pas de {})",
+            width,
+            height,
+            len,
+            stride,
+            required,
+            row_size
+        );
+    }
+    Ok(())
+}
+
+// io_seekable: champ seekable du contexte AVIO, None si le démuxeur n'en a pas
+fn is_seekable(ctx_flags: c_int, io_seekable: Option<c_int>, duration: i64) -> bool {
+    if ctx_flags & ffi::AVFMTCTX_UNSEEKABLE as c_int != 0 {
+        return false;
+    }
+    match io_seekable {
+        Some(flags) => flags & ffi::AVIO_SEEKABLE_NORMAL as c_int != 0,
+        // Démuxeurs sans I/O propre (séquences d'images...): seule une durée connue permet la recherche
+        None => duration > 0,
+    }
+}
+
+// Fichier ouvert avec ses décodeurs, et ce que l'entrée permet de faire
+pub struct Player {
+    pub ictx: Input,
+    pub decoder: Option<Decoder>,
+    // Décodeur du flux incrusté, seulement avec --pip
+    pub pip_decoder: Option<Decoder>,
+    pub audio_decoder: Option<ffmpeg::codec::decoder::Audio>,
+    pub audio_stream_index: Option<usize>,
+    pub subtitle_tracks: Vec<SubtitleTrack>,
+    // Position dans subtitle_tracks de la piste affichée au démarrage (--forced-subs)
+    pub subtitle_track: Option<usize>,
+    // Source en mémoire de open_reader: déclarée après ictx pour n'être libérée qu'après lui
+    #[allow(dead_code)]
+    reader_io: Option<ReaderIo>,
+    // Conversion vers RGBA de render_to, créée à la première frame
+    rgba_scaler: Option<ScalingContext>,
+    // Frame que render_to n'a pas pu écrire (buffer trop petit), rendue à l'appel suivant
+    pending_frame: Option<Video>,
+    // Taille imposée aux miniatures et au rendu RGBA (--output-size), avec bandes noires si keep_aspect
+    output_size: Option<(u32, u32)>,
+    keep_aspect: bool,
+    // Remplissage des tampons, partagé avec le callback audio et l'affichage de l'hôte
+    buffer_stats: Arc<BufferStats>,
+}
+
+impl Player {
+    pub fn open(options: &Options) -> Result<Self, PlayerError> {
+        Self::open_path(options).map_err(PlayerError::classify)
+    }
+
+    // Comme open, mais le média est lu dans un lecteur (octets téléchargés ou déchiffrés en mémoire)
+    // au lieu d'un chemin, sans fichier temporaire. options.video_path est ignoré. Si le lecteur
+    // ne sait pas donner sa position, seekable() renvoie false.
+    pub fn open_reader<R: Read + Seek + Send + 'static>(reader: R, options: &Options) -> Result<Self, PlayerError> {
+        let open = || -> Result<Self> {
+            let mut io = ReaderIo::new(reader)?;
+            let (ictx, unused) = open_input_io(&mut io, input_options(options))?;
+            Self::from_input(ictx, unused, Some(io), options)
+        };
+        open().map_err(PlayerError::classify)
+    }
+
+    fn open_path(options: &Options) -> Result<Self> {
+        let (ictx, unused) = match open_input(&input_url(options)?, input_options(options)) {
+            Ok(opened) => opened,
+            Err(e) if options.init_segment.is_none() && is_media_segment(&options.video_path) => {
+                return Err(e.context(
+                    "Segment média fMP4 sans segment d'initialisation: fournir celui-ci avec --init-segment <init.mp4>",
+                ));
+            }
+            Err(e) => return Err(e),
+        };
+        Self::from_input(ictx, unused, None, options)
+    }
+
+    fn from_input(
+        mut ictx: Input,
+        mut unused: Vec<String>,
+        reader_io: Option<ReaderIo>,
+        options: &Options,
+    ) -> Result<Self> {
+
+        if options.strict_streams && options.video_stream.is_none() && options.audio_stream.is_none() {
+            anyhow::bail!(
+                "--strict-streams demande --video-stream et/ou --audio-stream\n{}",
+                describe_streams(&ictx)
+            );
+        }
+
+        // Sans index explicite, le choix de ffmpeg (best) est conservé, sauf en mode strict
+        let video_stream = match options.video_stream {
+            Some(index) => Some(select_stream(&ictx, index, ffmpeg::media::Type::Video)?),
+            None if options.strict_streams => None,
+            None => main_video_stream(&ictx),
+        };
+        let decoder = match video_stream {
+            Some(video_stream) => {
+                let (mut decoder, video_unused) = open_video_decoder(&video_stream, options, options.hwaccel)?;
+                configure_filters(&mut decoder, &video_stream, options)?;
+                unused.retain(|key| video_unused.contains(key));
+                Some(decoder)
+            }
+            None => {
+                println!("Aucun flux vidéo trouvé, lecture audio seule");
+                None
+            }
+        };
+
+        let pip_decoder = match decoder.as_ref().filter(|_| options.pip) {
+            Some(main) => match secondary_video_stream(&ictx, main.stream_index) {
+                Some(stream) => {
+                    // Le flux secondaire reste en décodage logiciel, le matériel est réservé au principal
+                    let (pip_decoder, _) = open_video_decoder(&stream, options, HwAccel::None)?;
+                    Some(pip_decoder)
+                }
+                None => {
+                    println!("--pip: aucun second flux vidéo dans ce fichier");
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let audio_stream = match options.audio_stream {
+            Some(index) => Some(select_stream(&ictx, index, ffmpeg::media::Type::Audio)?),
+            None if options.strict_streams => None,
+            None => ictx.streams().best(ffmpeg::media::Type::Audio),
+        };
+        let audio_decoder = audio_stream
+            .and_then(|stream| {
+                println!("Information flux audio:");
+                println!("  Time base: {}", stream.time_base());
+                let context = ffmpeg::codec::Context::from_parameters(stream.parameters()).ok()?;
+                let (opened, audio_unused) = open_codec(context, decoder_options(options)).ok()?;
+                let audio_dec = opened.audio().ok()?;
+                println!("  Channels: {}", audio_dec.channels());
+                println!("  Sample format: {:?}", audio_dec.format());
+                println!("  Sample rate: {} Hz", audio_dec.rate());
+                Some((audio_dec, audio_unused, stream.index()))
+            })
+            .map(|(dec, audio_unused, index)| {
+                unused.retain(|key| audio_unused.contains(key));
+                (dec, index)
+            });
+        let (audio_decoder, audio_stream_index) = match audio_decoder {
+            Some((dec, index)) => (Some(dec), Some(index)),
+            None => (None, None),
+        };
+
+        // Une option --opt consommée ni par le démuxeur ni par un décodeur est sans effet
+        for (key, _) in &options.ffmpeg_options {
+            if unused.contains(key) {
+                println!("Attention: option --opt {} ignorée par ffmpeg", key);
+            }
+        }
+
+        if decoder.is_none() && audio_decoder.is_none() {
+            return Err(PlayerError::NoStream.into());
+        }
+
+        let subtitle_tracks = subtitle_tracks(&ictx);
+        let subtitle_track = if options.forced_subs && decoder.is_some() {
+            let audio_language = audio_stream_index
+                .and_then(|index| ictx.stream(index))
+                .and_then(|stream| stream.metadata().get("language").map(str::to_string));
+            let track = forced_subtitle_track(&subtitle_tracks, audio_language.as_deref());
+            if track.is_none() {
+                println!("--forced-subs: aucune piste de sous-titres forcés dans ce fichier");
+            }
+            track
+        } else {
+            None
+        };
+
+        let used = [
+            decoder.as_ref().map(|decoder| decoder.stream_index),
+            pip_decoder.as_ref().map(|decoder| decoder.stream_index),
+            audio_stream_index,
+            subtitle_track.map(|position| subtitle_tracks[position].index),
+        ];
+        discard_unused_streams(&mut ictx, &used);
+
+        Ok(Self {
+            ictx,
+            decoder,
+            pip_decoder,
+            audio_decoder,
+            audio_stream_index,
+            subtitle_tracks,
+            subtitle_track,
+            reader_io,
+            rgba_scaler: None,
+            pending_frame: None,
+            output_size: options.output_size,
+            keep_aspect: options.keep_aspect,
+            buffer_stats: Arc::new(BufferStats::new()),
+        })
+    }
+
+    pub fn has_video(&self) -> bool {
+        self.decoder.is_some()
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.audio_decoder.is_some()
+    }
+
+    // Taille des images écrites par render_to: taille d'affichage d'après le SAR, ou celle de
+    // --output-size si elle est donnée. None sans flux vidéo. Après un buffer trop petit (la taille du
+    // flux peut changer en cours de lecture), c'est celle de la frame gardée pour l'appel suivant
+    pub fn video_size(&self) -> Option<(u32, u32)> {
+        let size = match self.pending_frame {
+            Some(ref frame) => display_size(frame),
+            None => self.decoder.as_ref().map(|decoder| {
+                let decoder = &decoder.decoder;
+                sar_display_size(decoder.width(), decoder.height(), decoder.aspect_ratio())
+            })?,
+        };
+        Some(self.rgba_layout(size).size)
+    }
+
+    // Diagnostics des tampons. Le décodage de render_to y tient la file du décodeur vidéo; l'hôte
+    // garde le handle pour y écrire depuis son callback audio (record_audio_fill, record_underrun)
+    // et lire snapshot depuis son affichage
+    pub fn buffer_stats(&self) -> Arc<BufferStats> {
+        self.buffer_stats.clone()
+    }
+
+    // Placement des images de render_to: --output-size et --keep-aspect s'y appliquent comme aux miniatures
+    fn rgba_layout(&self, size: (u32, u32)) -> PosterLayout {
+        PosterLayout::new(size, self.output_size, self.keep_aspect)
+    }
+
+    // Rendu hors fenêtre pour une application hôte (OpenGL, wgpu...): décode la frame vidéo suivante
+    // et l'écrit en RGBA dans buf, une ligne tous les stride octets. Les paquets audio sont ignorés.
+    // Renvoie false à la fin du flux. Si buf est trop petit, la frame n'est pas perdue: elle est
+    // rendue à l'appel suivant, dans un buffer agrandi d'après video_size
+    pub fn render_to(&mut self, buf: &mut [u8], stride: usize) -> Result<bool, PlayerError> {
+        self.render_rgba(buf, stride).map_err(PlayerError::classify)
+    }
+
+    fn render_rgba(&mut self, buf: &mut [u8], stride: usize) -> Result<bool> {
+        let frame = match self.pending_frame.take() {
+            Some(frame) => frame,
+            None => match self.next_video_frame()? {
+                Some(frame) => frame,
+                None => return Ok(false),
+            },
+        };
+
+        let (width, height) = (frame.width(), frame.height());
+        // Pixels non carrés étirés à leurs proportions d'affichage, comme les miniatures
+        let layout = self.rgba_layout(display_size(&frame));
+        if let Err(e) = check_rgba_buffer(layout.size.0, layout.size.1, buf.len(), stride) {
+            self.pending_frame = Some(frame);
+            return Err(e);
+        }
+
+        // Le scaler est recréé si le format ou la taille des frames change
+        let rebuild = !self.rgba_scaler.as_ref().is_some_and(|scaler| {
+            let (input, output) = (scaler.input(), scaler.output());
+            input.format == frame.format()
+                && (input.width, input.height) == (width, height)
+                && (output.width, output.height) == layout.scaled
+        });
+        if rebuild {
+            self.rgba_scaler = Some(ScalingContext::get(
+                frame.format(),
+                width,
+                height,
+                Pixel::RGBA,
+                layout.scaled.0,
+                layout.scaled.1,
+                Flags::BILINEAR,
+            )?);
+        }
+
+        let mut rgba = Video::empty();
+        if let Some(ref mut scaler) = self.rgba_scaler {
+            scaler.run(&frame, &mut rgba)?;
+        }
+
+        place_rgba(&layout, rgba.data(0), rgba.stride(0), buf, stride);
+        Ok(true)
+    }
+
+    // Miniature représentative, à POSTER_FRACTION de la durée
+    pub fn poster_frame(&mut self) -> Result<DecodedFrame, PlayerError> {
+        self.poster_frame_at(POSTER_FRACTION)
+    }
+
+    // Miniature à la fraction donnée de la durée, sans affichage. Si la durée est inconnue, on prend
+    // la première frame qui n'est pas noire parmi les premières, pour éviter un fondu d'ouverture.
+    // La lecture reprend ensuite depuis cette position.
+    pub fn poster_frame_at(&mut self, fraction: f64) -> Result<DecodedFrame, PlayerError> {
+        self.find_poster_frame(fraction).map_err(PlayerError::classify)
+    }
+
+    fn find_poster_frame(&mut self, fraction: f64) -> Result<DecodedFrame> {
+        let time_base = self.decoder.as_ref().ok_or(PlayerError::NoVideoStream)?.time_base;
+        let duration = self.ictx.duration();
+
+        if duration > 0 {
+            let timestamp = (duration as f64 * fraction.clamp(0.0, 1.0)) as i64;
+            let target = timestamp as f64 * f64::from(ffmpeg::rescale::TIME_BASE);
+            if let Err(e) = self.ictx.seek(timestamp, ..timestamp) {
+                let error = anyhow::Error::new(e).context(format!("Impossible de se positionner à {:.2}s", target));
+                return Err(PlayerError::Seek(error).into());
+            }
+            if let Some(ref mut decoder) = self.decoder {
+                decoder.flush();
+                decoder.reset_clock();
+            }
+
+            // Le seek tombe sur l'image clé précédente: on décode jusqu'à la position visée
+            let mut poster = None;
+            while let Some(frame) = self.next_video_frame()? {
+                let reached = frame.timestamp().unwrap_or(0) as f64 * time_base >= target;
+                poster = Some(frame);
+                if reached {
+                    break;
+                }
+            }
+            let frame = poster.context("Aucune frame décodée pour la miniature")?;
+            return self.to_rgb(&frame);
+        }
+
+        let frames = std::iter::from_fn(|| match self.next_video_frame() {
+            Ok(Some(frame)) => Some(self.to_rgb(&frame)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        });
+        choose_poster(frames)?.context("Aucune frame décodée pour la miniature")
+    }
+
+    // Frame vidéo suivante, en mémoire système. Les paquets des autres flux sont ignorés.
+    fn next_video_frame(&mut self) -> Result<Option<Video>> {
+        let decoder = self.decoder.as_mut().ok_or(PlayerError::NoVideoStream)?;
+
+        let mut frame = Video::empty();
+        loop {
+            match decoder.decoder.receive_frame(&mut frame) {
+                Ok(()) => {
+                    decoder.queued_packets = decoder.queued_packets.saturating_sub(1);
+                    self.buffer_stats.record_video_queue(decoder.queued_packets);
+                    break;
+                }
+                Err(ffmpeg::Error::Other { errno: ffmpeg::error::EAGAIN }) => {}
+                Err(ffmpeg::Error::Eof) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+            match read_packet(&mut self.ictx)? {
+                Some(packet) => {
+                    if packet.stream() == decoder.stream_index {
+                        decoder.send_packet(&packet)?;
+                    }
+                }
+                None => decoder.decoder.send_eof()?,
+            }
+        }
+        hwaccel::download_frame(&mut frame)?;
+        Ok(Some(frame))
+    }
+
+    // Conversion en RGB, à la taille de --output-size si elle est donnée. Avec keep_aspect, l'image
+    // est mise à l'échelle dans le cadre sans déformation et centrée sur un fond noir. Les pixels
+    // non carrés (DVD, HDV anamorphiques) sont étirés à leurs proportions d'affichage
+    fn to_rgb(&self, frame: &Video) -> Result<DecodedFrame> {
+        let layout = PosterLayout::new(display_size(frame), self.output_size, self.keep_aspect);
+        let mut scaler = ScalingContext::get(
+            frame.format(),
+            frame.width(),
+            frame.height(),
+            Pixel::RGB24,
+            layout.scaled.0,
+            layout.scaled.1,
+            Flags::BILINEAR,
+        )?;
+        let mut rgb = Video::empty();
+        scaler.run(frame, &mut rgb)?;
+
+        let row_size = layout.size.0 as usize * 3;
+        let scaled_row_size = layout.scaled.0 as usize * 3;
+        let left = layout.offset.0 as usize * 3;
+        let top = layout.offset.1 as usize;
+        let stride = rgb.stride(0);
+        let mut data = vec![0; row_size * layout.size.1 as usize];
+        for (row, source) in rgb.data(0).chunks(stride).take(layout.scaled.1 as usize).enumerate() {
+            let start = (top + row) * row_size + left;
+            data[start..start + scaled_row_size].copy_from_slice(&source[..scaled_row_size]);
+        }
+        Ok(DecodedFrame { width: layout.size.0, height: layout.size.1, data })
+    }
+
+    // Faux pour un direct ou un pipe: l'interface doit alors désactiver la recherche
+    pub fn seekable(&self) -> bool {
+        unsafe {
+            let context = self.ictx.as_ptr();
+            let pb = (*context).pb;
+            let io_seekable = (!pb.is_null()).then(|| (*pb).seekable);
+            is_seekable((*context).ctx_flags, io_seekable, self.ictx.duration())
+        }
+    }
+
+    // Chapitres triés par début, vide si le fichier n'en définit pas
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let mut chapters: Vec<Chapter> = self
+            .ictx
+            .chapters()
+            .map(|chapter| {
+                let time_base = f64::from(chapter.time_base());
+                Chapter {
+                    start: chapter.start() as f64 * time_base,
+                    end: chapter.end() as f64 * time_base,
+                    title: chapter.metadata().get("title").map(str::to_string),
+                }
+            })
+            .collect();
+        chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+        chapters
+    }
+
+    // Pistes audio et de sous-titres disponibles
+    pub fn tracks(&self) -> Vec<TrackInfo> {
+        self.ictx
+            .streams()
+            .filter_map(|stream| {
+                let parameters = stream.parameters();
+                let medium = parameters.medium();
+                if medium != ffmpeg::media::Type::Audio && medium != ffmpeg::media::Type::Subtitle {
+                    return None;
+                }
+                Some(TrackInfo {
+                    index: stream.index(),
+                    medium,
+                    codec: parameters.id(),
+                    language: stream.metadata().get("language").map(str::to_string),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEKABLE: c_int = ffi::AVIO_SEEKABLE_NORMAL as c_int;
+
+    // Fichier WAV mono 16 bits de `frames` échantillons, entièrement en mémoire
+    fn wav_bytes(frames: u32) -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut bytes = Vec::new();
+        let mut writer = hound::WavWriter::new(std::io::Cursor::new(&mut bytes), spec).unwrap();
+        for index in 0..frames {
+            writer.write_sample(((index % 100) as i16 - 50) * 300).unwrap();
+        }
+        writer.finalize().unwrap();
+        bytes
+    }
+
+    // Nécessite les bibliothèques ffmpeg: le démuxeur lit le WAV par les callbacks de ReaderIo
+    #[test]
+    fn open_reader_decodes_wav_from_memory() {
+        ffmpeg::init().unwrap();
+        let mut player = Player::open_reader(std::io::Cursor::new(wav_bytes(24000)), &Options::default()).unwrap();
+        assert!(player.has_audio());
+        assert!(!player.has_video());
+        assert!(player.seekable());
+
+        let index = player.audio_stream_index.unwrap();
+        let mut decoder = player.audio_decoder.take().unwrap();
+        let mut frame = ffmpeg::frame::Audio::empty();
+        let mut samples = 0;
+        loop {
+            match read_packet(&mut player.ictx).unwrap() {
+                Some(packet) if packet.stream() == index => decoder.send_packet(&packet).unwrap(),
+                Some(_) => continue,
+                None => decoder.send_eof().unwrap(),
+            }
+            while decoder.receive_frame(&mut frame).is_ok() {
+                samples += frame.samples();
+            }
+            if samples >= 24000 {
+                break;
+            }
+        }
+        assert_eq!(samples, 24000);
+    }
+
+    // Fondu d'ouverture de 4 s à 10 i/s en 24x16: frames 0 à 3 noires (Y=16), puis Y=16+5*i
+    const FADE_IN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/fade_in.y4m");
+
+    fn open_fixture(path: &str) -> Player {
+        ffmpeg::init().unwrap();
+        let options = Options { video_path: path.to_string(), ..Options::default() };
+        Player::open(&options).unwrap()
+    }
+
+    // Nécessite les bibliothèques ffmpeg: seek à 10 % de la durée, sur la frame 4, la première éclairée
+    #[test]
+    fn poster_frame_seeks_past_the_fade_in() {
+        let mut player = open_fixture(FADE_IN);
+        let poster = player.poster_frame().unwrap();
+        assert_eq!((poster.width, poster.height), (24, 16));
+        assert!(poster.average_luma() >= POSTER_BLACK_LUMA, "{}", poster.average_luma());
+    }
+
+    // Nécessite les bibliothèques ffmpeg: render_to tient la file du décodeur vidéo, l'hôte écrit le
+    // reste par le même handle
+    #[test]
+    fn buffer_stats_are_shared_with_the_host() {
+        let mut player = open_fixture(FADE_IN);
+        let host = player.buffer_stats();
+        let mut buffer = vec![0; 24 * 4 * 16];
+        assert!(player.render_to(&mut buffer, 24 * 4).unwrap());
+        host.record_underrun();
+        let snapshot = player.buffer_stats().snapshot();
+        // Vidéo brute: chaque paquet sort aussitôt en frame
+        assert_eq!(snapshot.video_queue, 0);
+        assert_eq!(snapshot.underruns, 1);
+    }
+
+    // Nécessite les bibliothèques ffmpeg: 90x16 avec des pixels 64:45, affiché en 128x16
+    #[test]
+    fn render_to_stretches_anamorphic_frames() {
+        let mut player = open_fixture(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/anamorphic.y4m"));
+        assert_eq!(player.video_size(), Some((128, 16)));
+        let mut buffer = vec![0; 128 * 4 * 16];
+        assert!(player.render_to(&mut buffer, 128 * 4).unwrap());
+        // Gris uni sur toute la largeur d'affichage, sans bandes
+        assert!(buffer.chunks_exact(4).all(|pixel| pixel[3] == 255 && pixel[0].abs_diff(130) <= 4));
+    }
+
+    // Nécessite les bibliothèques ffmpeg: la luminance dit quelle frame le seek a atteinte
+    #[test]
+    fn poster_frame_at_reaches_the_requested_fraction() {
+        let mut player = open_fixture(FADE_IN);
+        // Frame 20, Y=116: gris à (116 - 16) * 255 / 219
+        let middle = player.poster_frame_at(0.5).unwrap();
+        assert!((middle.average_luma() - 116.4).abs() < 4.0, "{}", middle.average_luma());
+        // Retour en arrière sur le début noir
+        let start = player.poster_frame_at(0.0).unwrap();
+        assert!(start.average_luma() < POSTER_BLACK_LUMA, "{}", start.average_luma());
+    }
+
+    // Lecteur qui échoue comme un disque ou un réseau coupé une fois `limit` octets lus
+    struct FailingReader {
+        inner: std::io::Cursor<Vec<u8>>,
+        limit: u64,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let left = self.limit.saturating_sub(self.inner.position());
+            if left == 0 {
+                return Err(std::io::Error::other("lecture coupée"));
+            }
+            let len = buf.len().min(left as usize);
+            self.inner.read(&mut buf[..len])
+        }
+    }
+
+    impl Seek for FailingReader {
+        fn seek(&mut self, position: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(position)
+        }
+    }
+
+    // Nécessite les bibliothèques ffmpeg: une erreur de lecture au milieu du fichier remonte
+    // jusqu'à read_packet au lieu de passer pour la fin du fichier
+    #[test]
+    fn read_error_mid_file_is_not_end_of_file() {
+        ffmpeg::init().unwrap();
+        let reader = FailingReader { inner: std::io::Cursor::new(wav_bytes(48000)), limit: 32 * 1024 };
+        let mut player = Player::open_reader(reader, &Options::default()).unwrap();
+        let error = loop {
+            match read_packet(&mut player.ictx) {
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("la lecture coupée a été prise pour la fin du fichier"),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(error, ffmpeg::Error::Other { errno: ffmpeg::error::EIO }), "{:?}", error);
+    }
+
+    #[test]
+    fn regular_file_is_seekable() {
+        assert!(is_seekable(0, Some(SEEKABLE), 0));
+    }
+
+    #[test]
+    fn pipe_or_unseekable_input_is_not_seekable() {
+        // Pipe, stdin: AVIO sans recherche, même avec une durée annoncée
+        assert!(!is_seekable(0, Some(0), 60_000_000));
+        // Démuxeur qui se déclare non positionnable (direct)
+        assert!(!is_seekable(ffi::AVFMTCTX_UNSEEKABLE as c_int, Some(SEEKABLE), 60_000_000));
+    }
+
+    #[test]
+    fn input_without_io_needs_a_duration() {
+        assert!(is_seekable(0, None, 60_000_000));
+        assert!(!is_seekable(0, None, 0));
+    }
+
+    #[test]
+    fn read_error_is_not_end_of_file() {
+        assert!(matches!(packet_read(Ok(())), Ok(true)));
+        assert!(matches!(packet_read(Err(ffmpeg::Error::Eof)), Ok(false)));
+        let io_error = ffmpeg::Error::Other { errno: ffmpeg::error::EIO };
+        assert!(matches!(packet_read(Err(io_error)), Err(ffmpeg::Error::Other { errno: ffmpeg::error::EIO })));
+        // Lecture interrompue par le watchdog: on réessaie, ce n'est pas la fin du fichier
+        assert!(matches!(packet_read(Err(ffmpeg::Error::Exit)), Err(ffmpeg::Error::Exit)));
+    }
+
+    #[test]
+    fn rgba_buffer_must_hold_the_frame() {
+        assert!(check_rgba_buffer(4, 2, 32, 16).is_ok());
+        // Pas plus large que la ligne: la dernière ligne peut s'arrêter à ses pixels
+        assert!(check_rgba_buffer(4, 2, 36, 20).is_ok());
+        assert!(check_rgba_buffer(4, 2, 35, 20).is_err());
+        assert!(check_rgba_buffer(4, 2, 31, 16).is_err());
+        // Pas plus court qu'une ligne, même avec assez d'octets au total
+        assert!(check_rgba_buffer(4, 2, 1000, 12).is_err());
+    }
+
+    fn plain_frame(rgb: [u8; 3]) -> DecodedFrame {
+        DecodedFrame { width: 4, height: 2, data: rgb.repeat(8) }
+    }
+
+    #[test]
+    fn average_luma_weights_channels() {
+        assert_eq!(plain_frame([0, 0, 0]).average_luma(), 0.0);
+        assert!((plain_frame([255, 255, 255]).average_luma() - 255.0).abs() < 1e-9);
+        assert!((plain_frame([255, 0, 0]).average_luma() - 0.299 * 255.0).abs() < 1e-9);
+        assert_eq!(DecodedFrame { width: 0, height: 0, data: Vec::new() }.average_luma(), 0.0);
+    }
+
+    #[test]
+    fn poster_skips_black_opening() {
+        // Fondu au noir: deux frames noires, une très sombre, puis l'image
+        let frames = [[0, 0, 0], [2, 2, 2], [12, 12, 12], [90, 120, 60], [255, 255, 255]];
+        let poster = choose_poster(frames.into_iter().map(|rgb| Ok(plain_frame(rgb)))).unwrap().unwrap();
+        assert_eq!(&poster.data[..3], &[90, 120, 60]);
+        assert!(poster.average_luma() >= POSTER_BLACK_LUMA);
+    }
+
+    #[test]
+    fn poster_of_black_clip_is_last_examined_frame() {
+        // Au-delà de POSTER_MAX_FRAMES, les frames suivantes ne sont plus décodées
+        let frames = (0..POSTER_MAX_FRAMES + 10).map(|index| {
+            let value = if index < POSTER_MAX_FRAMES { (index % 10) as u8 } else { 200 };
+            Ok(plain_frame([value; 3]))
+        });
+        let poster = choose_poster(frames).unwrap().unwrap();
+        assert_eq!(poster.data[0], ((POSTER_MAX_FRAMES - 1) % 10) as u8);
+        assert!(choose_poster(std::iter::empty()).unwrap().is_none());
+    }
+
+    #[test]
+    fn poster_decode_error_is_reported() {
+        let frames = [Ok(plain_frame([0; 3])), Err(anyhow::anyhow!("frame corrompue"))];
+        assert!(choose_poster(frames.into_iter()).is_err());
+    }
+
+    #[test]
+    fn ppm_header_and_pixels() {
+        let mut output = Vec::new();
+        plain_frame([1, 2, 3]).write_ppm(&mut output).unwrap();
+        assert!(output.starts_with(b"P6\n4 2\n255\n"));
+        assert_eq!(output.len(), 11 + 4 * 2 * 3);
+        assert_eq!(&output[11..14], &[1, 2, 3]);
+    }
+
+    // Ouvreur factice: note les API essayées, et échoue pour celles de failing
+    fn try_open(accel: HwAccel, failing: &[HwAccel]) -> (Result<HwAccel>, Vec<HwAccel>) {
+        let mut attempts = Vec::new();
+        let opened = open_with_fallback(accel, |accel| {
+            attempts.push(accel);
+            if failing.contains(&accel) {
+                anyhow::bail!("{} indisponible", accel.name());
+            }
+            Ok(accel)
+        });
+        (opened, attempts)
+    }
+
+    #[test]
+    fn hardware_decoder_is_used_when_it_opens() {
+        let (opened, attempts) = try_open(HwAccel::Vaapi, &[]);
+        assert_eq!(opened.unwrap(), HwAccel::Vaapi);
+        assert_eq!(attempts, [HwAccel::Vaapi]);
+    }
+
+    #[test]
+    fn hardware_failure_falls_back_to_software() {
+        let (opened, attempts) = try_open(HwAccel::Cuda, &[HwAccel::Cuda]);
+        assert_eq!(opened.unwrap(), HwAccel::None);
+        assert_eq!(attempts, [HwAccel::Cuda, HwAccel::None]);
+    }
+
+    #[test]
+    fn software_decoding_is_tried_once() {
+        let (opened, attempts) = try_open(HwAccel::None, &[HwAccel::None]);
+        assert!(opened.is_err());
+        assert_eq!(attempts, [HwAccel::None]);
+        // Matériel et logiciel en échec: l'erreur du logiciel remonte
+        let (opened, attempts) = try_open(HwAccel::Qsv, &[HwAccel::Qsv, HwAccel::None]);
+        assert!(opened.unwrap_err().to_string().contains(HwAccel::None.name()));
+        assert_eq!(attempts, [HwAccel::Qsv, HwAccel::None]);
+    }
+
+    #[test]
+    fn poster_keeps_display_size_without_output_size() {
+        let layout = PosterLayout::new((1024, 576), None, false);
+        assert_eq!(layout, PosterLayout { size: (1024, 576), scaled: (1024, 576), offset: (0, 0) });
+    }
+
+    #[test]
+    fn output_size_stretches_without_keep_aspect() {
+        let layout = PosterLayout::new((1920, 1080), Some((320, 320)), false);
+        assert_eq!(layout, PosterLayout { size: (320, 320), scaled: (320, 320), offset: (0, 0) });
+    }
+
+    #[test]
+    fn keep_aspect_letterboxes_and_pillarboxes() {
+        // 16:9 dans un carré: bandes en haut et en bas
+        let layout = PosterLayout::new((1920, 1080), Some((320, 320)), true);
+        assert_eq!(layout, PosterLayout { size: (320, 320), scaled: (320, 180), offset: (0, 70) });
+        // 4:3 agrandi dans du 16:9: bandes sur les côtés
+        let layout = PosterLayout::new((640, 480), Some((1280, 720)), true);
+        assert_eq!(layout, PosterLayout { size: (1280, 720), scaled: (960, 720), offset: (160, 0) });
+        // Anamorphique PAL 720x576 affiché en 1024x576: déjà aux proportions du cadre
+        let layout = PosterLayout::new((1024, 576), Some((320, 180)), true);
+        assert_eq!(layout, PosterLayout { size: (320, 180), scaled: (320, 180), offset: (0, 0) });
+    }
+
+    #[test]
+    fn rgba_is_placed_between_opaque_black_bars() {
+        // Image 2x1 rouge et verte, centrée dans un cadre 2x3 dont les lignes font 12 octets
+        let layout = PosterLayout { size: (2, 3), scaled: (2, 1), offset: (0, 1) };
+        let source = [255, 0, 0, 255, 0, 255, 0, 255, 9, 9];
+        let mut buf = [7; 36];
+        place_rgba(&layout, &source, 10, &mut buf, 12);
+        let black = [0, 0, 0, 255, 0, 0, 0, 255];
+        assert_eq!(buf[..8], black);
+        assert_eq!(buf[12..20], source[..8]);
+        assert_eq!(buf[24..32], black);
+        // Le remplissage au-delà de la largeur du cadre n'est pas touché
+        assert_eq!(buf[8..12], [7; 4]);
+    }
+
+    #[test]
+    fn fit_within_keeps_at_least_two_pixels() {
+        assert_eq!(fit_within(10000, 10, 100, 100), (100, 2));
+        assert_eq!(fit_within(0, 0, 100, 50), (2, 2));
+    }
+
+    fn chapters() -> Vec<Chapter> {
+        [(10.0, 60.0), (60.0, 120.0), (120.0, 180.0)]
+            .into_iter()
+            .map(|(start, end)| Chapter { start, end, title: None })
+            .collect()
+    }
+
+    #[test]
+    fn chapter_at_boundaries() {
+        let chapters = chapters();
+        // Avant le premier chapitre (générique sans chapitre)
+        assert_eq!(chapter_at(&chapters, 5.0), None);
+        assert_eq!(chapter_at(&chapters, 10.0), Some(0));
+        // Pile sur une limite: le chapitre qui commence
+        assert_eq!(chapter_at(&chapters, 60.0), Some(1));
+        assert_eq!(chapter_at(&chapters, 119.99), Some(1));
+        // Après la fin du dernier: on y reste
+        assert_eq!(chapter_at(&chapters, 500.0), Some(2));
+        assert_eq!(chapter_at(&[], 30.0), None);
+    }
+
+    #[test]
+    fn chapter_start_forward() {
+        let chapters = chapters();
+        assert_eq!(chapter_start(&chapters, 0.0, true), Some(10.0));
+        // Sur une limite, le suivant est bien le chapitre d'après
+        assert_eq!(chapter_start(&chapters, 60.0, true), Some(120.0));
+        assert_eq!(chapter_start(&chapters, 130.0, true), None);
+        assert_eq!(chapter_start(&[], 30.0, true), None);
+    }
+
+    #[test]
+    fn chapter_start_backward() {
+        let chapters = chapters();
+        // Au milieu d'un chapitre: retour à son début
+        assert_eq!(chapter_start(&chapters, 90.0, false), Some(60.0));
+        // Dans sa première seconde: chapitre précédent
+        assert_eq!(chapter_start(&chapters, 60.5, false), Some(10.0));
+        assert_eq!(chapter_start(&chapters, 60.0, false), Some(10.0));
+        assert_eq!(chapter_start(&chapters, 500.0, false), Some(120.0));
+        assert_eq!(chapter_start(&chapters, 10.5, false), None);
+        assert_eq!(chapter_start(&chapters, 5.0, false), None);
+        assert_eq!(chapter_start(&[], 30.0, false), None);
+    }
+}
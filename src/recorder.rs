@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::JoinHandle;
+
+// Blocs du callback en attente d'écriture: au-delà, ils sont perdus plutôt que de bloquer le son
+const QUEUE_BLOCKS: usize = 256;
+
+// Enregistrement WAV de ce que joue le périphérique audio (--record-output), après volume et canaux
+// coupés. L'écriture se fait dans un thread dédié pour ne jamais retarder le temps réel. Le callback
+// ne fait que copier ses échantillons dans un des QUEUE_BLOCKS buffers alloués à la création, que le
+// thread d'écriture lui rend une fois vidés: aucune allocation ni libération côté temps réel
+pub struct OutputRecorder {
+    path: String,
+    sender: Option<SyncSender<Vec<f32>>>,
+    // Buffers vides rendus par le thread d'écriture, chacun d'une capacité de block_len échantillons
+    free: Receiver<Vec<f32>>,
+    block_len: usize,
+    worker: Option<JoinHandle<hound::Result<()>>>,
+    dropped_blocks: u64,
+}
+
+impl OutputRecorder {
+    // block_len: échantillons (tous canaux) reçus par appel du callback, un bloc plus long est découpé
+    pub fn create(path: &str, channels: u16, sample_rate: u32, block_len: usize) -> Result<Self> {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec)
+            .with_context(|| format!("Impossible de créer {}", path))?;
+        let block_len = block_len.max(1);
+        let (sender, receiver) = mpsc::sync_channel::<Vec<f32>>(QUEUE_BLOCKS);
+        let (recycler, free) = mpsc::sync_channel::<Vec<f32>>(QUEUE_BLOCKS);
+        for _ in 0..QUEUE_BLOCKS {
+            recycler.send(Vec::with_capacity(block_len))?;
+        }
+
+        // Les deux canaux tiennent tous les buffers: ni l'écriture ni le recyclage ne bloquent
+        let worker = std::thread::spawn(move || {
+            for mut block in receiver.iter() {
+                for &sample in &block {
+                    writer.write_sample(sample)?;
+                }
+                block.clear();
+                let _ = recycler.send(block);
+            }
+            writer.finalize()
+        });
+
+        Ok(Self {
+            path: path.to_string(),
+            sender: Some(sender),
+            free,
+            block_len,
+            worker: Some(worker),
+            dropped_blocks: 0,
+        })
+    }
+
+    // Appelé depuis le callback audio: ne bloque jamais et n'alloue rien. Sans buffer libre
+    // (écriture trop lente), le bloc est perdu
+    pub fn record(&mut self, samples: &[f32]) {
+        let Some(ref sender) = self.sender else {
+            return;
+        };
+        for chunk in samples.chunks(self.block_len) {
+            let Ok(mut block) = self.free.try_recv() else {
+                self.dropped_blocks += 1;
+                continue;
+            };
+            block.extend_from_slice(chunk);
+            let _ = sender.try_send(block);
+        }
+    }
+}
+
+impl Drop for OutputRecorder {
+    fn drop(&mut self) {
+        // Fermer le canal termine la boucle du thread, qui finalise alors l'en-tête WAV
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            match worker.join() {
+                Ok(Err(e)) => eprintln!("Erreur d'écriture de {}: {}", self.path, e),
+                Err(_) => eprintln!("Le thread d'enregistrement de la sortie audio a paniqué"),
+                Ok(Ok(())) => println!("Sortie audio enregistrée dans {}", self.path),
+            }
+        }
+        if self.dropped_blocks > 0 {
+            eprintln!(
+                "Attention: {} blocs audio perdus dans l'enregistrement (écriture trop lente)",
+                self.dropped_blocks
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_and_read(name: &str, block_len: usize, blocks: &[&[f32]]) -> (Vec<f32>, u64) {
+        let path = std::env::temp_dir().join(format!("rust-media-player-{}-{}.wav", name, std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut recorder = OutputRecorder::create(path, 2, 48000, block_len).unwrap();
+        for block in blocks {
+            recorder.record(block);
+        }
+        let dropped = recorder.dropped_blocks;
+        drop(recorder);
+        let samples = hound::WavReader::open(path).unwrap().samples::<f32>().map(Result::unwrap).collect();
+        std::fs::remove_file(path).unwrap();
+        (samples, dropped)
+    }
+
+    #[test]
+    fn blocks_are_written_in_order() {
+        let (samples, dropped) = record_and_read("order", 4, &[&[0.1, -0.1, 0.2, -0.2], &[0.3, -0.3]]);
+        assert_eq!(samples, [0.1, -0.1, 0.2, -0.2, 0.3, -0.3]);
+        assert_eq!(dropped, 0);
+    }
+
+    #[test]
+    fn block_longer_than_expected_is_split_without_growing_buffers() {
+        let long: Vec<f32> = (0..10).map(|index| index as f32 / 10.0).collect();
+        let (samples, dropped) = record_and_read("split", 4, &[&long]);
+        assert_eq!(samples, long);
+        assert_eq!(dropped, 0);
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use std::fs;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+const STATE_FILE: &str = "positions.tsv";
+// Au-delà, le fichier ne garde que les positions les plus récentes
+const MAX_ENTRIES: usize = 500;
+// Position trop proche du début pour valoir une reprise, en secondes
+const MIN_RESUME_POSITION: f64 = 5.0;
+// Arrêt à moins de END_MARGIN secondes ou END_RATIO de la durée: le fichier est considéré terminé
+const END_MARGIN: f64 = 10.0;
+const END_RATIO: f64 = 0.95;
+
+// Identité d'un fichier local: chemin absolu, taille et date de modification. Un fichier déplacé,
+// renommé ou remplacé au même chemin ne retrouve donc pas la position d'un autre
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileKey {
+    path: String,
+    size: u64,
+    modified: u64,
+}
+
+impl FileKey {
+    // None pour une URL ou un fichier illisible: rien n'est mémorisé
+    pub fn of(path: &str) -> Option<Self> {
+        let path = fs::canonicalize(path).ok()?;
+        let metadata = fs::metadata(&path).ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some(Self {
+            // Le chemin est en dernier sur la ligne, seule une tabulation ou un saut de ligne le casserait
+            path: path.to_str().filter(|path| !path.contains(['\t', '\n']))?.to_string(),
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+// Positions de lecture mémorisées, une ligne par fichier: position, taille, date, chemin.
+// Les plus récentes sont en fin de fichier
+pub struct ResumeStore {
+    path: PathBuf,
+    entries: Vec<(FileKey, f64)>,
+}
+
+impl ResumeStore {
+    pub fn open() -> Result<Self> {
+        let dirs = ProjectDirs::from("", "", "rust-media-player")
+            .context("Impossible de déterminer le dossier de configuration")?;
+        let path = dirs.config_dir().join(STATE_FILE);
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => contents.lines().filter_map(parse_entry).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("Impossible de lire {}", path.display())),
+        };
+        Ok(Self { path, entries })
+    }
+
+    // Position où reprendre, si elle n'est ni au tout début ni à la fin
+    pub fn position(&self, key: &FileKey, duration: Option<f64>) -> Option<f64> {
+        let (_, position) = self.entries.iter().find(|(entry, _)| entry == key)?;
+        (*position >= MIN_RESUME_POSITION && !near_end(*position, duration)).then_some(*position)
+    }
+
+    // Mémorise l'arrêt; un fichier vu jusqu'au bout, ou presque, reprendra au début
+    pub fn save(&mut self, key: FileKey, position: f64, duration: Option<f64>) -> Result<()> {
+        self.entries.retain(|(entry, _)| *entry != key);
+        if position >= MIN_RESUME_POSITION && !near_end(position, duration) {
+            self.entries.push((key, position));
+        }
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+
+        let contents: String = self
+            .entries
+            .iter()
+            .map(|(key, position)| format!("{:.3}\t{}\t{}\t{}\n", position, key.size, key.modified, key.path))
+            .collect();
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Impossible de créer {}", parent.display()))?;
+        }
+        // Écriture dans un fichier temporaire puis renommage: un arrêt brutal ne perd pas les autres positions
+        let temporary = self.path.with_extension("tmp");
+        fs::write(&temporary, contents).with_context(|| format!("Impossible d'écrire {}", temporary.display()))?;
+        fs::rename(&temporary, &self.path).with_context(|| format!("Impossible d'écrire {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+fn near_end(position: f64, duration: Option<f64>) -> bool {
+    duration.is_some_and(|duration| position >= duration - END_MARGIN || position >= duration * END_RATIO)
+}
+
+fn parse_entry(line: &str) -> Option<(FileKey, f64)> {
+    let mut fields = line.splitn(4, '\t');
+    let position = fields.next()?.parse().ok()?;
+    let size = fields.next()?.parse().ok()?;
+    let modified = fields.next()?.parse().ok()?;
+    let path = fields.next()?.to_string();
+    Some((FileKey { path, size, modified }, position))
+}
@@ -0,0 +1,231 @@
+// Entrelacement et gain des échantillons f32, en SSE2 ou AVX quand le processeur le permet.
+// Sans dépendance au reste du lecteur, pour être repris tel quel par le benchmark (benches/)
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+// Jeu d'instructions utilisé pour les conversions d'échantillons, choisi une fois au démarrage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimdLevel {
+    Scalar,
+    Sse2,
+    Avx,
+}
+
+impl SimdLevel {
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx") {
+                return SimdLevel::Avx;
+            }
+            if is_x86_feature_detected!("sse2") {
+                return SimdLevel::Sse2;
+            }
+        }
+        SimdLevel::Scalar
+    }
+
+    // Niveaux utilisables sur ce processeur, du plus simple au plus rapide. Sert aux tests et au
+    // benchmark, qui comparent chacun d'eux au code scalaire
+    #[allow(dead_code)]
+    pub fn available() -> Vec<SimdLevel> {
+        let detected = Self::detect();
+        [SimdLevel::Scalar, SimdLevel::Sse2, SimdLevel::Avx]
+            .into_iter()
+            .filter(|level| *level as u8 <= detected as u8)
+            .collect()
+    }
+}
+
+// Ajoute les canaux entrelacés à la fin de output. Seule la stéréo a un chemin SIMD
+pub fn interleave(level: SimdLevel, planes: &[&[f32]], output: &mut Vec<f32>) {
+    match (level, planes) {
+        #[cfg(target_arch = "x86_64")]
+        (SimdLevel::Avx, [left, right]) => unsafe { interleave_stereo_avx(left, right, output) },
+        #[cfg(target_arch = "x86_64")]
+        (SimdLevel::Sse2, [left, right]) => unsafe { interleave_stereo_sse2(left, right, output) },
+        _ => interleave_scalar(planes, output),
+    }
+}
+
+pub fn apply_gain(level: SimdLevel, samples: &mut [f32], gain: f32) {
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx => unsafe { gain_avx(samples, gain) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Sse2 => unsafe { gain_sse2(samples, gain) },
+        _ => gain_scalar(samples, gain),
+    }
+}
+
+fn interleave_scalar(planes: &[&[f32]], output: &mut Vec<f32>) {
+    let frames = planes.iter().map(|plane| plane.len()).min().unwrap_or(0);
+    output.reserve(frames * planes.len());
+    for i in 0..frames {
+        for plane in planes {
+            output.push(plane[i]);
+        }
+    }
+}
+
+fn gain_scalar(samples: &mut [f32], gain: f32) {
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn interleave_stereo_sse2(left: &[f32], right: &[f32], output: &mut Vec<f32>) {
+    let frames = left.len().min(right.len());
+    let start = output.len();
+    output.resize(start + frames * 2, 0.0);
+    let out = &mut output[start..];
+
+    let chunks = frames / 4;
+    for i in 0..chunks {
+        let l = _mm_loadu_ps(left.as_ptr().add(i * 4));
+        let r = _mm_loadu_ps(right.as_ptr().add(i * 4));
+        _mm_storeu_ps(out.as_mut_ptr().add(i * 8), _mm_unpacklo_ps(l, r));
+        _mm_storeu_ps(out.as_mut_ptr().add(i * 8 + 4), _mm_unpackhi_ps(l, r));
+    }
+    for i in chunks * 4..frames {
+        out[2 * i] = left[i];
+        out[2 * i + 1] = right[i];
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx")]
+unsafe fn interleave_stereo_avx(left: &[f32], right: &[f32], output: &mut Vec<f32>) {
+    let frames = left.len().min(right.len());
+    let start = output.len();
+    output.resize(start + frames * 2, 0.0);
+    let out = &mut output[start..];
+
+    let chunks = frames / 8;
+    for i in 0..chunks {
+        let l = _mm256_loadu_ps(left.as_ptr().add(i * 8));
+        let r = _mm256_loadu_ps(right.as_ptr().add(i * 8));
+        // unpack travaille par moitiés de 128 bits, permute2f128 remet les paires dans l'ordre
+        let lo = _mm256_unpacklo_ps(l, r);
+        let hi = _mm256_unpackhi_ps(l, r);
+        _mm256_storeu_ps(out.as_mut_ptr().add(i * 16), _mm256_permute2f128_ps(lo, hi, 0x20));
+        _mm256_storeu_ps(out.as_mut_ptr().add(i * 16 + 8), _mm256_permute2f128_ps(lo, hi, 0x31));
+    }
+    for i in chunks * 8..frames {
+        out[2 * i] = left[i];
+        out[2 * i + 1] = right[i];
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn gain_sse2(samples: &mut [f32], gain: f32) {
+    let factor = _mm_set1_ps(gain);
+    let mut chunks = samples.chunks_exact_mut(4);
+    for chunk in &mut chunks {
+        let value = _mm_loadu_ps(chunk.as_ptr());
+        _mm_storeu_ps(chunk.as_mut_ptr(), _mm_mul_ps(value, factor));
+    }
+    gain_scalar(chunks.into_remainder(), gain);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx")]
+unsafe fn gain_avx(samples: &mut [f32], gain: f32) {
+    let factor = _mm256_set1_ps(gain);
+    let mut chunks = samples.chunks_exact_mut(8);
+    for chunk in &mut chunks {
+        let value = _mm256_loadu_ps(chunk.as_ptr());
+        _mm256_storeu_ps(chunk.as_mut_ptr(), _mm256_mul_ps(value, factor));
+    }
+    gain_scalar(chunks.into_remainder(), gain);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Générateur xorshift: des valeurs variées et reproductibles, sans dépendance
+    struct Xorshift(u64);
+
+    impl Xorshift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, max: usize) -> usize {
+            (self.next() % max as u64) as usize
+        }
+
+        fn sample(&mut self) -> f32 {
+            (self.next() >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+        }
+
+        fn samples(&mut self, len: usize) -> Vec<f32> {
+            (0..len).map(|_| self.sample()).collect()
+        }
+    }
+
+    // Longueurs autour des largeurs de vecteur (4 et 8), puis au hasard: la fin non alignée
+    // passe toujours par le code scalaire
+    fn lengths(rng: &mut Xorshift) -> Vec<usize> {
+        let mut lengths: Vec<usize> = (0..=33).collect();
+        lengths.extend((0..50).map(|_| rng.below(5000)));
+        lengths
+    }
+
+    #[test]
+    fn simd_interleave_matches_scalar() {
+        let mut rng = Xorshift(0x9e37_79b9_7f4a_7c15);
+        for len in lengths(&mut rng) {
+            // Canaux de longueurs différentes: seule la plus courte compte
+            let extra = rng.below(3);
+            let left = rng.samples(len);
+            let right = rng.samples(len + extra);
+            let mut expected = vec![1.5];
+            interleave_scalar(&[&left, &right], &mut expected);
+            for level in SimdLevel::available() {
+                // interleave ajoute à la fin du buffer sans toucher à ce qui y est déjà
+                let mut output = vec![1.5];
+                interleave(level, &[&left, &right], &mut output);
+                assert_eq!(output, expected, "{:?}, {} frames", level, len);
+            }
+        }
+    }
+
+    #[test]
+    fn simd_gain_matches_scalar() {
+        let mut rng = Xorshift(0x2545_f491_4f6c_dd1d);
+        for len in lengths(&mut rng) {
+            let samples = rng.samples(len);
+            let gain = rng.sample() * 2.0;
+            let mut expected = samples.clone();
+            gain_scalar(&mut expected, gain);
+            for level in SimdLevel::available() {
+                // Départ décalé d'un échantillon: les chargements SIMD ne sont pas alignés
+                let mut output = samples.clone();
+                if !output.is_empty() {
+                    output[0] *= gain;
+                    apply_gain(level, &mut output[1..], gain);
+                }
+                assert_eq!(output, expected, "{:?}, {} échantillons", level, len);
+            }
+        }
+    }
+
+    #[test]
+    fn interleave_keeps_channel_order_beyond_stereo() {
+        let planes: [&[f32]; 3] = [&[1.0, 4.0], &[2.0, 5.0], &[3.0, 6.0]];
+        for level in SimdLevel::available() {
+            let mut output = Vec::new();
+            interleave(level, &planes, &mut output);
+            assert_eq!(output, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        }
+    }
+}
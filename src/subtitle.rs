@@ -0,0 +1,439 @@
+use anyhow::{Context, Result};
+use ffmpeg_next as ffmpeg;
+use ffmpeg::codec::subtitle::Rect as SubtitleRect;
+use ffmpeg::format::context::Input;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+use crate::subtitle_file::SubtitleFile;
+use crate::{bitmap_font, player};
+
+// Échelle de la police: un pixel de police pour FONT_SCALE_DIVISOR lignes de la fenêtre
+const FONT_SCALE_DIVISOR: u32 = 120;
+const MIN_FONT_SCALE: u32 = 2;
+// Espace entre deux lignes et autour du texte, en pixels de police
+const LINE_SPACING: u32 = 2;
+const PADDING: u32 = 2;
+// Marge sous les sous-titres, en fraction de la hauteur de la fenêtre
+const BOTTOM_MARGIN_DIVISOR: u32 = 20;
+// Espace entre deux répliques empilées, en pixels de police
+const CUE_SPACING: u32 = 3;
+// Répliques gardées en même temps pour une piste du fichier: au-delà, les plus anciennes sont oubliées
+const MAX_CUES: usize = 8;
+
+const BACKGROUND: Color = Color::RGBA(0, 0, 0, 160);
+const TEXT: Color = Color::RGB(255, 255, 255);
+
+// Piste de sous-titres du fichier
+#[derive(Clone, Debug)]
+pub struct SubtitleTrack {
+    pub index: usize,
+    pub codec: ffmpeg::codec::Id,
+    pub language: Option<String>,
+    // Disposition "forced": seuls les passages à traduire (dialogues en langue étrangère, panneaux)
+    pub forced: bool,
+}
+
+impl SubtitleTrack {
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "Sous-titres {} ({}, {:?})",
+            self.index,
+            self.language.as_deref().unwrap_or("langue inconnue"),
+            self.codec
+        );
+        if self.forced {
+            description.push_str(" [forcés]");
+        }
+        description
+    }
+}
+
+// Réplique affichée entre start et end, en secondes
+#[derive(PartialEq)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    pub lines: Vec<String>,
+}
+
+// Source des sous-titres affichés
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Selection {
+    Off,
+    // Fichier externe (--sub)
+    File,
+    // Position dans tracks d'une piste du fichier
+    Stream(usize),
+}
+
+// Piste de sous-titres active et réplique courante
+pub struct Subtitles {
+    tracks: Vec<SubtitleTrack>,
+    // Proposé avant les pistes du fichier quand la touche S fait défiler les sous-titres
+    file: Option<SubtitleFile>,
+    active: Selection,
+    decoder: Option<ffmpeg::codec::decoder::Subtitle>,
+    time_base: f64,
+    // Répliques décodées de la piste active, par début croissant. Plusieurs peuvent se chevaucher
+    // (locuteur et dialogue, bruitages des sous-titres pour sourds et malentendants)
+    cues: Vec<Cue>,
+    bitmap_warned: bool,
+}
+
+impl Subtitles {
+    // Un fichier externe est affiché d'office, sinon la piste demandée (--forced-subs)
+    pub fn new(
+        ictx: &mut Input,
+        tracks: Vec<SubtitleTrack>,
+        active: Option<usize>,
+        file: Option<SubtitleFile>,
+    ) -> Result<Self> {
+        let active = match active {
+            _ if file.is_some() => Selection::File,
+            Some(position) => Selection::Stream(position),
+            None => Selection::Off,
+        };
+        let mut subtitles = Self {
+            tracks,
+            file,
+            active: Selection::Off,
+            decoder: None,
+            time_base: 0.0,
+            cues: Vec::new(),
+            bitmap_warned: false,
+        };
+        subtitles.select(ictx, active)?;
+        Ok(subtitles)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active != Selection::Off
+    }
+
+    pub fn active_track(&self) -> Option<&SubtitleTrack> {
+        match self.active {
+            Selection::Stream(position) => Some(&self.tracks[position]),
+            _ => None,
+        }
+    }
+
+    pub fn stream_index(&self) -> Option<usize> {
+        self.active_track().map(|track| track.index)
+    }
+
+    pub fn describe(&self) -> String {
+        match (self.active, &self.file) {
+            (Selection::File, Some(file)) => format!("Sous-titres {} ({})", file.path, file.format),
+            (Selection::Stream(position), _) => self.tracks[position].describe(),
+            _ if self.tracks.is_empty() && self.file.is_none() => "Aucune piste de sous-titres".to_string(),
+            _ => "Sous-titres désactivés".to_string(),
+        }
+    }
+
+    // Fichier externe, puis pistes du fichier, et retour à "désactivés" après la dernière
+    pub fn cycle(&mut self, ictx: &mut Input) -> Result<()> {
+        let first_track = (!self.tracks.is_empty()).then_some(Selection::Stream(0));
+        let next = match self.active {
+            Selection::Off if self.file.is_some() => Selection::File,
+            Selection::Off | Selection::File => first_track.unwrap_or(Selection::Off),
+            Selection::Stream(position) if position + 1 < self.tracks.len() => Selection::Stream(position + 1),
+            Selection::Stream(_) => Selection::Off,
+        };
+        self.select(ictx, next)
+    }
+
+    // Après une reconnexion: le nouveau démuxeur a écarté le flux, il est réactivé avec un nouveau décodeur
+    pub fn reopen(&mut self, ictx: &mut Input) -> Result<()> {
+        let active = std::mem::replace(&mut self.active, Selection::Off);
+        self.select(ictx, active)
+    }
+
+    // La réplique affichée appartient à l'ancienne piste: elle est effacée, et seul le flux
+    // de la nouvelle piste est lu par le démuxeur
+    fn select(&mut self, ictx: &mut Input, selection: Selection) -> Result<()> {
+        if let Some(index) = self.stream_index() {
+            player::set_stream_enabled(ictx, index, false);
+        }
+        self.active = Selection::Off;
+        self.decoder = None;
+        self.cues.clear();
+
+        let position = match selection {
+            Selection::Off => return Ok(()),
+            Selection::File => {
+                self.active = Selection::File;
+                return Ok(());
+            }
+            Selection::Stream(position) => position,
+        };
+        let index = self.tracks[position].index;
+        let stream = ictx.stream(index).context("Flux de sous-titres introuvable")?;
+        self.time_base = f64::from(stream.time_base());
+        let context = ffmpeg::codec::Context::from_parameters(stream.parameters())?;
+        let decoder = context
+            .decoder()
+            .subtitle()
+            .with_context(|| format!("Impossible d'ouvrir le décodeur de sous-titres du flux {}", index))?;
+        self.decoder = Some(decoder);
+        player::set_stream_enabled(ictx, index, true);
+        self.active = Selection::Stream(position);
+        Ok(())
+    }
+
+    // Décode un paquet de la piste active, la réplique obtenue s'ajoute à celles encore en cours
+    pub fn push_packet(&mut self, packet: &ffmpeg::Packet) -> Result<()> {
+        let Some(ref mut decoder) = self.decoder else {
+            return Ok(());
+        };
+        let mut subtitle = ffmpeg::Subtitle::new();
+        if !decoder.decode(packet, &mut subtitle)? {
+            return Ok(());
+        }
+
+        let mut lines = Vec::new();
+        for rect in subtitle.rects() {
+            let text = match rect {
+                SubtitleRect::Text(text) => text.get().to_string(),
+                SubtitleRect::Ass(ass) => ass_text(ass.get()),
+                SubtitleRect::Bitmap(_) => {
+                    if !self.bitmap_warned {
+                        println!("Sous-titres image (PGS, DVD...) non pris en charge: seul le texte est affiché");
+                        self.bitmap_warned = true;
+                    }
+                    continue;
+                }
+                SubtitleRect::None(_) => continue,
+            };
+            lines.extend(text.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string));
+        }
+
+        // start() et end() sont en millisecondes, relatifs au PTS du paquet
+        let packet_time = packet.pts().unwrap_or(0) as f64 * self.time_base;
+        let start = packet_time + f64::from(subtitle.start()) / 1000.0;
+        let end = if subtitle.end() > subtitle.start() {
+            packet_time + f64::from(subtitle.end()) / 1000.0
+        } else if packet.duration() > 0 {
+            start + packet.duration() as f64 * self.time_base
+        } else {
+            // Sans durée connue, la réplique reste jusqu'à la suivante
+            f64::INFINITY
+        };
+        // Une réplique vide efface les précédentes
+        if lines.is_empty() {
+            self.cues.clear();
+            return Ok(());
+        }
+        add_cue(&mut self.cues, Cue { start, end, lines });
+        Ok(())
+    }
+
+    // Répliques en cours centrées en bas de la fenêtre, chacune sur un fond semi-transparent.
+    // Elles s'empilent de bas en haut, la plus récente en bas
+    pub fn draw(&self, canvas: &mut Canvas<Window>, time: f64) -> Result<()> {
+        let cues: Vec<&Cue> = match (self.active, &self.file) {
+            (Selection::File, Some(file)) => file.cues_at(time).collect(),
+            _ => self.cues.iter().filter(|cue| cue.start <= time && time < cue.end).collect(),
+        };
+        if cues.is_empty() {
+            return Ok(());
+        }
+        let (width, height) = canvas.output_size().map_err(|e| anyhow::anyhow!(e))?;
+        let scale = (height / FONT_SCALE_DIVISOR).max(MIN_FONT_SCALE);
+
+        let previous_color = canvas.draw_color();
+        for cue_box in layout(&cues, width, height, scale) {
+            draw_cue(canvas, &cue_box, width, scale)?;
+        }
+        canvas.set_draw_color(previous_color);
+        Ok(())
+    }
+}
+
+// Ajoute une réplique d'une piste du fichier à celles en cours. Sont oubliées les répliques
+// terminées, celles sans fin connue (remplacées par la suivante), et celles postérieures à la
+// nouvelle ou identiques à elle, restées d'avant un seek en arrière
+fn add_cue(cues: &mut Vec<Cue>, cue: Cue) {
+    cues.retain(|previous| {
+        previous.start <= cue.start && cue.start < previous.end && previous.end.is_finite() && *previous != cue
+    });
+    if cues.len() == MAX_CUES {
+        cues.remove(0);
+    }
+    cues.push(cue);
+}
+
+// Fond d'une réplique, avec son texte coupé à la largeur de la fenêtre
+struct CueBox {
+    lines: Vec<String>,
+    rect: Rect,
+}
+
+// Fonds des répliques empilés de bas en haut au-dessus de la marge du bas, la plus récente
+// (la dernière) en bas. La hauteur de chacun dépend de son nombre de lignes une fois coupées
+fn layout(cues: &[&Cue], width: u32, height: u32, scale: u32) -> Vec<CueBox> {
+    let padding = PADDING * scale;
+    let line_height = bitmap_font::text_height(scale) + LINE_SPACING * scale;
+    let mut bottom = height as i32 - (height / BOTTOM_MARGIN_DIVISOR) as i32;
+    let mut boxes = Vec::new();
+    for cue in cues.iter().rev() {
+        let lines = wrap_lines(&cue.lines, width.saturating_sub(4 * padding), scale);
+        if lines.is_empty() {
+            continue;
+        }
+        let text_width = lines.iter().map(|line| bitmap_font::text_width(line, scale)).max().unwrap_or(0);
+        let box_width = text_width + 2 * padding;
+        let box_height = lines.len() as u32 * line_height - LINE_SPACING * scale + 2 * padding;
+        let top = bottom - box_height as i32;
+        let rect = Rect::new((width as i32 - box_width as i32) / 2, top, box_width, box_height);
+        boxes.push(CueBox { lines, rect });
+        bottom = top - (CUE_SPACING * scale) as i32;
+    }
+    boxes
+}
+
+fn draw_cue(canvas: &mut Canvas<Window>, cue_box: &CueBox, width: u32, scale: u32) -> Result<()> {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKGROUND);
+    canvas.fill_rect(cue_box.rect).map_err(|e| anyhow::anyhow!(e))?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(TEXT);
+    let line_height = bitmap_font::text_height(scale) + LINE_SPACING * scale;
+    for (row, line) in cue_box.lines.iter().enumerate() {
+        let x = (width as i32 - bitmap_font::text_width(line, scale) as i32) / 2;
+        let y = cue_box.rect.y() + (PADDING * scale + row as u32 * line_height) as i32;
+        bitmap_font::draw_text(canvas, x, y, scale, line)?;
+    }
+    Ok(())
+}
+
+// Texte d'un événement ASS décodé par ffmpeg ("ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text"),
+// sans les balises de style {...}
+fn ass_text(event: &str) -> String {
+    let text = event.splitn(9, ',').nth(8).unwrap_or(event);
+    let mut plain = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for character in text.chars() {
+        match character {
+            '{' => in_tag = true,
+            '}' => in_tag = false,
+            _ if !in_tag => plain.push(character),
+            _ => {}
+        }
+    }
+    plain.replace("\\N", "\n").replace("\\n", "\n").replace("\\h", " ")
+}
+
+// Coupe les lignes trop larges pour la fenêtre entre deux mots
+fn wrap_lines(lines: &[String], max_width: u32, scale: u32) -> Vec<String> {
+    let mut wrapped = Vec::new();
+    for line in lines {
+        let mut current = String::new();
+        for word in line.split_whitespace() {
+            if current.is_empty() {
+                current.push_str(word);
+                continue;
+            }
+            let candidate = format!("{} {}", current, word);
+            if bitmap_font::text_width(&candidate, scale) > max_width {
+                wrapped.push(std::mem::replace(&mut current, word.to_string()));
+            } else {
+                current = candidate;
+            }
+        }
+        if !current.is_empty() {
+            wrapped.push(current);
+        }
+    }
+    wrapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        Cue { start, end, lines: vec![text.to_string()] }
+    }
+
+    fn texts(cues: &[Cue]) -> Vec<&str> {
+        cues.iter().map(|cue| cue.lines[0].as_str()).collect()
+    }
+
+    #[test]
+    fn overlapping_cues_are_kept_together() {
+        let mut cues = Vec::new();
+        add_cue(&mut cues, cue(1.0, 5.0, "MARIE"));
+        add_cue(&mut cues, cue(2.0, 3.0, "Bonjour"));
+        assert_eq!(texts(&cues), ["MARIE", "Bonjour"]);
+    }
+
+    #[test]
+    fn finished_cues_expire() {
+        let mut cues = Vec::new();
+        add_cue(&mut cues, cue(1.0, 2.0, "Avant"));
+        add_cue(&mut cues, cue(2.0, 3.0, "Après"));
+        assert_eq!(texts(&cues), ["Après"]);
+    }
+
+    #[test]
+    fn oldest_cue_is_evicted_beyond_max_cues() {
+        let mut cues = Vec::new();
+        for index in 0..=MAX_CUES {
+            add_cue(&mut cues, cue(index as f64, 100.0, &index.to_string()));
+        }
+        assert_eq!(cues.len(), MAX_CUES);
+        assert_eq!(cues[0].lines[0], "1");
+        assert_eq!(cues[MAX_CUES - 1].lines[0], MAX_CUES.to_string());
+    }
+
+    #[test]
+    fn backward_seek_drops_later_and_repeated_cues() {
+        let mut cues = Vec::new();
+        add_cue(&mut cues, cue(1.0, 50.0, "Longue"));
+        add_cue(&mut cues, cue(10.0, 20.0, "Plus tard"));
+        // Retour à 5 s: la réplique de 10 s n'est pas encore commencée
+        add_cue(&mut cues, cue(5.0, 6.0, "Plus tôt"));
+        assert_eq!(texts(&cues), ["Longue", "Plus tôt"]);
+        // La même réplique décodée une seconde fois n'est pas dupliquée
+        add_cue(&mut cues, cue(5.0, 6.0, "Plus tôt"));
+        assert_eq!(texts(&cues), ["Longue", "Plus tôt"]);
+    }
+
+    #[test]
+    fn open_ended_cue_is_replaced_by_the_next() {
+        let mut cues = Vec::new();
+        add_cue(&mut cues, cue(1.0, f64::INFINITY, "Sans fin"));
+        add_cue(&mut cues, cue(4.0, f64::INFINITY, "Suivante"));
+        assert_eq!(texts(&cues), ["Suivante"]);
+    }
+
+    #[test]
+    fn overlapping_cues_are_stacked_bottom_up() {
+        let (width, height, scale) = (1280, 720, 6);
+        let speaker = cue(1.0, 5.0, "MARIE");
+        let dialogue = Cue { start: 2.0, end: 3.0, lines: vec!["Bonjour".to_string(), "tu es là ?".to_string()] };
+        let boxes = layout(&[&speaker, &dialogue], width, height, scale);
+        assert_eq!(boxes.len(), 2);
+
+        // La plus récente en bas, au-dessus de la marge
+        let (newest, oldest) = (&boxes[0].rect, &boxes[1].rect);
+        assert_eq!(boxes[0].lines, dialogue.lines);
+        assert_eq!(newest.bottom(), (height - height / BOTTOM_MARGIN_DIVISOR) as i32);
+        // Deux lignes: un fond plus haut que celui d'une seule
+        assert!(newest.height() > oldest.height());
+        // L'autre au-dessus, séparée par CUE_SPACING: les fonds ne se recouvrent pas
+        assert_eq!(oldest.bottom(), newest.top() - (CUE_SPACING * scale) as i32);
+    }
+
+    #[test]
+    fn long_lines_wrap_to_the_window() {
+        let scale = 2;
+        let long = cue(0.0, 1.0, &"mot ".repeat(200));
+        let boxes = layout(&[&long], 320, 240, scale);
+        assert!(boxes[0].lines.len() > 1);
+        assert!(boxes[0].rect.width() <= 320);
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::{Context, Result};
+use std::fs;
+use crate::subtitle::Cue;
+
+// Fichier de sous-titres externe (--sub): WebVTT (.vtt) ou SubRip (.srt), lu en entier au démarrage
+pub struct SubtitleFile {
+    pub path: String,
+    pub format: &'static str,
+    // Répliques triées par début. Elles peuvent se chevaucher
+    cues: Vec<Cue>,
+}
+
+impl SubtitleFile {
+    // Le format est reconnu à l'en-tête WEBVTT, sans lui le fichier est lu comme du SRT
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path).with_context(|| format!("Impossible de lire {}", path))?;
+        Self::from_contents(path, &contents)
+    }
+
+    fn from_contents(path: &str, contents: &str) -> Result<Self> {
+        let contents = contents.trim_start_matches('\u{feff}');
+        let webvtt = contents.starts_with("WEBVTT");
+        if !webvtt && path.to_ascii_lowercase().ends_with(".vtt") {
+            anyhow::bail!("{}: en-tête WEBVTT manquant", path);
+        }
+        let mut cues = parse(contents, webvtt).with_context(|| format!("Sous-titres invalides: {}", path))?;
+        if cues.is_empty() {
+            anyhow::bail!("Aucune réplique dans {}", path);
+        }
+        cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+        let format = if webvtt { "WebVTT" } else { "SRT" };
+        println!("Sous-titres {}: {} répliques ({})", path, cues.len(), format);
+        Ok(Self { path: path.to_string(), format, cues })
+    }
+
+    // Répliques en cours, par début croissant
+    pub fn cues_at(&self, time: f64) -> impl Iterator<Item = &Cue> {
+        let started = self.cues.partition_point(|cue| cue.start <= time);
+        self.cues[..started].iter().filter(move |cue| time < cue.end)
+    }
+}
+
+// Répliques séparées par des lignes vides: identifiant facultatif (numéro en SRT), ligne de temps
+// `début --> fin`, puis le texte sur une ou plusieurs lignes
+fn parse(contents: &str, webvtt: bool) -> Result<Vec<Cue>> {
+    let mut cues = Vec::new();
+    let mut block: Vec<(usize, &str)> = Vec::new();
+    for (number, line) in contents.lines().enumerate().chain([(0, "")]) {
+        let line = line.trim_end();
+        if !line.is_empty() {
+            block.push((number + 1, line));
+            continue;
+        }
+        if let Some(cue) = parse_block(&block, webvtt)? {
+            cues.push(cue);
+        }
+        block.clear();
+    }
+    Ok(cues)
+}
+
+fn parse_block(block: &[(usize, &str)], webvtt: bool) -> Result<Option<Cue>> {
+    let Some(&(_, first)) = block.first() else {
+        return Ok(None);
+    };
+    // En-tête et blocs WebVTT sans réplique: commentaires, feuilles de style, régions
+    if webvtt && ["WEBVTT", "NOTE", "STYLE", "REGION"].iter().any(|keyword| first.starts_with(keyword)) {
+        return Ok(None);
+    }
+    // La ligne de temps est la première, ou la deuxième après un identifiant
+    let Some(timing) = block.iter().take(2).position(|(_, line)| line.contains("-->")) else {
+        return Ok(None);
+    };
+    let (number, timing_line) = block[timing];
+    let (start, end) = timing_line.split_once("-->").context("Ligne de temps attendue")?;
+    // Les réglages qui suivent la fin (position, alignement...) sont ignorés
+    let end = end.split_whitespace().next().unwrap_or("");
+    let start = parse_timestamp(start.trim()).with_context(|| format!("ligne {}", number))?;
+    let end = parse_timestamp(end).with_context(|| format!("ligne {}", number))?;
+
+    let lines: Vec<String> = block[timing + 1..]
+        .iter()
+        .map(|(_, line)| cue_text(line))
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.is_empty() || end <= start {
+        return Ok(None);
+    }
+    Ok(Some(Cue { start, end, lines }))
+}
+
+// HH:MM:SS.mmm ou MM:SS.mmm en WebVTT, HH:MM:SS,mmm en SRT
+fn parse_timestamp(value: &str) -> Result<f64> {
+    let invalid = || format!("Horodatage invalide: {}", value);
+    let normalized = value.replace(',', ".");
+    let parts: Vec<&str> = normalized.split(':').collect();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [hours, minutes, seconds] => (*hours, *minutes, *seconds),
+        [minutes, seconds] => ("0", *minutes, *seconds),
+        _ => anyhow::bail!(invalid()),
+    };
+    let hours: u64 = hours.parse().with_context(invalid)?;
+    let minutes: u64 = minutes.parse().with_context(invalid)?;
+    let seconds: f64 = seconds.parse().with_context(invalid)?;
+    if minutes >= 60 || !(0.0..60.0).contains(&seconds) {
+        anyhow::bail!(invalid());
+    }
+    Ok((hours * 3600 + minutes * 60) as f64 + seconds)
+}
+
+// Texte brut d'une ligne: sans balises (<i>, <c.classe>, <v Locuteur>, horodatages internes),
+// entités HTML décodées
+fn cue_text(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for character in line.chars() {
+        match character {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => plain.push(character),
+            _ => {}
+        }
+    }
+    plain
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&lrm;", "")
+        .replace("&rlm;", "")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'a>(cues: impl Iterator<Item = &'a Cue>) -> Vec<String> {
+        cues.map(|cue| cue.lines.join(" / ")).collect()
+    }
+
+    #[test]
+    fn overlapping_cues_from_the_fixture() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/overlapping.vtt");
+        let file = SubtitleFile::load(path).unwrap();
+        assert_eq!(file.format, "WebVTT");
+        assert_eq!(file.cues.len(), 4);
+
+        assert!(texts(file.cues_at(0.5)).is_empty());
+        assert_eq!(texts(file.cues_at(1.2)), ["MARIE"]);
+        // Trois répliques en même temps, par début croissant
+        assert_eq!(
+            texts(file.cues_at(2.2)),
+            ["MARIE", "Bonjour, tu es là ? / Je t'attendais.", "[porte qui claque]"]
+        );
+        // La fin est exclue: à 3 s, seule la première reste
+        assert_eq!(texts(file.cues_at(3.0)), ["MARIE"]);
+        assert_eq!(texts(file.cues_at(4.5)), ["MARIE", "Oui, j&arrive."]);
+        assert_eq!(texts(file.cues_at(5.0)), ["Oui, j&arrive."]);
+        assert!(texts(file.cues_at(6.0)).is_empty());
+    }
+
+    #[test]
+    fn srt_uses_comma_timestamps_and_numbered_cues() {
+        let srt = "1\r\n00:00:01,500 --> 00:00:03,250\r\nBonjour\r\n\r\n2\r\n01:00:00,000 --> 01:00:02,000\r\n<i>Une heure</i>\r\nplus tard\r\n";
+        let file = SubtitleFile::from_contents("film.srt", srt).unwrap();
+        assert_eq!(file.format, "SRT");
+        assert_eq!((file.cues[0].start, file.cues[0].end), (1.5, 3.25));
+        assert_eq!(file.cues[1].start, 3600.0);
+        assert_eq!(file.cues[1].lines, ["Une heure", "plus tard"]);
+    }
+
+    #[test]
+    fn timestamps_with_and_without_hours() {
+        assert_eq!(parse_timestamp("01:02.500").unwrap(), 62.5);
+        assert_eq!(parse_timestamp("00:01:02.500").unwrap(), 62.5);
+        assert_eq!(parse_timestamp("100:00:00.000").unwrap(), 360000.0);
+        for invalid in ["00:60:00.000", "01:60.000", "1.5", "a:00.000", "00:00:00:00.000"] {
+            assert!(parse_timestamp(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn cue_settings_are_ignored() {
+        let vtt = "WEBVTT\n\n00:01.000 --> 00:02.000 position:10% align:start size:50%\nTexte\n";
+        let file = SubtitleFile::from_contents("film.vtt", vtt).unwrap();
+        assert_eq!((file.cues[0].start, file.cues[0].end), (1.0, 2.0));
+    }
+
+    #[test]
+    fn note_style_and_region_blocks_are_skipped() {
+        let vtt = "WEBVTT\n\nREGION\nid:haut\n\nSTYLE\n::cue { color: red }\n\nNOTE une remarque\nsur deux lignes\n\n00:01.000 --> 00:02.000\nTexte\n";
+        let file = SubtitleFile::from_contents("film.vtt", vtt).unwrap();
+        assert_eq!(texts(file.cues.iter()), ["Texte"]);
+    }
+
+    #[test]
+    fn byte_order_mark_is_skipped_before_the_header() {
+        let vtt = "\u{feff}WEBVTT\n\n00:01.000 --> 00:02.000\nTexte\n";
+        assert_eq!(SubtitleFile::from_contents("film.vtt", vtt).unwrap().format, "WebVTT");
+        // Sans en-tête, un .vtt est refusé plutôt que lu comme du SRT
+        assert!(SubtitleFile::from_contents("film.vtt", "00:01.000 --> 00:02.000\nTexte\n").is_err());
+    }
+
+    #[test]
+    fn empty_or_reversed_cues_are_dropped() {
+        let vtt = "WEBVTT\n\n00:02.000 --> 00:02.000\nVide\n\n00:03.000 --> 00:01.000\nÀ l'envers\n\n00:04.000 --> 00:05.000\nGardée\n";
+        let file = SubtitleFile::from_contents("film.vtt", vtt).unwrap();
+        assert_eq!(texts(file.cues.iter()), ["Gardée"]);
+        // Aucune réplique valide: erreur
+        assert!(SubtitleFile::from_contents("film.vtt", "WEBVTT\n\n00:02.000 --> 00:01.000\nTexte\n").is_err());
+    }
+
+    #[test]
+    fn invalid_timestamp_reports_its_line() {
+        let result = SubtitleFile::from_contents("film.vtt", "WEBVTT\n\n00:01.000 --> 00:0x.000\nTexte\n");
+        let error = result.err().unwrap();
+        assert!(format!("{:#}", error).contains("ligne 3"));
+    }
+}
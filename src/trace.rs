@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+// Nombre de lignes écrites entre deux flush, pour pouvoir suivre le fichier pendant la lecture
+const FLUSH_INTERVAL: usize = 120;
+
+// Une ligne par frame décodée, temps en millisecondes depuis l'ouverture de la trace
+pub struct TraceRow {
+    pub frame_index: u64,
+    pub pts: i64,
+    pub pts_seconds: f64,
+    pub scheduled: Option<Instant>,
+    pub displayed_at: Option<Instant>,
+    pub audio_clock: Option<f64>,
+}
+
+// Trace CSV du cadencement: l'écriture se fait dans un thread dédié pour ne pas bloquer le rendu
+pub struct FrameTrace {
+    start: Instant,
+    sender: Option<Sender<String>>,
+    worker: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl FrameTrace {
+    pub fn create(path: &str) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Impossible de créer {}", path))?;
+        let (sender, receiver) = mpsc::channel::<String>();
+
+        let worker = std::thread::spawn(move || {
+            let mut writer = BufWriter::new(file);
+            writeln!(
+                writer,
+                "frame,pts,pts_seconds,scheduled_ms,displayed_ms,displayed,audio_clock"
+            )?;
+            for (count, line) in receiver.iter().enumerate() {
+                writeln!(writer, "{}", line)?;
+                if count % FLUSH_INTERVAL == 0 {
+                    writer.flush()?;
+                }
+            }
+            writer.flush()
+        });
+
+        Ok(Self {
+            start: Instant::now(),
+            sender: Some(sender),
+            worker: Some(worker),
+        })
+    }
+
+    pub fn record(&self, row: TraceRow) {
+        let millis = |instant: Option<Instant>| {
+            instant
+                .map(|instant| format!("{:.3}", instant.saturating_duration_since(self.start).as_secs_f64() * 1000.0))
+                .unwrap_or_default()
+        };
+        let line = format!(
+            "{},{},{:.6},{},{},{},{}",
+            row.frame_index,
+            row.pts,
+            row.pts_seconds,
+            millis(row.scheduled),
+            millis(row.displayed_at),
+            u8::from(row.displayed_at.is_some()),
+            row.audio_clock.map(|clock| format!("{:.6}", clock)).unwrap_or_default()
+        );
+        if let Some(ref sender) = self.sender {
+            let _ = sender.send(line);
+        }
+    }
+}
+
+impl Drop for FrameTrace {
+    fn drop(&mut self) {
+        // Fermer le canal termine la boucle du thread, qui vide alors son buffer
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            match worker.join() {
+                Ok(Err(e)) => eprintln!("Erreur d'écriture de la trace CSV: {}", e),
+                Err(_) => eprintln!("Le thread d'écriture de la trace CSV a paniqué"),
+                Ok(Ok(())) => {}
+            }
+        }
+    }
+}
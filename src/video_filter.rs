@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use ffmpeg_next as ffmpeg;
+use ffmpeg::filter::{Filter, Graph};
+use ffmpeg::format::Pixel;
+use ffmpeg::util::frame::video::Video;
+use ffmpeg::Rescale;
+
+use crate::decoder::MIN_VIDEO_DIMENSION;
+
+// Chaîne de filtres ffmpeg appliquée aux frames décodées avant le scaler (--vf, --interpolate).
+// Une frame poussée peut en donner zéro, une ou plusieurs: elles sont récupérées une à une avec pull()
+pub struct VideoFilter {
+    // Chaîne au format de ffmpeg -vf, ex: "hflip,eq=contrast=1.2"
+    spec: String,
+    // Time base du flux, dans laquelle les PTS produits sont ramenés pour le cadencement
+    time_base: ffmpeg::Rational,
+    graph: Option<Graph>,
+    // Format et taille des frames pour lesquelles le graphe a été construit
+    input: Option<(Pixel, u32, u32)>,
+    // Taille des images produites pour la taille du flux: crop, scale ou transpose la changent
+    output_size: (u32, u32),
+    finished: bool,
+}
+
+impl VideoFilter {
+    // La chaîne est vérifiée tout de suite sur un graphe d'essai aux dimensions du flux, pour qu'une
+    // faute de frappe soit signalée au démarrage plutôt qu'à la première frame
+    pub fn new(spec: &str, time_base: ffmpeg::Rational, width: u32, height: u32) -> Result<Self> {
+        let mut filter = Self {
+            spec: spec.to_string(),
+            time_base,
+            graph: None,
+            input: None,
+            output_size: (width, height),
+            finished: false,
+        };
+        let mut graph = filter
+            .build(Pixel::YUV420P, width, height, ffmpeg::Rational::new(1, 1))
+            .with_context(|| format!("Chaîne de filtres vidéo invalide: {}", spec))?;
+        let output = graph.get("out").context("Sortie du filtre introuvable")?;
+        // ffmpeg-next n'expose pas la taille négociée en sortie du buffersink
+        let output_width = unsafe { ffmpeg::ffi::av_buffersink_get_w(output.as_ptr()) };
+        let output_height = unsafe { ffmpeg::ffi::av_buffersink_get_h(output.as_ptr()) };
+        if output_width < MIN_VIDEO_DIMENSION as i32 || output_height < MIN_VIDEO_DIMENSION as i32 {
+            anyhow::bail!("La chaîne de filtres {} produit des images de {}x{}", spec, output_width, output_height);
+        }
+        filter.output_size = (output_width as u32, output_height as u32);
+        Ok(filter)
+    }
+
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    pub fn output_size(&self) -> (u32, u32) {
+        self.output_size
+    }
+
+    // Le graphe est construit à la première frame, et reconstruit si le format ou la taille change:
+    // en décodage matériel, le format n'est connu qu'après le rapatriement
+    pub fn push(&mut self, frame: &Video) -> Result<()> {
+        let input = (frame.format(), frame.width(), frame.height());
+        if self.input != Some(input) {
+            let sar = frame.aspect_ratio();
+            let sar = if sar.numerator() > 0 && sar.denominator() > 0 { sar } else { ffmpeg::Rational::new(1, 1) };
+            self.graph = Some(self.build(frame.format(), frame.width(), frame.height(), sar)?);
+            self.input = Some(input);
+            self.finished = false;
+        }
+        if let Some(ref mut graph) = self.graph {
+            graph.get("in").context("Entrée du filtre introuvable")?.source().add(frame)?;
+        }
+        Ok(())
+    }
+
+    // Fin du flux: le filtre rend ses dernières images
+    pub fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        if let Some(ref mut graph) = self.graph {
+            graph.get("in").context("Entrée du filtre introuvable")?.source().flush()?;
+        }
+        Ok(())
+    }
+
+    // Image suivante, avec un PTS dans la time base du flux. false s'il faut d'abord une autre frame
+    pub fn pull(&mut self, frame: &mut Video) -> Result<bool> {
+        let Some(ref mut graph) = self.graph else {
+            return Ok(false);
+        };
+        let mut output = graph.get("out").context("Sortie du filtre introuvable")?;
+        let mut sink = output.sink();
+        match sink.frame(frame) {
+            Ok(()) => {
+                let sink_time_base = sink.time_base();
+                let pts = frame.pts().map(|pts| pts.rescale(sink_time_base, self.time_base));
+                frame.set_pts(pts);
+                Ok(true)
+            }
+            Err(ffmpeg::Error::Other { errno: ffmpeg::error::EAGAIN }) | Err(ffmpeg::Error::Eof) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Après un seek: les images en attente dans le filtre appartiennent à l'ancienne position
+    pub fn reset(&mut self) {
+        self.graph = None;
+        self.input = None;
+        self.finished = false;
+    }
+
+    fn build(&self, format: Pixel, width: u32, height: u32, sar: ffmpeg::Rational) -> Result<Graph> {
+        let pixel_format = format
+            .descriptor()
+            .with_context(|| format!("Format de frame inconnu: {:?}", format))?
+            .name();
+        let args = format!(
+            "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+            width,
+            height,
+            pixel_format,
+            self.time_base.numerator(),
+            self.time_base.denominator(),
+            sar.numerator(),
+            sar.denominator()
+        );
+
+        let mut graph = Graph::new();
+        graph.add(&ffmpeg::filter::find("buffer").context("Filtre buffer indisponible")?, "in", &args)?;
+        graph.add(
+            &ffmpeg::filter::find("buffersink").context("Filtre buffersink indisponible")?,
+            "out",
+            "",
+        )?;
+        // Le format de sortie reste libre: le scaler s'adapte à celui que produit la chaîne
+        graph.output("in", 0)?.input("out", 0)?.parse(&self.spec)?;
+        graph.validate()?;
+        Ok(graph)
+    }
+}
+
+// Filtres vidéo disponibles dans la version de ffmpeg liée, pour --list-filters
+pub fn print_video_filters() {
+    println!("Filtres vidéo disponibles:");
+    let mut opaque = std::ptr::null_mut();
+    loop {
+        let ptr = unsafe { ffmpeg::ffi::av_filter_iterate(&mut opaque) };
+        if ptr.is_null() {
+            break;
+        }
+        let filter = unsafe { Filter::wrap(ptr as *mut _) };
+        // Les sources (testsrc...) et les filtres audio sont ignorés: seuls comptent ceux qui prennent
+        // et rendent de la vidéo
+        let video_pads = |pads: Option<ffmpeg::filter::filter::PadIter>| {
+            pads.is_some_and(|mut pads| pads.any(|pad| pad.medium() == ffmpeg::media::Type::Video))
+        };
+        if video_pads(filter.inputs()) && video_pads(filter.outputs()) {
+            println!("  {:<20} {}", filter.name(), filter.description().unwrap_or(""));
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::Result;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+// Échantillons mono conservés pour l'affichage, de quoi remplir une FFT
+pub const HISTORY_SIZE: usize = 2048;
+const FFT_SIZE: usize = HISTORY_SIZE;
+const BAR_COUNT: usize = 48;
+// Plage affichée par le spectre, en dB
+const MIN_DB: f32 = -80.0;
+// Décroissance des barres d'une image à l'autre, pour un rendu moins nerveux
+const BAR_DECAY: f32 = 0.85;
+// Fréquence la plus basse représentée, en fraction de la bande utile
+const MIN_BAND: f32 = 1.0 / 512.0;
+
+const BACKGROUND: Color = Color::RGB(0, 0, 0);
+const FOREGROUND: Color = Color::RGB(80, 200, 255);
+
+// Derniers échantillons joués, copiés par le callback audio sans jamais l'attendre (try_lock)
+pub type SampleHistory = Arc<Mutex<VecDeque<f32>>>;
+
+pub fn new_history() -> SampleHistory {
+    Arc::new(Mutex::new(VecDeque::with_capacity(HISTORY_SIZE)))
+}
+
+// Ajoute les frames entrelacées à l'historique, ramenées en mono
+pub fn record(history: &SampleHistory, samples: &[f32], channels: usize) {
+    let Ok(mut history) = history.try_lock() else {
+        return;
+    };
+    for frame in samples.chunks_exact(channels.max(1)) {
+        if history.len() == HISTORY_SIZE {
+            history.pop_front();
+        }
+        history.push_back(frame.iter().sum::<f32>() / frame.len() as f32);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisualizationMode {
+    Off,
+    Spectrum,
+    Waveform,
+}
+
+impl VisualizationMode {
+    fn next(self) -> Self {
+        match self {
+            VisualizationMode::Off => VisualizationMode::Spectrum,
+            VisualizationMode::Spectrum => VisualizationMode::Waveform,
+            VisualizationMode::Waveform => VisualizationMode::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VisualizationMode::Off => "aucune",
+            VisualizationMode::Spectrum => "spectre",
+            VisualizationMode::Waveform => "forme d'onde",
+        }
+    }
+}
+
+// Spectre ou forme d'onde pour la lecture d'un fichier audio seul
+pub struct Visualizer {
+    pub mode: VisualizationMode,
+    history: SampleHistory,
+    fft: Arc<dyn Fft<f32>>,
+    // Fenêtre de Hann, limite les fuites spectrales
+    window: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    levels: Vec<f32>,
+}
+
+impl Visualizer {
+    pub fn new(history: SampleHistory) -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        Self {
+            mode: VisualizationMode::Off,
+            history,
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window,
+            spectrum: vec![Complex::default(); FFT_SIZE],
+            levels: vec![0.0; BAR_COUNT],
+        }
+    }
+
+    pub fn cycle_mode(&mut self) {
+        self.mode = self.mode.next();
+        self.levels.iter_mut().for_each(|level| *level = 0.0);
+    }
+
+    // Redessine toute la fenêtre, sans la présenter: l'appelant peut encore y ajouter un overlay
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>) -> Result<()> {
+        // Copie locale: le verrou n'est gardé que le temps de la copie
+        let samples: Vec<f32> = match self.history.lock() {
+            Ok(history) => history.iter().copied().collect(),
+            Err(_) => return Ok(()),
+        };
+
+        canvas.set_draw_color(BACKGROUND);
+        canvas.clear();
+        canvas.set_draw_color(FOREGROUND);
+        match self.mode {
+            VisualizationMode::Off => {}
+            VisualizationMode::Spectrum => self.draw_spectrum(canvas, &samples)?,
+            VisualizationMode::Waveform => draw_waveform(canvas, &samples)?,
+        }
+        Ok(())
+    }
+
+    fn draw_spectrum(&mut self, canvas: &mut Canvas<Window>, samples: &[f32]) -> Result<()> {
+        if samples.len() < FFT_SIZE {
+            return Ok(());
+        }
+        for ((bin, &sample), &weight) in self.spectrum.iter_mut().zip(samples).zip(&self.window) {
+            *bin = Complex::new(sample * weight, 0.0);
+        }
+        self.fft.process(&mut self.spectrum);
+
+        // Bandes espacées logarithmiquement sur la moitié utile du spectre
+        let half = FFT_SIZE / 2;
+        let scale = 2.0 / FFT_SIZE as f32;
+        for (bar, level) in self.levels.iter_mut().enumerate() {
+            let start = band_edge(bar, half);
+            let end = band_edge(bar + 1, half).max(start + 1);
+            let peak = self.spectrum[start..end]
+                .iter()
+                .map(|bin| bin.norm() * scale)
+                .fold(0.0f32, f32::max);
+            let db = 20.0 * peak.max(1e-9).log10();
+            let normalized = ((db - MIN_DB) / -MIN_DB).clamp(0.0, 1.0);
+            *level = normalized.max(*level * BAR_DECAY);
+        }
+
+        let (width, height) = canvas.output_size().map_err(|e| anyhow::anyhow!(e))?;
+        let bar_width = (width / BAR_COUNT as u32).max(1);
+        let rects: Vec<Rect> = self
+            .levels
+            .iter()
+            .enumerate()
+            .filter(|(_, &level)| level > 0.0)
+            .map(|(bar, &level)| {
+                let bar_height = ((level * height as f32) as u32).max(1);
+                Rect::new(
+                    (bar as u32 * bar_width) as i32 + 1,
+                    (height - bar_height) as i32,
+                    bar_width.saturating_sub(2).max(1),
+                    bar_height,
+                )
+            })
+            .collect();
+        canvas.fill_rects(&rects).map_err(|e| anyhow::anyhow!(e))
+    }
+}
+
+// Premier bin de la bande `bar`
+fn band_edge(bar: usize, half: usize) -> usize {
+    let fraction = MIN_BAND * (1.0 / MIN_BAND).powf(bar as f32 / BAR_COUNT as f32);
+    ((fraction * half as f32) as usize).clamp(1, half)
+}
+
+fn draw_waveform(canvas: &mut Canvas<Window>, samples: &[f32]) -> Result<()> {
+    if samples.len() < 2 {
+        return Ok(());
+    }
+    let (width, height) = canvas.output_size().map_err(|e| anyhow::anyhow!(e))?;
+    let middle = height as f32 / 2.0;
+    let points: Vec<Point> = samples
+        .iter()
+        .enumerate()
+        .map(|(index, &sample)| {
+            let x = index as f32 * width as f32 / (samples.len() - 1) as f32;
+            let y = middle - sample.clamp(-1.0, 1.0) * middle;
+            Point::new(x as i32, y as i32)
+        })
+        .collect();
+    canvas.draw_lines(points.as_slice()).map_err(|e| anyhow::anyhow!(e))
+}
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+use ffmpeg::ffi;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+use std::os::raw::{c_int, c_void};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use crate::bitmap_font;
+
+// Blocage prolongé au-delà de GIVE_UP_FACTOR délais: reconnexion en réseau, erreur en local
+const GIVE_UP_FACTOR: u32 = 3;
+// Fréquence de rafraîchissement de l'avertissement pendant le blocage
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+const FONT_SCALE: u32 = 3;
+const PADDING: i32 = 8;
+const BACKGROUND: Color = Color::RGBA(0, 0, 0, 180);
+const TEXT: Color = Color::RGB(240, 200, 40);
+const MESSAGE: &str = "Lecture bloquée - en attente de données";
+
+// État partagé avec le callback d'interruption de ffmpeg, appelé depuis les lectures bloquantes:
+// délai en millisecondes (0 = désactivé) et début de la lecture en cours (0 = aucune)
+static READ_TIMEOUT_MS: AtomicU64 = AtomicU64::new(0);
+static READ_STARTED_MS: AtomicU64 = AtomicU64::new(0);
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+// Millisecondes depuis le premier appel, jamais 0 pour distinguer « aucune lecture en cours »
+fn now_ms() -> u64 {
+    EPOCH.get_or_init(Instant::now).elapsed().as_millis() as u64 + 1
+}
+
+pub fn set_read_timeout(timeout: Option<Duration>) {
+    let timeout_ms = timeout.map_or(0, |timeout| timeout.as_millis().max(1) as u64);
+    READ_TIMEOUT_MS.store(timeout_ms, Ordering::Relaxed);
+}
+
+// Callback à installer dans l'AVFormatContext avant son ouverture: ffmpeg le consulte pendant
+// les attentes réseau et abandonne la lecture (AVERROR_EXIT) quand il renvoie 1
+pub fn interrupt_callback() -> ffi::AVIOInterruptCB {
+    ffi::AVIOInterruptCB {
+        callback: Some(interrupt),
+        opaque: std::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn interrupt(_opaque: *mut c_void) -> c_int {
+    let timeout_ms = READ_TIMEOUT_MS.load(Ordering::Relaxed);
+    let started_ms = READ_STARTED_MS.load(Ordering::Relaxed);
+    (timeout_ms > 0 && started_ms > 0 && now_ms().saturating_sub(started_ms) > timeout_ms) as c_int
+}
+
+// Délimite une lecture bloquante surveillée: ouverture de l'entrée, lecture d'un paquet
+pub fn guard<T>(read: impl FnOnce() -> T) -> T {
+    READ_STARTED_MS.store(now_ms(), Ordering::Relaxed);
+    let result = read();
+    READ_STARTED_MS.store(0, Ordering::Relaxed);
+    result
+}
+
+// Surveillance de la lecture (--read-timeout): temps écoulé depuis la dernière frame produite
+pub struct Watchdog {
+    timeout: Option<Duration>,
+    last_progress: Instant,
+    // Avertissement déjà signalé pour le blocage en cours
+    warned: bool,
+    last_redraw: Option<Instant>,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            last_progress: Instant::now(),
+            warned: false,
+            last_redraw: None,
+        }
+    }
+
+    // Une frame vidéo ou audio vient d'être produite
+    pub fn progress(&mut self) {
+        if self.warned {
+            println!("Données reçues à nouveau après {:.1}s", self.last_progress.elapsed().as_secs_f64());
+        }
+        self.reset();
+    }
+
+    // Pause, seek, reconnexion: l'attente qui suit n'est pas un blocage
+    pub fn reset(&mut self) {
+        self.last_progress = Instant::now();
+        self.warned = false;
+        self.last_redraw = None;
+    }
+
+    // Durée du blocage en cours, None tant que le délai n'est pas dépassé
+    pub fn stalled_for(&mut self) -> Option<Duration> {
+        let timeout = self.timeout?;
+        let stalled_for = self.last_progress.elapsed();
+        if stalled_for < timeout {
+            return None;
+        }
+        if !self.warned {
+            self.warned = true;
+            println!("Lecture bloquée: aucune frame depuis {} ms, en attente de données", stalled_for.as_millis());
+        }
+        Some(stalled_for)
+    }
+
+    // Blocage persistant: il faut se reconnecter ou abandonner
+    pub fn gave_up(&self) -> bool {
+        self.timeout
+            .is_some_and(|timeout| self.last_progress.elapsed() >= timeout * GIVE_UP_FACTOR)
+    }
+
+    // L'avertissement n'est redessiné que quelques fois par seconde
+    pub fn should_redraw(&mut self) -> bool {
+        if self.last_redraw.is_some_and(|last| last.elapsed() < REDRAW_INTERVAL) {
+            return false;
+        }
+        self.last_redraw = Some(Instant::now());
+        true
+    }
+}
+
+// Bandeau centré par-dessus la dernière image, tant que les données n'arrivent plus
+pub fn draw(canvas: &mut Canvas<Window>, stalled_for: Duration) -> Result<()> {
+    let text = format!("{} ({}s)", MESSAGE, stalled_for.as_secs());
+    let text_width = bitmap_font::text_width(&text, FONT_SCALE);
+    let width = text_width + 2 * PADDING as u32;
+    let height = bitmap_font::text_height(FONT_SCALE) + 2 * PADDING as u32;
+    let (canvas_width, canvas_height) = canvas.output_size().map_err(|e| anyhow::anyhow!(e))?;
+    let x = (canvas_width as i32 - width as i32) / 2;
+    let y = (canvas_height as i32 - height as i32) / 2;
+
+    // Même précaution que fps_overlay: la couleur courante sert à canvas.clear()
+    let previous_color = canvas.draw_color();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKGROUND);
+    canvas
+        .fill_rect(Rect::new(x, y, width, height))
+        .map_err(|e| anyhow::anyhow!(e))?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(TEXT);
+    bitmap_font::draw_text(canvas, x + PADDING, y + PADDING, FONT_SCALE, &text)?;
+    canvas.set_draw_color(previous_color);
+    Ok(())
+}
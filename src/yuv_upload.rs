@@ -0,0 +1,136 @@
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+use ffmpeg::format::Pixel;
+use ffmpeg::util::frame::video::Video;
+use sdl2::render::Texture;
+use sdl2::sys;
+use std::os::raw::c_int;
+use std::ptr;
+
+// Envoie une frame YUV420P dans une texture IYUV de même taille. Chaque plan est vérifié avant
+// l'envoi: une frame incohérente (plan trop court, pas de ligne trop petit, autre format) est
+// ignorée avec un message au lieu d'interrompre la lecture ou de faire lire SDL hors du buffer.
+// Renvoie false si la frame n'a pas été envoyée
+pub fn upload(texture: &mut Texture, frame: &Video) -> Result<bool> {
+    let query = texture.query();
+    if frame.format() != Pixel::YUV420P {
+        eprintln!("Frame ignorée: format {:?} au lieu de YUV420P", frame.format());
+        return Ok(false);
+    }
+    if frame.width() != query.width || frame.height() != query.height {
+        eprintln!(
+            "Frame ignorée: {}x{} pour une texture {}x{}",
+            frame.width(),
+            frame.height(),
+            query.width,
+            query.height
+        );
+        return Ok(false);
+    }
+
+    let planes = [0, 1, 2].map(|index| (frame.data(index), frame.stride(index)));
+    let sizes = planes.map(|(data, stride)| (data.len(), stride));
+    if let Err(reason) = check_planes(query.width as usize, query.height as usize, sizes) {
+        eprintln!("Frame ignorée: {}", reason);
+        return Ok(false);
+    }
+
+    // Texture::update_yuv de sdl2 0.35 exige pas * hauteur / 2 octets de chroma, une ligne de moins
+    // que ce que SDL lit pour une hauteur impaire: appel direct à SDL, les plans venant d'être vérifiés
+    let [(y, y_pitch), (u, u_pitch), (v, v_pitch)] = planes;
+    let ret = unsafe {
+        sys::SDL_UpdateYUVTexture(
+            texture.raw(),
+            ptr::null(),
+            y.as_ptr(),
+            y_pitch as c_int,
+            u.as_ptr(),
+            u_pitch as c_int,
+            v.as_ptr(),
+            v_pitch as c_int,
+        )
+    };
+    if ret != 0 {
+        anyhow::bail!("Impossible de mettre à jour la texture YUV: {}", sdl2::get_error());
+    }
+    Ok(true)
+}
+
+// Vérifie les plans (longueur, pas) d'une frame YUV420P de width x height. Comme SDL, la chroma
+// est lue sur (height + 1) / 2 lignes de (width + 1) / 2 octets: une dimension impaire garde sa
+// dernière ligne et sa dernière colonne. La dernière ligne d'un plan peut s'arrêter à la fin de
+// ses pixels, sans le remplissage du pas
+fn check_planes(width: usize, height: usize, planes: [(usize, usize); 3]) -> Result<(), String> {
+    let chroma = (width.div_ceil(2), height.div_ceil(2));
+    let dimensions = [(width, height), chroma, chroma];
+    for (index, ((len, stride), (row_bytes, rows))) in planes.into_iter().zip(dimensions).enumerate() {
+        if stride < row_bytes {
+            return Err(format!("pas de {} octets pour une ligne de {} sur le plan {}", stride, row_bytes, index));
+        }
+        let needed = stride * rows.saturating_sub(1) + row_bytes;
+        if len < needed {
+            return Err(format!("plan {} de {} octets, {} attendus", index, len, needed));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plans tels qu'ffmpeg les alloue: pas aligné sur 32 octets, hauteur de chroma arrondie vers le haut
+    fn planes(width: usize, height: usize) -> [(usize, usize); 3] {
+        let luma_stride = width.next_multiple_of(32);
+        let chroma_stride = width.div_ceil(2).next_multiple_of(32);
+        let chroma = (chroma_stride * height.div_ceil(2), chroma_stride);
+        [(luma_stride * height, luma_stride), chroma, chroma]
+    }
+
+    #[test]
+    fn accepts_even_and_odd_dimensions() {
+        for (width, height) in [(1920, 1080), (720, 576), (853, 481), (1, 1)] {
+            assert_eq!(check_planes(width, height, planes(width, height)), Ok(()), "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn odd_height_needs_the_last_chroma_row() {
+        // 853x481: 241 lignes de chroma. Un plan arrêté à 240 lignes ferait lire SDL hors du buffer
+        let mut planes = planes(853, 481);
+        let (_, stride) = planes[2];
+        planes[2].0 = stride * 240;
+        assert_eq!(check_planes(853, 481, planes), Err(format!("plan 2 de {} octets, {} attendus", stride * 240, stride * 240 + 427)));
+    }
+
+    #[test]
+    fn last_row_may_stop_at_its_pixels() {
+        // Pas de 768 et 384 octets: chaque plan s'arrête juste après les pixels de sa dernière ligne
+        let planes = [(768 * 575 + 720, 768), (384 * 287 + 360, 384), (384 * 287 + 360, 384)];
+        assert_eq!(check_planes(720, 576, planes), Ok(()));
+        for index in 0..3 {
+            let mut short = planes;
+            short[index].0 -= 1;
+            assert!(check_planes(720, 576, short).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_a_stride_smaller_than_a_row() {
+        let mut planes = planes(720, 576);
+        planes[1].1 = 359;
+        assert_eq!(check_planes(720, 576, planes), Err("pas de 359 octets pour une ligne de 360 sur le plan 1".to_string()));
+        // Pas plus petit qu'une ligne mais plan assez long: refusé quand même
+        let planes = [(720 * 576, 700), (360 * 288, 360), (360 * 288, 360)];
+        assert!(check_planes(720, 576, planes).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_planes() {
+        for index in 0..3 {
+            let mut planes = planes(720, 576);
+            planes[index].0 /= 2;
+            assert!(check_planes(720, 576, planes).unwrap_err().starts_with(&format!("plan {} ", index)));
+        }
+    }
+}